@@ -24,7 +24,7 @@ const configTestnet = {
 const config = process.env.NEAR_ENV == 'testnet' ? configTestnet : configSandbox;
 
 const methods = {
-  viewMethods: ['version'],
+  viewMethods: ['staged_code_hash'],
 };
 
 (async function () {
@@ -47,16 +47,17 @@ const methods = {
   const wasm = await fs.readFile(config.contractPath);
   const account = new nearAPI.Account(near.connection, config.accountId);
 
-  // Update the contract.
+  // Stage the code, `commit_upgrade` deploys it after the timelock.
   await account.signAndSendTransaction({
     receiverId: config.contractId,
     actions: [
-      nearAPI.transactions.functionCall('upgrade', wasm, 100000000000000, '0'),
+      nearAPI.transactions.functionCall('stage_code', wasm, 100000000000000, '0'),
     ],
   });
 
-  // Check that the contract has been upgraded.
-  // Change the `version` method returning 'UPGRADED:VERSION' to test this.
+  // Check that the code has been staged.
   const contract = new nearAPI.Contract(account, config.contractId, methods);
-  assert.equal(await contract.version(), 'UPGRADED:VERSION');
+  const hash = await contract.staged_code_hash();
+  assert.ok(hash);
+  console.log(`Staged code ${hash}, commit it with commit_upgrade after the timelock`);
 })();
@@ -0,0 +1,114 @@
+//! Bounded log of administrative calls for post-incident forensics.
+//!
+//! Calls are recorded by the owner, guardian and operator checks, which take
+//! `&self`, so the log lives outside of the contract state: entries under
+//! `StorageKey::AdminLog` and the length under a raw storage key. Only calls
+//! which didn't fail are kept, as a panic reverts the record.
+
+use std::convert::TryInto;
+
+use crate::*;
+
+use near_sdk::json_types::Base58CryptoHash;
+use near_sdk::CryptoHash;
+
+/// Number of the latest calls kept in the log.
+const ADMIN_LOG_CAPACITY: u64 = 500;
+const DEFAULT_ADMIN_LOG_LIMIT: u64 = 50;
+const ADMIN_LOG_LEN_KEY: &[u8] = b"ADMIN_LOG_LEN";
+
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(crate = "near_sdk::serde")]
+pub struct AdminLogEntry {
+    pub index: U64,
+    pub timestamp: U64,
+    pub method: String,
+    pub caller_id: AccountId,
+    /// SHA-256 of the JSON arguments.
+    pub args_hash: Base58CryptoHash,
+}
+
+fn entries() -> LookupMap<u64, AdminLogEntry> {
+    LookupMap::new(StorageKey::AdminLog)
+}
+
+/// Number of calls ever recorded, i.e. the index of the next one.
+fn len() -> u64 {
+    env::storage_read(ADMIN_LOG_LEN_KEY).map_or(0, |data| {
+        u64::from_le_bytes(data.try_into().expect("Invalid admin log length"))
+    })
+}
+
+/// Records the call of `method` by the predecessor.
+pub(crate) fn record(method: &str) {
+    let index = len();
+    let args_hash: CryptoHash = env::sha256(&env::input().unwrap_or_default())
+        .try_into()
+        .unwrap();
+    let entry = AdminLogEntry {
+        index: index.into(),
+        timestamp: env::block_timestamp().into(),
+        method: method.to_string(),
+        caller_id: env::predecessor_account_id(),
+        args_hash: args_hash.into(),
+    };
+    entries().insert(&(index % ADMIN_LOG_CAPACITY), &entry);
+    env::storage_write(ADMIN_LOG_LEN_KEY, &(index + 1).to_le_bytes());
+}
+
+#[near_bindgen]
+impl Contract {
+    /// Returns the administrative calls starting from the `from` index (the oldest by default).
+    pub fn get_admin_log(&self, from: Option<U64>, limit: Option<u64>) -> Vec<AdminLogEntry> {
+        let len = len();
+        let from = from
+            .map_or(0, |from| from.0)
+            .max(len.saturating_sub(ADMIN_LOG_CAPACITY));
+        let to = len.min(from.saturating_add(limit.unwrap_or(DEFAULT_ADMIN_LOG_LIMIT)));
+        let entries = entries();
+        (from..to)
+            .map(|index| entries.get(&(index % ADMIN_LOG_CAPACITY)).unwrap())
+            .collect()
+    }
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use super::*;
+    use crate::testing::setup_contract;
+    use near_sdk::test_utils::accounts;
+    use near_sdk::testing_env;
+
+    #[test]
+    fn test_admin_log() {
+        let (mut context, mut contract) = setup_contract(accounts(1));
+        contract.set_backing_floor(Some(9000));
+        contract.extend_guardians(vec![accounts(2)]);
+
+        testing_env!(context.predecessor_account_id(accounts(2)).build());
+        contract.cancel_staged_code();
+
+        let log = contract.get_admin_log(None, None);
+        assert_eq!(log.len(), 3);
+        assert_eq!(log[0].method, "set_backing_floor");
+        assert_eq!(log[0].caller_id, accounts(1));
+        assert_eq!(log[2].index, U64(2));
+        assert_eq!(log[2].method, "cancel_staged_code");
+        assert_eq!(log[2].caller_id, accounts(2));
+        assert_eq!(
+            contract.get_admin_log(Some(U64(1)), Some(1))[0].method,
+            "extend_guardians"
+        );
+    }
+
+    #[test]
+    fn test_admin_log_ring_buffer() {
+        let (_, contract) = setup_contract(accounts(0));
+        for _ in 0..ADMIN_LOG_CAPACITY + 5 {
+            record("pause");
+        }
+        let log = contract.get_admin_log(None, Some(ADMIN_LOG_CAPACITY * 2));
+        assert_eq!(log.len() as u64, ADMIN_LOG_CAPACITY);
+        assert_eq!(log[0].index, U64(5));
+    }
+}
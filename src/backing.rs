@@ -0,0 +1,112 @@
+//! Backing of USN by the treasury reserves.
+//!
+//! Stable reserves are the tracked deposits minus withdrawals rather than queried
+//! FT balances, and NEAR of the contract is valued at the last oracle report.
+//! Operations minting unbacked USN log `under_collateralized` below the floor.
+
+use crate::*;
+
+/// 100% in basis points.
+const FULL_BACKING: u128 = 10_000;
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+#[serde(crate = "near_sdk::serde")]
+pub struct BackingRatio {
+    pub total_supply: U128,
+    /// USN value of the stable reserves.
+    pub stable_reserve: U128,
+    /// USN value of the NEAR balance not locked for the storage.
+    pub near_reserve: U128,
+    /// Reserves to the total supply in basis points, `None` without supply.
+    pub ratio: Option<u32>,
+    pub floor: Option<u32>,
+}
+
+#[near_bindgen]
+impl Contract {
+    pub fn backing_ratio(&self) -> BackingRatio {
+        let total_supply = self.token.total_supply;
+        let stable_reserve = self.stable_treasury.total_reserve();
+        let near_reserve = self.near_reserve();
+        let reserve = rounding::checked_add(stable_reserve, near_reserve, "backing::reserve");
+        let ratio = if total_supply > 0 {
+            let ratio = rounding::mul_div(reserve, FULL_BACKING, total_supply, rounding::BACKING);
+            Some(ratio.min(u32::MAX as u128) as u32)
+        } else {
+            None
+        };
+
+        BackingRatio {
+            total_supply: total_supply.into(),
+            stable_reserve: stable_reserve.into(),
+            near_reserve: near_reserve.into(),
+            ratio,
+            floor: self.backing_floor,
+        }
+    }
+
+    /// Sets the backing ratio floor in basis points, `None` disables the alarm.
+    /// Only can be called by owner.
+    pub fn set_backing_floor(&mut self, floor: Option<u32>) {
+        self.assert_owner("set_backing_floor");
+        self.backing_floor = floor;
+    }
+
+    /// Logs `under_collateralized` if the backing ratio is below the floor.
+    pub(crate) fn check_backing(&self) {
+        let backing = self.backing_ratio();
+        if let (Some(ratio), Some(floor)) = (backing.ratio, backing.floor) {
+            if ratio < floor {
+                event::emit::under_collateralized(ratio, floor, backing.total_supply.0);
+            }
+        }
+    }
+
+    fn near_reserve(&self) -> Balance {
+        let storage_cost = env::storage_byte_cost() * Balance::from(env::storage_usage());
+        let near = env::account_balance().saturating_sub(storage_cost);
+        self.oracle.last_report.as_ref().map_or(0, |rate| {
+            rounding::mul_div(
+                near,
+                rate.multiplier(),
+                10u128.pow(u32::from(rate.decimals() - USN_DECIMALS)),
+                rounding::BACKING,
+            )
+        })
+    }
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use super::*;
+    use crate::testing::setup_contract;
+    use near_sdk::test_utils::{accounts, get_logs};
+    use near_sdk::testing_env;
+
+    #[test]
+    fn test_backing_ratio() {
+        let (mut context, mut contract) = setup_contract(accounts(1));
+        assert_eq!(contract.backing_ratio().ratio, None);
+
+        contract
+            .stable_treasury
+            .deposit(&mut contract.token, &accounts(2), &usdt_id(), 1000000);
+        let backing = contract.backing_ratio();
+        assert_eq!(backing.stable_reserve, U128(1000000000000000000));
+        assert_eq!(backing.near_reserve, U128(0));
+        assert_eq!(backing.ratio, Some(10001));
+
+        contract.set_backing_floor(Some(10000));
+        // Drops logs of the deposit.
+        testing_env!(context.build());
+        contract.check_backing();
+        assert!(get_logs().is_empty());
+
+        contract
+            .token
+            .internal_deposit(&accounts(2), 1000000000000000000);
+        assert_eq!(contract.backing_ratio().ratio, Some(5000));
+        contract.check_backing();
+        assert!(get_logs()[0].contains("under_collateralized"));
+    }
+}
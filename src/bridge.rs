@@ -0,0 +1,158 @@
+//! Transfers of USN to and from Ethereum over Rainbow Bridge.
+//!
+//! USN is burned on NEAR and the `eth_transfer` event is logged for the relayer.
+//! Transfers back are minted by the bridge connector after it verifies the proof
+//! of the Ethereum-side burn. The connector must be a minter, its cap bounds
+//! USN in transit.
+
+use std::convert::TryInto;
+
+use crate::*;
+
+use near_sdk::json_types::Base64VecU8;
+use near_sdk::{require, CryptoHash};
+
+/// Parses an Ethereum address with or without `0x`, returns it in lowercase hex without `0x`.
+fn parse_eth_address(address: &str) -> String {
+    let address = address.strip_prefix("0x").unwrap_or(address);
+    require!(
+        address.len() == 40 && address.chars().all(|c| c.is_ascii_hexdigit()),
+        "Invalid Ethereum address"
+    );
+    address.to_ascii_lowercase()
+}
+
+#[near_bindgen]
+impl Contract {
+    /// Sets the bridge connector, `None` disables the bridge. Only can be called by owner.
+    pub fn set_eth_connector(&mut self, connector_id: Option<AccountId>) {
+        self.assert_owner("set_eth_connector");
+        self.eth_connector = connector_id;
+    }
+
+    pub fn eth_connector(&self) -> Option<AccountId> {
+        self.eth_connector.clone()
+    }
+
+    /// Burns `amount` of the caller's USN to be released to `eth_recipient` on Ethereum.
+    /// Returns the nonce of the transfer.
+    #[payable]
+    pub fn transfer_to_ethereum(&mut self, amount: U128, eth_recipient: String) -> U64 {
+        assert_one_yocto();
+        self.abort_if_pause();
+        let sender_id = env::predecessor_account_id();
+        self.abort_if_blacklisted(&sender_id);
+        require!(amount.0 > 0, "The amount should be a positive number");
+        let eth_recipient = parse_eth_address(&eth_recipient);
+        let connector_id = self.eth_connector_or_panic();
+
+        // The USN in transit can be minted back by the connector.
+        let mut connector = self.minter_or_panic(&connector_id);
+        connector.burn(amount.into());
+        self.minters.insert(&connector_id, &connector);
+
+        self.token.internal_withdraw(&sender_id, amount.into());
+        self.eth_transfer_nonce += 1;
+        event::emit::ft_burn(&sender_id, amount.into(), Some("Bridge"));
+        event::emit::eth_transfer(
+            &sender_id,
+            &eth_recipient,
+            amount.into(),
+            self.eth_transfer_nonce,
+        );
+        self.eth_transfer_nonce.into()
+    }
+
+    /// Mints USN of the verified Ethereum-side burn. Each proof can be used once.
+    /// Only can be called by the bridge connector.
+    pub fn finish_transfer_from_ethereum(
+        &mut self,
+        receiver_id: AccountId,
+        amount: U128,
+        proof: Base64VecU8,
+    ) {
+        self.abort_if_pause();
+        let connector_id = self.eth_connector_or_panic();
+        require!(
+            env::predecessor_account_id() == connector_id,
+            "This method can be called only by bridge connector"
+        );
+        self.abort_if_blacklisted(&receiver_id);
+
+        let proof_hash: CryptoHash = env::sha256(&proof.0).try_into().unwrap();
+        require!(
+            self.eth_proofs.insert(&proof_hash),
+            "The proof has been already used"
+        );
+
+        let mut connector = self.minter_or_panic(&connector_id);
+        connector.mint(amount.into());
+        self.minters.insert(&connector_id, &connector);
+
+        self.token.internal_deposit(&receiver_id, amount.into());
+        event::emit::ft_mint(&receiver_id, amount.into(), Some("Bridge"));
+        event::emit::eth_transfer_finish(&receiver_id, amount.into(), &proof_hash);
+        self.check_backing();
+    }
+
+    fn eth_connector_or_panic(&self) -> AccountId {
+        self.eth_connector
+            .clone()
+            .unwrap_or_else(|| env::panic_str("The bridge is disabled"))
+    }
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use super::*;
+    use crate::testing::setup_contract;
+    use near_sdk::test_utils::{accounts, VMContextBuilder};
+    use near_sdk::{testing_env, ONE_YOCTO};
+
+    const ETH_RECIPIENT: &str = "0x5A0b54D5dc17e0AadC383d2db43B0a0D3E029c4c";
+
+    fn setup_bridge() -> (VMContextBuilder, Contract) {
+        let (mut context, mut contract) = setup_contract(accounts(1));
+        contract.set_minter(accounts(2), U128(1000), U128(1000));
+        contract.set_eth_connector(Some(accounts(2)));
+        contract.token.internal_deposit(&accounts(3), 500);
+        testing_env!(context
+            .predecessor_account_id(accounts(3))
+            .attached_deposit(ONE_YOCTO)
+            .build());
+        (context, contract)
+    }
+
+    #[test]
+    fn test_eth_round_trip() {
+        let (mut context, mut contract) = setup_bridge();
+        assert_eq!(
+            contract.transfer_to_ethereum(U128(200), ETH_RECIPIENT.to_string()),
+            U64(1)
+        );
+        assert_eq!(contract.ft_balance_of(accounts(3)), U128(300));
+        assert_eq!(contract.ft_total_supply(), U128(300));
+        assert_eq!(contract.minters()[0].1.cap, U128(1200));
+
+        testing_env!(context.predecessor_account_id(accounts(2)).build());
+        contract.finish_transfer_from_ethereum(accounts(3), U128(200), vec![1, 2, 3].into());
+        assert_eq!(contract.ft_balance_of(accounts(3)), U128(500));
+        assert_eq!(contract.minters()[0].1.cap, U128(1000));
+    }
+
+    #[test]
+    #[should_panic(expected = "The proof has been already used")]
+    fn test_eth_proof_replay() {
+        let (mut context, mut contract) = setup_bridge();
+        testing_env!(context.predecessor_account_id(accounts(2)).build());
+        contract.finish_transfer_from_ethereum(accounts(3), U128(1), vec![1].into());
+        contract.finish_transfer_from_ethereum(accounts(3), U128(1), vec![1].into());
+    }
+
+    #[test]
+    #[should_panic(expected = "Invalid Ethereum address")]
+    fn test_invalid_eth_recipient() {
+        let (_, mut contract) = setup_bridge();
+        contract.transfer_to_ethereum(U128(200), "0x1234".to_string());
+    }
+}
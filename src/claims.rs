@@ -0,0 +1,280 @@
+//! Airdrop campaigns claimed with merkle proofs.
+//!
+//! The owner funds a campaign with USN and publishes the merkle root of its leaves.
+//! A leaf is sha256 of borsh-serialized `AirdropLeaf`, inner nodes are sha256 of
+//! the concatenation of the sorted pair of child hashes.
+
+use crate::*;
+
+use near_sdk::json_types::Base64VecU8;
+use near_sdk::require;
+
+const HASH_LEN: usize = 32;
+const BITMAP_WORD_BITS: u64 = 64;
+
+#[derive(BorshSerialize)]
+pub struct AirdropLeaf {
+    pub index: u64,
+    pub account_id: AccountId,
+    pub amount: Balance,
+}
+
+impl AirdropLeaf {
+    pub fn hash(&self) -> Vec<u8> {
+        env::sha256(&self.try_to_vec().unwrap())
+    }
+}
+
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(crate = "near_sdk::serde")]
+pub struct Airdrop {
+    pub merkle_root: Base64VecU8,
+    pub funded: U128,
+    pub claimed: U128,
+    pub closed: bool,
+}
+
+#[near_bindgen]
+impl Contract {
+    /// Creates an airdrop campaign funded with `amount` of the owner's USN.
+    /// Returns the campaign id. Only can be called by owner.
+    #[payable]
+    pub fn create_airdrop(&mut self, merkle_root: Base64VecU8, amount: U128) -> u64 {
+        assert_one_yocto();
+        self.assert_owner("create_airdrop");
+        require!(
+            merkle_root.0.len() == HASH_LEN,
+            "The merkle root should be 32 bytes"
+        );
+        self.token.internal_transfer(
+            &self.owner_id,
+            &env::current_account_id(),
+            amount.into(),
+            Some("Airdrop funding".to_string()),
+        );
+
+        let airdrop_id = self.airdrops.len();
+        self.airdrops.push(&Airdrop {
+            merkle_root,
+            funded: amount,
+            claimed: U128(0),
+            closed: false,
+        });
+        self.airdrop_escrow += amount.0;
+        event::emit::airdrop_create(airdrop_id, amount.into());
+        airdrop_id
+    }
+
+    /// Returns unclaimed USN of the campaign to the owner and stops claims.
+    /// Only can be called by owner.
+    #[payable]
+    pub fn close_airdrop(&mut self, airdrop_id: u64) {
+        assert_one_yocto();
+        self.assert_owner("close_airdrop");
+        let mut airdrop = self.airdrop_or_panic(airdrop_id);
+        require!(!airdrop.closed, "The airdrop is closed");
+        let unclaimed = airdrop.funded.0 - airdrop.claimed.0;
+        if unclaimed > 0 {
+            self.token.internal_transfer(
+                &env::current_account_id(),
+                &self.owner_id,
+                unclaimed,
+                Some("Airdrop closing".to_string()),
+            );
+        }
+        airdrop.closed = true;
+        self.airdrops.replace(airdrop_id, &airdrop);
+        self.airdrop_escrow -= unclaimed;
+    }
+
+    /// Transfers `amount` of USN to the caller if the leaf is in the campaign merkle tree.
+    pub fn claim_airdrop(
+        &mut self,
+        airdrop_id: u64,
+        index: u64,
+        amount: U128,
+        proof: Vec<Base64VecU8>,
+    ) {
+        let account_id = env::predecessor_account_id();
+        self.abort_if_pause();
+        self.abort_if_blacklisted(&account_id);
+
+        let mut airdrop = self.airdrop_or_panic(airdrop_id);
+        require!(!airdrop.closed, "The airdrop is closed");
+        require!(
+            !self.is_airdrop_claimed(airdrop_id, index),
+            "The airdrop is already claimed"
+        );
+
+        let leaf = AirdropLeaf {
+            index,
+            account_id: account_id.clone(),
+            amount: amount.into(),
+        }
+        .hash();
+        require!(
+            verify_merkle_proof(leaf, &proof, &airdrop.merkle_root.0),
+            "Invalid merkle proof"
+        );
+
+        airdrop.claimed =
+            rounding::checked_add(airdrop.claimed.0, amount.0, "claims::claim").into();
+        require!(
+            airdrop.claimed.0 <= airdrop.funded.0,
+            "The airdrop is exhausted"
+        );
+        self.airdrops.replace(airdrop_id, &airdrop);
+        self.set_airdrop_claimed(airdrop_id, index);
+        self.airdrop_escrow -= amount.0;
+
+        self.token.internal_transfer(
+            &env::current_account_id(),
+            &account_id,
+            amount.into(),
+            Some("Airdrop".to_string()),
+        );
+        event::emit::airdrop_claim(airdrop_id, index, &account_id, amount.into());
+    }
+
+    pub fn airdrop(&self, airdrop_id: u64) -> Option<Airdrop> {
+        self.airdrops.get(airdrop_id)
+    }
+
+    pub fn is_airdrop_claimed(&self, airdrop_id: u64, index: u64) -> bool {
+        let word = self
+            .airdrop_claims
+            .get(&(airdrop_id, index / BITMAP_WORD_BITS))
+            .unwrap_or(0);
+        word & (1 << (index % BITMAP_WORD_BITS)) != 0
+    }
+
+    fn set_airdrop_claimed(&mut self, airdrop_id: u64, index: u64) {
+        let key = (airdrop_id, index / BITMAP_WORD_BITS);
+        let word = self.airdrop_claims.get(&key).unwrap_or(0);
+        self.airdrop_claims
+            .insert(&key, &(word | (1 << (index % BITMAP_WORD_BITS))));
+    }
+
+    fn airdrop_or_panic(&self, airdrop_id: u64) -> Airdrop {
+        self.airdrops
+            .get(airdrop_id)
+            .unwrap_or_else(|| env::panic_str("The airdrop doesn't exist"))
+    }
+}
+
+fn verify_merkle_proof(leaf: Vec<u8>, proof: &[Base64VecU8], root: &[u8]) -> bool {
+    let hash = proof.iter().fold(leaf, |hash, sibling| {
+        let (left, right) = if hash <= sibling.0 {
+            (&hash, &sibling.0)
+        } else {
+            (&sibling.0, &hash)
+        };
+        env::sha256(&[left.as_slice(), right.as_slice()].concat())
+    });
+    hash == root
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use super::*;
+    use crate::testing::setup_contract;
+    use near_sdk::test_utils::{accounts, VMContextBuilder};
+    use near_sdk::{testing_env, ONE_YOCTO};
+
+    fn leaf(index: u64, account_id: AccountId, amount: Balance) -> Vec<u8> {
+        AirdropLeaf {
+            index,
+            account_id,
+            amount,
+        }
+        .hash()
+    }
+
+    fn node(a: &[u8], b: &[u8]) -> Vec<u8> {
+        if a <= b {
+            env::sha256(&[a, b].concat())
+        } else {
+            env::sha256(&[b, a].concat())
+        }
+    }
+
+    fn setup_airdrop() -> (VMContextBuilder, Contract, Vec<Vec<u8>>) {
+        let (mut context, mut contract) = setup_contract(accounts(1));
+        testing_env!(context.attached_deposit(ONE_YOCTO).build());
+        contract.token.internal_deposit(&accounts(1), 1000);
+
+        let leaves = vec![
+            leaf(0, accounts(2), 100),
+            leaf(1, accounts(3), 200),
+            leaf(2, accounts(4), 300),
+        ];
+        let root = node(&node(&leaves[0], &leaves[1]), &leaves[2]);
+        contract.create_airdrop(Base64VecU8(root), U128(600));
+        (context, contract, leaves)
+    }
+
+    #[test]
+    fn test_claim_airdrop() {
+        let (mut context, mut contract, leaves) = setup_airdrop();
+        assert_eq!(contract.ft_balance_of(accounts(1)), U128(400));
+        assert_eq!(contract.ft_balance_of(accounts(0)), U128(600));
+
+        testing_env!(context.predecessor_account_id(accounts(3)).build());
+        contract.claim_airdrop(
+            0,
+            1,
+            U128(200),
+            vec![
+                Base64VecU8(leaves[0].clone()),
+                Base64VecU8(leaves[2].clone()),
+            ],
+        );
+        assert_eq!(contract.ft_balance_of(accounts(3)), U128(200));
+        assert!(contract.is_airdrop_claimed(0, 1));
+        assert!(!contract.is_airdrop_claimed(0, 0));
+        assert_eq!(contract.airdrop(0).unwrap().claimed, U128(200));
+        assert_eq!(contract.escrowed_contract_usn(), 400);
+
+        testing_env!(context.predecessor_account_id(accounts(4)).build());
+        let branch = node(&leaves[0], &leaves[1]);
+        contract.claim_airdrop(0, 2, U128(300), vec![Base64VecU8(branch)]);
+        assert_eq!(contract.ft_balance_of(accounts(4)), U128(300));
+
+        testing_env!(context.predecessor_account_id(accounts(1)).build());
+        contract.close_airdrop(0);
+        assert_eq!(contract.ft_balance_of(accounts(1)), U128(500));
+        assert!(contract.airdrop(0).unwrap().closed);
+        assert_eq!(contract.escrowed_contract_usn(), 0);
+    }
+
+    #[test]
+    #[should_panic(expected = "The airdrop is already claimed")]
+    fn test_claim_airdrop_twice() {
+        let (mut context, mut contract, leaves) = setup_airdrop();
+        let proof = vec![
+            Base64VecU8(leaves[1].clone()),
+            Base64VecU8(leaves[2].clone()),
+        ];
+
+        testing_env!(context.predecessor_account_id(accounts(2)).build());
+        contract.claim_airdrop(0, 0, U128(100), proof.clone());
+        contract.claim_airdrop(0, 0, U128(100), proof);
+    }
+
+    #[test]
+    #[should_panic(expected = "Invalid merkle proof")]
+    fn test_claim_airdrop_wrong_amount() {
+        let (mut context, mut contract, leaves) = setup_airdrop();
+
+        testing_env!(context.predecessor_account_id(accounts(2)).build());
+        contract.claim_airdrop(
+            0,
+            0,
+            U128(101),
+            vec![
+                Base64VecU8(leaves[1].clone()),
+                Base64VecU8(leaves[2].clone()),
+            ],
+        );
+    }
+}
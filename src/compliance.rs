@@ -0,0 +1,235 @@
+//! Configurable compliance rules checked on `ft_transfer` and `ft_transfer_call`.
+//!
+//! The owner registers rules by id and enables or disables them individually.
+//! A transfer violating an enabled rule isn't made and logs a `compliance_reject` event.
+//! The call fails by the returned promise rather than a panic, so the event isn't reverted.
+
+use crate::*;
+
+const DAY_NS: u64 = 24 * 60 * 60 * 1_000_000_000;
+
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(crate = "near_sdk::serde")]
+pub enum ComplianceRule {
+    /// Maximum amount of a single transfer.
+    MaxTransfer { amount: U128 },
+    /// Maximum amount sent by an account during a UTC day.
+    DailyCap { amount: U128 },
+    /// Rejects transfers from or to accounts tagged with any of the tags.
+    RestrictedTags { tags: Vec<String> },
+}
+
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(crate = "near_sdk::serde")]
+pub struct ComplianceRuleConfig {
+    pub rule: ComplianceRule,
+    pub enabled: bool,
+}
+
+/// Amount sent by an account during the day.
+#[derive(BorshDeserialize, BorshSerialize)]
+pub struct DailyTransfers {
+    day: u64,
+    amount: Balance,
+}
+
+#[near_bindgen]
+impl Contract {
+    /// Adds or replaces the rule, it's enabled. Only can be called by owner.
+    pub fn set_compliance_rule(&mut self, rule_id: String, rule: ComplianceRule) {
+        self.assert_owner("set_compliance_rule");
+        self.compliance_rules.insert(
+            &rule_id,
+            &ComplianceRuleConfig {
+                rule,
+                enabled: true,
+            },
+        );
+    }
+
+    pub fn set_compliance_rule_enabled(&mut self, rule_id: String, enabled: bool) {
+        self.assert_owner("set_compliance_rule_enabled");
+        let mut config = self
+            .compliance_rules
+            .get(&rule_id)
+            .unwrap_or_else(|| env::panic_str("The compliance rule doesn't exist"));
+        config.enabled = enabled;
+        self.compliance_rules.insert(&rule_id, &config);
+    }
+
+    pub fn remove_compliance_rule(&mut self, rule_id: String) {
+        self.assert_owner("remove_compliance_rule");
+        self.compliance_rules
+            .remove(&rule_id)
+            .unwrap_or_else(|| env::panic_str("The compliance rule doesn't exist"));
+    }
+
+    pub fn compliance_rules(&self) -> Vec<(String, ComplianceRuleConfig)> {
+        self.compliance_rules.to_vec()
+    }
+
+    /// Replaces tags of the account, empty tags remove them. Only can be called by owner.
+    pub fn set_account_tags(&mut self, account_id: AccountId, tags: Vec<String>) {
+        self.assert_owner("set_account_tags");
+        if tags.is_empty() {
+            self.account_tags.remove(&account_id);
+        } else {
+            self.account_tags.insert(&account_id, &tags);
+        }
+    }
+
+    pub fn account_tags(&self, account_id: AccountId) -> Vec<String> {
+        self.account_tags.get(&account_id).unwrap_or_default()
+    }
+
+    /// Checks the transfer against enabled rules accounting it in the daily volume.
+    /// Returns the promise failing the call if the transfer violates a rule,
+    /// the caller returns it without making the transfer.
+    pub(crate) fn check_compliance(
+        &mut self,
+        sender_id: &AccountId,
+        receiver_id: &AccountId,
+        amount: Balance,
+    ) -> Option<Promise> {
+        let rules: Vec<(String, ComplianceRule)> = self
+            .compliance_rules
+            .iter()
+            .filter(|(_, config)| config.enabled)
+            .map(|(rule_id, config)| (rule_id, config.rule))
+            .collect();
+        if rules.is_empty() {
+            return None;
+        }
+
+        let day = env::block_timestamp() / DAY_NS;
+        let mut daily = self
+            .daily_transfers
+            .get(sender_id)
+            .filter(|daily| daily.day == day)
+            .unwrap_or(DailyTransfers { day, amount: 0 });
+        daily.amount = daily.amount.saturating_add(amount);
+
+        let mut has_daily_cap = false;
+        for (rule_id, rule) in rules.iter() {
+            let violated = match rule {
+                ComplianceRule::MaxTransfer { amount: max } => amount > max.0,
+                ComplianceRule::DailyCap { amount: cap } => {
+                    has_daily_cap = true;
+                    daily.amount > cap.0
+                }
+                ComplianceRule::RestrictedTags { tags } => [sender_id, receiver_id]
+                    .iter()
+                    .flat_map(|account_id| self.account_tags.get(*account_id).unwrap_or_default())
+                    .any(|tag| tags.contains(&tag)),
+            };
+            if violated {
+                event::emit::compliance_reject(rule_id, sender_id, receiver_id, amount);
+                return Some(reject(format!(
+                    "The transfer violates the compliance rule '{}'",
+                    rule_id
+                )));
+            }
+        }
+
+        // The daily volume is kept only while it's limited.
+        if has_daily_cap {
+            self.daily_transfers.insert(sender_id, &daily);
+        }
+        None
+    }
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use super::*;
+    use crate::testing::setup_contract;
+    use near_sdk::test_utils::{accounts, get_logs};
+    use near_sdk::testing_env;
+
+    #[test]
+    fn test_daily_cap() {
+        let (mut context, mut contract) = setup_contract(accounts(0));
+        contract.set_compliance_rule(
+            "cap".to_string(),
+            ComplianceRule::DailyCap { amount: U128(100) },
+        );
+
+        assert!(contract
+            .check_compliance(&accounts(1), &accounts(2), 60)
+            .is_none());
+        assert!(contract
+            .check_compliance(&accounts(1), &accounts(2), 40)
+            .is_none());
+        assert!(contract
+            .check_compliance(&accounts(2), &accounts(1), 100)
+            .is_none());
+
+        // The next day.
+        testing_env!(context.block_timestamp(DAY_NS).build());
+        assert!(contract
+            .check_compliance(&accounts(1), &accounts(2), 100)
+            .is_none());
+    }
+
+    #[test]
+    fn test_daily_cap_exceeded() {
+        let (_, mut contract) = setup_contract(accounts(0));
+        contract.set_compliance_rule(
+            "cap".to_string(),
+            ComplianceRule::DailyCap { amount: U128(100) },
+        );
+
+        assert!(contract
+            .check_compliance(&accounts(1), &accounts(2), 60)
+            .is_none());
+        assert!(contract
+            .check_compliance(&accounts(1), &accounts(2), 41)
+            .is_some());
+        assert_eq!(
+            get_logs().last().unwrap(),
+            r#"EVENT_JSON:{"data":[{"amount":"41","receiver_id":"charlie","rule_id":"cap","sender_id":"bob"}],"event":"compliance_reject","standard":"usn","version":"1.0.0"}"#
+        );
+
+        // The rejected transfer isn't accounted in the daily volume.
+        assert!(contract
+            .check_compliance(&accounts(1), &accounts(2), 40)
+            .is_none());
+    }
+
+    #[test]
+    fn test_restricted_tags() {
+        let (_, mut contract) = setup_contract(accounts(0));
+        contract.set_compliance_rule(
+            "sanctions".to_string(),
+            ComplianceRule::RestrictedTags {
+                tags: vec!["XX".to_string()],
+            },
+        );
+        contract.set_account_tags(accounts(2), vec!["YY".to_string()]);
+        assert!(contract
+            .check_compliance(&accounts(1), &accounts(2), 1)
+            .is_none());
+
+        contract.set_account_tags(accounts(2), vec!["YY".to_string(), "XX".to_string()]);
+        assert!(contract
+            .check_compliance(&accounts(1), &accounts(2), 1)
+            .is_some());
+    }
+
+    #[test]
+    fn test_disabled_rule() {
+        let (_, mut contract) = setup_contract(accounts(0));
+        contract.set_compliance_rule(
+            "max".to_string(),
+            ComplianceRule::MaxTransfer { amount: U128(10) },
+        );
+        contract.set_compliance_rule_enabled("max".to_string(), false);
+        assert!(contract
+            .check_compliance(&accounts(1), &accounts(2), 11)
+            .is_none());
+        assert!(!contract.compliance_rules()[0].1.enabled);
+
+        contract.remove_compliance_rule("max".to_string());
+        assert!(contract.compliance_rules().is_empty());
+    }
+}
@@ -0,0 +1,408 @@
+//! Delegated transfers signed off-chain by the token owner.
+//!
+//! A relayer submits the signed authorization and pays for gas instead of the user.
+//!
+//! Only session keys registered by the sender are accepted, full-access keys are not.
+//! A contract can't read the access keys of a named account, and the key of an implicit
+//! account can't be revoked, so an implicit account registers its own key as a session key.
+
+use crate::*;
+
+use std::convert::TryFrom;
+
+use near_sdk::json_types::{Base64VecU8, U64};
+use near_sdk::{require, CurveType, PublicKey, Timestamp};
+
+const MAX_SESSION_KEYS: usize = 5;
+
+/// Payload of a transfer authorization.
+/// The sender signs sha256 of its borsh serialization with an ED25519 key.
+#[derive(BorshSerialize)]
+pub struct TransferAuthorization {
+    pub contract_id: AccountId,
+    pub sender_id: AccountId,
+    pub receiver_id: AccountId,
+    pub amount: Balance,
+    pub nonce: u64,
+    pub deadline: Timestamp,
+}
+
+impl TransferAuthorization {
+    pub fn hash(&self) -> Vec<u8> {
+        env::sha256(&self.try_to_vec().unwrap())
+    }
+}
+
+#[near_bindgen]
+impl Contract {
+    /// Registers a session key which can sign transfer authorizations of the caller.
+    /// Requires 1 yoctoNEAR, so the call is signed with a full access key.
+    #[payable]
+    pub fn add_session_key(&mut self, public_key: PublicKey) {
+        assert_one_yocto();
+        require!(
+            public_key.curve_type() == CurveType::ED25519,
+            "Only ED25519 session keys are supported"
+        );
+
+        let account_id = env::predecessor_account_id();
+        let mut keys = self.session_keys.get(&account_id).unwrap_or_default();
+        require!(
+            !keys.contains(&public_key),
+            "The session key is already registered"
+        );
+        require!(
+            keys.len() < MAX_SESSION_KEYS,
+            &format!("Maximum {} session keys are allowed", MAX_SESSION_KEYS)
+        );
+        keys.push(public_key);
+        self.session_keys.insert(&account_id, &keys);
+    }
+
+    /// Revokes a session key of the caller.
+    #[payable]
+    pub fn remove_session_key(&mut self, public_key: PublicKey) {
+        assert_one_yocto();
+        let account_id = env::predecessor_account_id();
+        let mut keys = self.session_keys.get(&account_id).unwrap_or_default();
+        let len = keys.len();
+        keys.retain(|key| key != &public_key);
+        require!(keys.len() < len, "The session key is not registered");
+        if keys.is_empty() {
+            self.session_keys.remove(&account_id);
+        } else {
+            self.session_keys.insert(&account_id, &keys);
+        }
+    }
+
+    pub fn session_keys(&self, account_id: AccountId) -> Vec<PublicKey> {
+        self.session_keys.get(&account_id).unwrap_or_default()
+    }
+
+    /// Returns the nonce expected in the next transfer authorization of the account.
+    pub fn transfer_nonce(&self, account_id: AccountId) -> U64 {
+        self.transfer_nonces.get(&account_id).unwrap_or(0).into()
+    }
+
+    /// Transfers tokens on behalf of `sender_id` who signed the authorization off-chain.
+    /// Anyone can relay the authorization and pay for gas.
+    ///
+    ///  * `nonce` - must be equal to `transfer_nonce(sender_id)`, it's incremented on success.
+    ///  * `deadline` - block timestamp (in nanoseconds) after which the authorization expires.
+    ///  * `signature` - ED25519 signature of `TransferAuthorization` hash made with
+    ///     a session key registered by `sender_id`.
+    pub fn transfer_with_signature(
+        &mut self,
+        sender_id: AccountId,
+        receiver_id: AccountId,
+        amount: U128,
+        nonce: U64,
+        deadline: U64,
+        signature: Base64VecU8,
+    ) {
+        self.abort_if_pause();
+        self.abort_if_blacklisted(&sender_id);
+
+        require!(
+            env::block_timestamp() <= deadline.0,
+            "The transfer authorization has expired"
+        );
+
+        let expected_nonce = self.transfer_nonces.get(&sender_id).unwrap_or(0);
+        require!(
+            nonce.0 == expected_nonce,
+            &format!("Invalid nonce: expected {}", expected_nonce)
+        );
+
+        let message = TransferAuthorization {
+            contract_id: env::current_account_id(),
+            sender_id: sender_id.clone(),
+            receiver_id: receiver_id.clone(),
+            amount: amount.into(),
+            nonce: nonce.into(),
+            deadline: deadline.into(),
+        }
+        .hash();
+
+        require!(
+            self.is_signed_by(&sender_id, &message, &signature.0),
+            "Invalid signature"
+        );
+        if let Some(rejection) = self.check_compliance(&sender_id, &receiver_id, amount.into()) {
+            rejection.as_return();
+            return;
+        }
+
+        self.transfer_nonces
+            .insert(&sender_id, &(expected_nonce + 1));
+        self.token
+            .internal_transfer(&sender_id, &receiver_id, amount.into(), None);
+    }
+
+    /// Only registered session keys sign, so every key can be revoked.
+    /// An implicit account registers its own key to opt in.
+    fn is_signed_by(&self, account_id: &AccountId, message: &[u8], signature: &[u8]) -> bool {
+        self.session_keys
+            .get(account_id)
+            .unwrap_or_default()
+            .iter()
+            // The first byte is a curve type.
+            .any(|key| verify_ed25519(&key.as_bytes()[1..], message, signature))
+    }
+}
+
+fn verify_ed25519(public_key: &[u8], message: &[u8], signature: &[u8]) -> bool {
+    let public_key = match ed25519_dalek::PublicKey::from_bytes(public_key) {
+        Ok(public_key) => public_key,
+        Err(_) => return false,
+    };
+    let signature = match ed25519_dalek::Signature::try_from(signature) {
+        Ok(signature) => signature,
+        Err(_) => return false,
+    };
+    public_key.verify_strict(message, &signature).is_ok()
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use super::*;
+    use crate::testing::setup_contract;
+    use ed25519_dalek::{Keypair, SecretKey, Signer};
+    use near_sdk::test_utils::{accounts, VMContextBuilder};
+    use near_sdk::{testing_env, ONE_YOCTO};
+
+    fn keypair(seed: u8) -> Keypair {
+        let secret = SecretKey::from_bytes(&[seed; 32]).unwrap();
+        let public = (&secret).into();
+        Keypair { secret, public }
+    }
+
+    fn implicit_account(keypair: &Keypair) -> AccountId {
+        keypair
+            .public
+            .as_bytes()
+            .iter()
+            .map(|b| format!("{:02x}", b))
+            .collect::<String>()
+            .parse()
+            .unwrap()
+    }
+
+    fn session_key(keypair: &Keypair) -> PublicKey {
+        let mut bytes = vec![0u8];
+        bytes.extend_from_slice(keypair.public.as_bytes());
+        PublicKey::try_from(bytes).unwrap()
+    }
+
+    fn sign(keypair: &Keypair, sender_id: &AccountId, amount: Balance, nonce: u64) -> Base64VecU8 {
+        let message = TransferAuthorization {
+            contract_id: accounts(0),
+            sender_id: sender_id.clone(),
+            receiver_id: accounts(3),
+            amount,
+            nonce,
+            deadline: 1000,
+        }
+        .hash();
+        keypair.sign(&message).to_bytes().to_vec().into()
+    }
+
+    /// Funds the account with 1000 USN and registers the key, then the relayer calls.
+    fn register_session_key(
+        context: &mut VMContextBuilder,
+        contract: &mut Contract,
+        account_id: &AccountId,
+        keypair: &Keypair,
+    ) {
+        contract.token.internal_deposit(account_id, 1000);
+        testing_env!(context
+            .predecessor_account_id(account_id.clone())
+            .attached_deposit(ONE_YOCTO)
+            .build());
+        contract.add_session_key(session_key(keypair));
+        testing_env!(context
+            .predecessor_account_id(accounts(4))
+            .attached_deposit(0)
+            .build());
+    }
+
+    #[test]
+    #[should_panic(expected = "Invalid signature")]
+    fn test_transfer_signed_by_unregistered_implicit_key() {
+        let (_, mut contract) = setup_contract(accounts(1));
+        let keypair = keypair(1);
+        let sender_id = implicit_account(&keypair);
+        contract.token.internal_deposit(&sender_id, 1000);
+
+        let signature = sign(&keypair, &sender_id, 400, 0);
+        contract.transfer_with_signature(
+            sender_id.clone(),
+            accounts(3),
+            U128(400),
+            U64(0),
+            U64(1000),
+            signature,
+        );
+        assert_eq!(contract.ft_balance_of(accounts(3)), U128(0));
+        assert_eq!(contract.transfer_nonce(sender_id), U64(0));
+    }
+
+    #[test]
+    fn test_transfer_signed_by_implicit_account() {
+        let (mut context, mut contract) = setup_contract(accounts(1));
+        let keypair = keypair(1);
+        let sender_id = implicit_account(&keypair);
+        register_session_key(&mut context, &mut contract, &sender_id, &keypair);
+
+        let signature = sign(&keypair, &sender_id, 400, 0);
+        contract.transfer_with_signature(
+            sender_id.clone(),
+            accounts(3),
+            U128(400),
+            U64(0),
+            U64(1000),
+            signature,
+        );
+
+        assert_eq!(contract.ft_balance_of(sender_id.clone()), U128(600));
+        assert_eq!(contract.ft_balance_of(accounts(3)), U128(400));
+        assert_eq!(contract.transfer_nonce(sender_id), U64(1));
+    }
+
+    #[test]
+    fn test_transfer_violating_compliance() {
+        let (mut context, mut contract) = setup_contract(accounts(1));
+        contract.set_compliance_rule(
+            "max".to_string(),
+            compliance::ComplianceRule::MaxTransfer { amount: U128(300) },
+        );
+        let keypair = keypair(1);
+        let sender_id = implicit_account(&keypair);
+        register_session_key(&mut context, &mut contract, &sender_id, &keypair);
+
+        let signature = sign(&keypair, &sender_id, 400, 0);
+        contract.transfer_with_signature(
+            sender_id,
+            accounts(3),
+            U128(400),
+            U64(0),
+            U64(1000),
+            signature,
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Invalid nonce: expected 1")]
+    fn test_transfer_replay() {
+        let (mut context, mut contract) = setup_contract(accounts(1));
+        let keypair = keypair(1);
+        let sender_id = implicit_account(&keypair);
+        register_session_key(&mut context, &mut contract, &sender_id, &keypair);
+
+        let signature = sign(&keypair, &sender_id, 400, 0);
+        for _ in 0..2 {
+            contract.transfer_with_signature(
+                sender_id.clone(),
+                accounts(3),
+                U128(400),
+                U64(0),
+                U64(1000),
+                signature.clone(),
+            );
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "The transfer authorization has expired")]
+    fn test_transfer_expired() {
+        let (mut context, mut contract) = setup_contract(accounts(1));
+        let keypair = keypair(1);
+        let sender_id = implicit_account(&keypair);
+        register_session_key(&mut context, &mut contract, &sender_id, &keypair);
+
+        testing_env!(context.block_timestamp(1001).build());
+        let signature = sign(&keypair, &sender_id, 400, 0);
+        contract.transfer_with_signature(
+            sender_id,
+            accounts(3),
+            U128(400),
+            U64(0),
+            U64(1000),
+            signature,
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Invalid signature")]
+    fn test_transfer_tampered_amount() {
+        let (mut context, mut contract) = setup_contract(accounts(1));
+        let keypair = keypair(1);
+        let sender_id = implicit_account(&keypair);
+        register_session_key(&mut context, &mut contract, &sender_id, &keypair);
+
+        let signature = sign(&keypair, &sender_id, 400, 0);
+        contract.transfer_with_signature(
+            sender_id,
+            accounts(3),
+            U128(500),
+            U64(0),
+            U64(1000),
+            signature,
+        );
+    }
+
+    #[test]
+    fn test_transfer_signed_by_session_key() {
+        let (mut context, mut contract) = setup_contract(accounts(1));
+        let keypair = keypair(2);
+        contract.token.internal_deposit(&accounts(2), 1000);
+
+        testing_env!(context
+            .predecessor_account_id(accounts(2))
+            .attached_deposit(ONE_YOCTO)
+            .build());
+        contract.add_session_key(session_key(&keypair));
+        assert_eq!(contract.session_keys(accounts(2)).len(), 1);
+
+        testing_env!(context
+            .predecessor_account_id(accounts(4))
+            .attached_deposit(0)
+            .build());
+        let signature = sign(&keypair, &accounts(2), 1000, 0);
+        contract.transfer_with_signature(
+            accounts(2),
+            accounts(3),
+            U128(1000),
+            U64(0),
+            U64(1000),
+            signature,
+        );
+        assert_eq!(contract.ft_balance_of(accounts(3)), U128(1000));
+    }
+
+    #[test]
+    #[should_panic(expected = "Invalid signature")]
+    fn test_transfer_signed_by_removed_session_key() {
+        let (mut context, mut contract) = setup_contract(accounts(1));
+        let keypair = keypair(2);
+        contract.token.internal_deposit(&accounts(2), 1000);
+
+        testing_env!(context
+            .predecessor_account_id(accounts(2))
+            .attached_deposit(ONE_YOCTO)
+            .build());
+        contract.add_session_key(session_key(&keypair));
+        contract.remove_session_key(session_key(&keypair));
+        assert!(contract.session_keys(accounts(2)).is_empty());
+
+        let signature = sign(&keypair, &accounts(2), 1000, 0);
+        contract.transfer_with_signature(
+            accounts(2),
+            accounts(3),
+            U128(1000),
+            U64(0),
+            U64(1000),
+            signature,
+        );
+    }
+}
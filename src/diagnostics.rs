@@ -0,0 +1,219 @@
+//! Cheap internal consistency checks to run right after an upgrade.
+
+use crate::*;
+
+use treasury::pools_config_is_valid;
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct CheckResult {
+    pub name: String,
+    pub passed: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct SelfCheckReport {
+    pub passed: bool,
+    pub checks: Vec<CheckResult>,
+}
+
+impl SelfCheckReport {
+    fn new() -> Self {
+        Self {
+            passed: true,
+            checks: vec![],
+        }
+    }
+
+    fn check(&mut self, name: &str, passed: bool) {
+        self.passed &= passed;
+        self.checks.push(CheckResult {
+            name: name.to_string(),
+            passed,
+        });
+    }
+}
+
+#[near_bindgen]
+impl Contract {
+    /// Performs internal consistency checks and returns a pass/fail report.
+    /// Intended to be called by deployment automation after every upgrade.
+    pub fn self_check(&self) -> SelfCheckReport {
+        let mut report = SelfCheckReport::new();
+
+        report.check(
+            "metadata",
+            self.metadata
+                .get()
+                .map_or(false, |metadata| metadata.decimals == USN_DECIMALS),
+        );
+        report.check(
+            "treasury_enabled_asset",
+            self.stable_treasury
+                .supported_assets()
+                .iter()
+                .any(|(_, asset)| asset.is_enabled()),
+        );
+        report.check("oracle_config", self.oracle_config.is_valid());
+        report.check("gas_config", self.gas_config.is_valid());
+        report.check("pools_config", pools_config_is_valid());
+        report.check(
+            "owner_not_guardian",
+            !self.guardians.contains(&self.owner_id),
+        );
+
+        report
+    }
+
+    /// Recomputes protocol invariants which are cheap to verify on-chain.
+    /// Anyone can call it, e.g. monitoring or bug bounty hunters.
+    pub fn verify_invariants(&self) -> SelfCheckReport {
+        let mut report = SelfCheckReport::new();
+        let contract_balance = self
+            .token
+            .internal_unwrap_balance_of(&env::current_account_id());
+
+        report.check(
+            "escrow_within_contract_balance",
+            self.escrowed_contract_usn() <= contract_balance,
+        );
+        report.check(
+            "airdrops_within_funding",
+            self.airdrops
+                .iter()
+                .all(|airdrop| airdrop.claimed.0 <= airdrop.funded.0),
+        );
+
+        // Commission v2 accrued before its lifetime flows were tracked stays on top of them.
+        // A payout in the asset is recorded once transferred, so may fail the check meanwhile.
+        let commission: Balance = self
+            .stable_treasury
+            .supported_assets()
+            .iter()
+            .map(|(_, asset)| asset.commission().0)
+            .sum();
+        let lifetime = self.stable_treasury.lifetime_commission();
+        let lifetime_net = lifetime
+            .accrued
+            .0
+            .saturating_sub(lifetime.refunded.0)
+            .saturating_sub(lifetime.transferred.0);
+        report.check("commission_v2_accounted", lifetime_net <= commission);
+
+        report.check(
+            "revenue_split_within_100_percent",
+            self.revenue.split.insurance + self.revenue.split.burn <= 100,
+        );
+        report.check(
+            "guardian_threshold_reachable",
+            self.guardian_policy.as_ref().map_or(true, |policy| {
+                policy.threshold as u64 <= self.guardians.len()
+            }),
+        );
+
+        report
+    }
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use super::*;
+    use crate::testing::setup_contract;
+    use near_sdk::test_utils::accounts;
+    use near_sdk::{testing_env, ONE_YOCTO};
+
+    #[test]
+    fn test_self_check() {
+        let (_, mut contract) = setup_contract(accounts(1));
+
+        let report = contract.self_check();
+        assert!(report.passed);
+        assert!(report.checks.iter().all(|check| check.passed));
+
+        contract.disable_stable_asset(&usdt_id());
+        let report = contract.self_check();
+        assert!(!report.passed);
+        assert_eq!(
+            report
+                .checks
+                .iter()
+                .filter(|check| !check.passed)
+                .map(|check| check.name.as_str())
+                .collect::<Vec<_>>(),
+            vec!["treasury_enabled_asset"]
+        );
+    }
+
+    #[test]
+    fn test_verify_invariants_accounting() {
+        let (_, mut contract) = setup_contract(accounts(1));
+        contract
+            .stable_treasury
+            .deposit(&mut contract.token, &accounts(2), &usdt_id(), 1000000);
+        assert!(contract.verify_invariants().passed);
+
+        contract.vesting_escrow = 1;
+        contract.stable_treasury.decrease_commission(&usdt_id(), 1);
+
+        let report = contract.verify_invariants();
+        assert_eq!(
+            report
+                .checks
+                .iter()
+                .filter(|check| !check.passed)
+                .map(|check| check.name.as_str())
+                .collect::<Vec<_>>(),
+            vec!["escrow_within_contract_balance", "commission_v2_accounted"]
+        );
+    }
+
+    #[test]
+    fn test_verify_invariants_bridged_out() {
+        let (mut context, mut contract) = setup_contract(accounts(1));
+        contract.set_minter(accounts(3), U128(0), U128(0));
+        contract.set_eth_connector(Some(accounts(3)));
+        contract
+            .stable_treasury
+            .deposit(&mut contract.token, &accounts(2), &usdt_id(), 1000000);
+
+        // Burns leave the reserve, raising the backing ratio.
+        testing_env!(context
+            .predecessor_account_id(accounts(2))
+            .attached_deposit(ONE_YOCTO)
+            .build());
+        contract.transfer_to_ethereum(
+            U128(999900000000000000),
+            "0x5A0b54D5dc17e0AadC383d2db43B0a0D3E029c4c".to_string(),
+        );
+        assert_eq!(contract.ft_total_supply(), U128(0));
+        assert!(contract.verify_invariants().passed);
+    }
+
+    #[test]
+    fn test_verify_invariants() {
+        let (_, mut contract) = setup_contract(accounts(1));
+        assert!(contract.verify_invariants().passed);
+
+        contract.extend_guardians(vec![accounts(2)]);
+        contract.set_guardian_policy(Some(guardian::GuardianPolicy {
+            threshold: 1,
+            min_severity: guardian::Severity::High,
+            window: U64(60),
+        }));
+        // `remove_guardians` keeps the threshold reachable.
+        contract.guardians.remove(&accounts(2));
+
+        let report = contract.verify_invariants();
+        assert!(!report.passed);
+        assert_eq!(
+            report
+                .checks
+                .iter()
+                .filter(|check| !check.passed)
+                .map(|check| check.name.as_str())
+                .collect::<Vec<_>>(),
+            vec!["guardian_threshold_reachable"]
+        );
+    }
+}
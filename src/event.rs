@@ -1,8 +1,27 @@
 pub mod emit {
     use near_contract_standards::fungible_token::events::{FtBurn, FtMint};
+    use near_sdk::json_types::Base58CryptoHash;
+    use near_sdk::serde_json::{self, json, Value};
 
     use crate::*;
 
+    const EVENT_STANDARD: &str = "usn";
+    const EVENT_STANDARD_VERSION: &str = "1.0.0";
+
+    /// Logs a NEP-297 event of the USN standard.
+    fn log_event(event: &str, data: Value) {
+        let event = json!({
+            "standard": EVENT_STANDARD,
+            "version": EVENT_STANDARD_VERSION,
+            "event": event,
+            "data": [data],
+        });
+        env::log_str(&format!(
+            "EVENT_JSON:{}",
+            serde_json::to_string(&event).unwrap()
+        ));
+    }
+
     pub fn ft_mint(owner_id: &AccountId, amount: Balance, memo: Option<&str>) {
         (FtMint {
             owner_id: owner_id,
@@ -20,4 +39,600 @@ pub mod emit {
         })
         .emit();
     }
+
+    pub fn ft_approve(owner_id: &AccountId, spender_id: &AccountId, amount: Balance) {
+        log_event(
+            "ft_approve",
+            json!({
+                "owner_id": owner_id,
+                "spender_id": spender_id,
+                "amount": U128(amount),
+            }),
+        );
+    }
+
+    pub fn stable_deposit(
+        account_id: &AccountId,
+        asset_id: &AccountId,
+        asset_amount: Balance,
+        amount: Balance,
+        commission: Balance,
+    ) {
+        log_event(
+            "stable_deposit",
+            json!({
+                "account_id": account_id,
+                "asset_id": asset_id,
+                "asset_amount": U128(asset_amount),
+                "amount": U128(amount),
+                "commission": U128(commission),
+            }),
+        );
+    }
+
+    pub fn stable_withdraw(
+        account_id: &AccountId,
+        asset_id: &AccountId,
+        amount: Balance,
+        asset_amount: Balance,
+        commission: Balance,
+        reserve: Balance,
+        total_supply: Balance,
+    ) {
+        log_event(
+            "stable_withdraw",
+            json!({
+                "account_id": account_id,
+                "asset_id": asset_id,
+                "amount": U128(amount),
+                "asset_amount": U128(asset_amount),
+                "commission": U128(commission),
+                "reserve": U128(reserve),
+                "total_supply": U128(total_supply),
+            }),
+        );
+    }
+
+    pub fn stable_reserve_set(asset_id: &AccountId, reserve: Balance) {
+        log_event(
+            "stable_reserve_set",
+            json!({
+                "asset_id": asset_id,
+                "reserve": U128(reserve),
+            }),
+        );
+    }
+
+    pub fn stable_refund(
+        account_id: &AccountId,
+        asset_id: &AccountId,
+        amount: Balance,
+        commission: Balance,
+    ) {
+        log_event(
+            "stable_refund",
+            json!({
+                "account_id": account_id,
+                "asset_id": asset_id,
+                "amount": U128(amount),
+                "commission": U128(commission),
+            }),
+        );
+    }
+
+    pub fn failed_withdrawal_add(
+        account_id: &AccountId,
+        asset_id: &AccountId,
+        asset_amount: Balance,
+    ) {
+        log_event(
+            "failed_withdrawal_add",
+            json!({
+                "account_id": account_id,
+                "asset_id": asset_id,
+                "asset_amount": U128(asset_amount),
+            }),
+        );
+    }
+
+    pub fn failed_withdrawal_claim(
+        account_id: &AccountId,
+        asset_id: &AccountId,
+        asset_amount: Balance,
+    ) {
+        log_event(
+            "failed_withdrawal_claim",
+            json!({
+                "account_id": account_id,
+                "asset_id": asset_id,
+                "asset_amount": U128(asset_amount),
+            }),
+        );
+    }
+
+    pub fn compliance_reject(
+        rule_id: &str,
+        sender_id: &AccountId,
+        receiver_id: &AccountId,
+        amount: Balance,
+    ) {
+        log_event(
+            "compliance_reject",
+            json!({
+                "rule_id": rule_id,
+                "sender_id": sender_id,
+                "receiver_id": receiver_id,
+                "amount": U128(amount),
+            }),
+        );
+    }
+
+    pub fn airdrop_create(airdrop_id: u64, amount: Balance) {
+        log_event(
+            "airdrop_create",
+            json!({
+                "airdrop_id": airdrop_id,
+                "amount": U128(amount),
+            }),
+        );
+    }
+
+    pub fn airdrop_claim(airdrop_id: u64, index: u64, account_id: &AccountId, amount: Balance) {
+        log_event(
+            "airdrop_claim",
+            json!({
+                "airdrop_id": airdrop_id,
+                "index": index,
+                "account_id": account_id,
+                "amount": U128(amount),
+            }),
+        );
+    }
+
+    pub fn vesting_create(
+        account_id: &AccountId,
+        total: Balance,
+        start: Timestamp,
+        cliff: Timestamp,
+        end: Timestamp,
+    ) {
+        log_event(
+            "vesting_create",
+            json!({
+                "account_id": account_id,
+                "total": U128(total),
+                "start": U64(start),
+                "cliff": U64(cliff),
+                "end": U64(end),
+            }),
+        );
+    }
+
+    pub fn vesting_claim(account_id: &AccountId, amount: Balance) {
+        log_event(
+            "vesting_claim",
+            json!({
+                "account_id": account_id,
+                "amount": U128(amount),
+            }),
+        );
+    }
+
+    pub fn vesting_revoke(account_id: &AccountId, amount: Balance) {
+        log_event(
+            "vesting_revoke",
+            json!({
+                "account_id": account_id,
+                "amount": U128(amount),
+            }),
+        );
+    }
+
+    pub fn asset_delisting(asset_id: &AccountId, deadline: Timestamp) {
+        log_event(
+            "asset_delisting",
+            json!({
+                "asset_id": asset_id,
+                "deadline": U64(deadline),
+            }),
+        );
+    }
+
+    pub fn asset_delisted(asset_id: &AccountId, successor_id: &AccountId, commission: Balance) {
+        log_event(
+            "asset_delisted",
+            json!({
+                "asset_id": asset_id,
+                "successor_id": successor_id,
+                "commission": U128(commission),
+            }),
+        );
+    }
+
+    pub fn commission_transfer(
+        receiver_id: &AccountId,
+        amount: Balance,
+        usn_v1: Balance,
+        near_v1: Balance,
+        epoch: u64,
+        memo: Option<&str>,
+    ) {
+        log_event(
+            "commission_transfer",
+            json!({
+                "receiver_id": receiver_id,
+                "amount": U128(amount),
+                "usn_v1": U128(usn_v1),
+                "near_v1": U128(near_v1),
+                "epoch": U64(epoch),
+                "memo": memo,
+            }),
+        );
+    }
+
+    pub fn commission_withdraw(
+        receiver_id: &AccountId,
+        asset_id: &AccountId,
+        amount: Balance,
+        asset_amount: Balance,
+    ) {
+        log_event(
+            "commission_withdraw",
+            json!({
+                "receiver_id": receiver_id,
+                "asset_id": asset_id,
+                "amount": U128(amount),
+                "asset_amount": U128(asset_amount),
+            }),
+        );
+    }
+
+    pub fn commission_split(dao: Balance, insurance: Balance, burned: Balance) {
+        log_event(
+            "commission_split",
+            json!({
+                "dao": U128(dao),
+                "insurance": U128(insurance),
+                "burned": U128(burned),
+            }),
+        );
+    }
+
+    pub fn blacklist_add(account_id: &AccountId) {
+        log_event("blacklist_add", json!({ "account_id": account_id }));
+    }
+
+    pub fn blacklist_limit_hit(limit: &str) {
+        log_event("blacklist_limit_hit", json!({ "limit": limit }));
+    }
+
+    pub fn blacklist_limits_propose(activates_at: Timestamp) {
+        log_event(
+            "blacklist_limits_propose",
+            json!({ "activates_at": U64(activates_at) }),
+        );
+    }
+
+    pub fn blacklist_remove(account_id: &AccountId) {
+        log_event("blacklist_remove", json!({ "account_id": account_id }));
+    }
+
+    pub fn black_funds_destroy(account_id: &AccountId, amount: Balance) {
+        log_event(
+            "black_funds_destroy",
+            json!({
+                "account_id": account_id,
+                "amount": U128(amount),
+            }),
+        );
+    }
+
+    pub fn account_freeze(account_id: &AccountId, expiry: Timestamp) {
+        log_event(
+            "account_freeze",
+            json!({
+                "account_id": account_id,
+                "expiry": U64(expiry),
+            }),
+        );
+    }
+
+    pub fn account_unfreeze(account_id: &AccountId) {
+        log_event("account_unfreeze", json!({ "account_id": account_id }));
+    }
+
+    pub fn contract_pause(account_id: &AccountId) {
+        log_event("contract_pause", json!({ "account_id": account_id }));
+    }
+
+    pub fn contract_resume(account_id: &AccountId) {
+        log_event("contract_resume", json!({ "account_id": account_id }));
+    }
+
+    pub fn guardians_add(guardians: &[AccountId]) {
+        log_event("guardians_add", json!({ "guardians": guardians }));
+    }
+
+    pub fn guardians_remove(guardians: &[AccountId]) {
+        log_event("guardians_remove", json!({ "guardians": guardians }));
+    }
+
+    pub fn operator_set(account_id: &AccountId, methods: &[String]) {
+        log_event(
+            "operator_set",
+            json!({ "account_id": account_id, "methods": methods }),
+        );
+    }
+
+    pub fn operator_remove(account_id: &AccountId) {
+        log_event("operator_remove", json!({ "account_id": account_id }));
+    }
+
+    #[cfg(feature = "gas_profiling")]
+    pub fn gas_profile(method: &str, used_gas: Gas, prepaid_gas: Gas) {
+        log_event(
+            "gas_profile",
+            json!({
+                "method": method,
+                "used_gas": used_gas,
+                "prepaid_gas": prepaid_gas,
+            }),
+        );
+    }
+
+    #[cfg(feature = "paranoid")]
+    pub fn math_overflow(site: &str, message: &str) {
+        log_event("math_overflow", json!({ "site": site, "message": message }));
+    }
+
+    pub fn guardian_action_confirm(
+        action: &GuardianAction,
+        guardian_id: &AccountId,
+        confirmations: usize,
+    ) {
+        log_event(
+            "guardian_action_confirm",
+            json!({
+                "action": action,
+                "guardian_id": guardian_id,
+                "confirmations": confirmations,
+            }),
+        );
+    }
+
+    pub fn guardian_action_revoke(action: &GuardianAction, guardian_id: &AccountId) {
+        log_event(
+            "guardian_action_revoke",
+            json!({ "action": action, "guardian_id": guardian_id }),
+        );
+    }
+
+    pub fn recovery_approve(
+        guardian_id: &AccountId,
+        new_owner_id: &AccountId,
+        unlocks_at: Option<U64>,
+    ) {
+        log_event(
+            "recovery_approve",
+            json!({
+                "guardian_id": guardian_id,
+                "new_owner_id": new_owner_id,
+                "unlocks_at": unlocks_at,
+            }),
+        );
+    }
+
+    pub fn recovery_cancel(new_owner_id: &AccountId) {
+        log_event("recovery_cancel", json!({ "new_owner_id": new_owner_id }));
+    }
+
+    pub fn ownership_recover(previous_owner_id: &AccountId, owner_id: &AccountId) {
+        log_event(
+            "ownership_recover",
+            json!({
+                "previous_owner_id": previous_owner_id,
+                "owner_id": owner_id,
+            }),
+        );
+    }
+
+    /// The rate used by the operation and whether it is the current or the smooth one.
+    pub fn oracle_rate(rate: &ExchangeRate, source: RateSource) {
+        log_event(
+            "oracle_rate",
+            json!({
+                "multiplier": U128(rate.multiplier()),
+                "decimals": rate.decimals(),
+                "timestamp": U64(rate.timestamp()),
+                "source": source,
+            }),
+        );
+    }
+
+    pub fn liquidity_add(pool_id: u64, amounts: &[U128]) {
+        log_event(
+            "liquidity_add",
+            json!({
+                "pool_id": pool_id,
+                "amounts": amounts,
+            }),
+        );
+    }
+
+    pub fn ref_emergency_withdraw(token_id: &AccountId, amount: Balance, success: bool) {
+        log_event(
+            "ref_emergency_withdraw",
+            json!({
+                "token_id": token_id,
+                "amount": U128(amount),
+                "success": success,
+            }),
+        );
+    }
+
+    pub fn upgrade_stage(hash: &Base58CryptoHash, activates_at: Timestamp) {
+        log_event(
+            "upgrade_stage",
+            json!({
+                "hash": hash,
+                "activates_at": U64(activates_at),
+            }),
+        );
+    }
+
+    pub fn upgrade_commit(hash: &Base58CryptoHash) {
+        log_event("upgrade_commit", json!({ "hash": hash }));
+    }
+
+    pub fn minter_set(account_id: &AccountId, cap: Balance, daily_limit: Balance) {
+        log_event(
+            "minter_set",
+            json!({
+                "account_id": account_id,
+                "cap": U128(cap),
+                "daily_limit": U128(daily_limit),
+            }),
+        );
+    }
+
+    pub fn minter_remove(account_id: &AccountId) {
+        log_event("minter_remove", json!({ "account_id": account_id }));
+    }
+
+    pub fn bridged_mint(minter_id: &AccountId, receiver_id: &AccountId, amount: Balance) {
+        log_event(
+            "bridged_mint",
+            json!({
+                "minter_id": minter_id,
+                "receiver_id": receiver_id,
+                "amount": U128(amount),
+            }),
+        );
+    }
+
+    pub fn bridged_burn(minter_id: &AccountId, amount: Balance) {
+        log_event(
+            "bridged_burn",
+            json!({
+                "minter_id": minter_id,
+                "amount": U128(amount),
+            }),
+        );
+    }
+
+    pub fn eth_transfer(sender_id: &AccountId, eth_recipient: &str, amount: Balance, nonce: u64) {
+        log_event(
+            "eth_transfer",
+            json!({
+                "sender_id": sender_id,
+                "eth_recipient": eth_recipient,
+                "amount": U128(amount),
+                "nonce": U64(nonce),
+            }),
+        );
+    }
+
+    pub fn eth_transfer_finish(receiver_id: &AccountId, amount: Balance, proof_hash: &CryptoHash) {
+        log_event(
+            "eth_transfer_finish",
+            json!({
+                "receiver_id": receiver_id,
+                "amount": U128(amount),
+                "proof_hash": Base58CryptoHash::from(*proof_hash),
+            }),
+        );
+    }
+
+    pub fn wusn_wrap(account_id: &AccountId, amount: Balance) {
+        log_event(
+            "wusn_wrap",
+            json!({
+                "account_id": account_id,
+                "amount": U128(amount),
+            }),
+        );
+    }
+
+    pub fn wusn_unwrap(account_id: &AccountId, amount: Balance) {
+        log_event(
+            "wusn_unwrap",
+            json!({
+                "account_id": account_id,
+                "amount": U128(amount),
+            }),
+        );
+    }
+
+    pub fn under_collateralized(ratio: u32, floor: u32, total_supply: Balance) {
+        log_event(
+            "under_collateralized",
+            json!({
+                "ratio": ratio,
+                "floor": floor,
+                "total_supply": U128(total_supply),
+            }),
+        );
+    }
+
+    pub fn reserve_snapshot(snapshot: &ReserveSnapshot) {
+        log_event("reserve_snapshot", json!(snapshot));
+    }
+
+    pub fn oracle_config(config: &OracleConfig) {
+        log_event("oracle_config", json!(config));
+    }
+
+    pub fn gas_config(config: &GasConfig) {
+        log_event("gas_config", json!(config));
+    }
+
+    pub fn stable_depeg(asset_id: &AccountId, peg: &AssetPeg) {
+        log_event(
+            "stable_depeg",
+            json!({
+                "asset_id": asset_id,
+                "price": peg.price,
+                "tolerance": peg.tolerance,
+            }),
+        );
+    }
+
+    pub fn stable_peg_reject(
+        account_id: &AccountId,
+        asset_id: &AccountId,
+        peg: &AssetPeg,
+        reason: &str,
+    ) {
+        log_event(
+            "stable_peg_reject",
+            json!({
+                "account_id": account_id,
+                "asset_id": asset_id,
+                "price": peg.price,
+                "tolerance": peg.tolerance,
+                "updated_at": peg.updated_at,
+                "reason": reason,
+            }),
+        );
+    }
+
+    pub fn usn_reconcile(pool_id: Option<u64>, amount: Balance) {
+        log_event(
+            "usn_reconcile",
+            json!({
+                "pool_id": pool_id,
+                "amount": U128(amount),
+            }),
+        );
+    }
+
+    pub fn liquidity_remove(pool_id: u64, shares: Balance) {
+        log_event(
+            "liquidity_remove",
+            json!({
+                "pool_id": pool_id,
+                "shares": U128(shares),
+            }),
+        );
+    }
 }
@@ -18,6 +18,58 @@ trait FungibleTokenResolver {
     ) -> U128;
 }
 
+/// Cumulative transfer statistics of an account, tracked with the `account_stats` feature.
+/// Mints and burns aren't counted.
+#[cfg(feature = "account_stats")]
+#[derive(
+    BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug, Default, PartialEq,
+)]
+#[serde(crate = "near_sdk::serde")]
+pub struct AccountStats {
+    pub received: U128,
+    pub sent: U128,
+    pub transfer_count: u64,
+    /// Timestamp of the last transfer in nanoseconds.
+    pub last_activity: U64,
+}
+
+#[cfg(feature = "account_stats")]
+impl AccountStats {
+    fn touch(&mut self) {
+        self.transfer_count += 1;
+        self.last_activity = env::block_timestamp().into();
+    }
+}
+
+/// The stats are kept out of `FungibleTokenFreeStorage` to keep its state layout.
+#[cfg(feature = "account_stats")]
+fn account_stats() -> LookupMap<AccountId, AccountStats> {
+    LookupMap::new(StorageKey::AccountStats)
+}
+
+#[cfg(feature = "account_stats")]
+fn track_transfer(sender_id: &AccountId, receiver_id: &AccountId, amount: Balance) {
+    let mut stats = account_stats();
+
+    let mut sender = stats.get(sender_id).unwrap_or_default();
+    sender.sent = sender.sent.0.saturating_add(amount).into();
+    sender.touch();
+    stats.insert(sender_id, &sender);
+
+    let mut receiver = stats.get(receiver_id).unwrap_or_default();
+    receiver.received = receiver.received.0.saturating_add(amount).into();
+    receiver.touch();
+    stats.insert(receiver_id, &receiver);
+}
+
+#[cfg(feature = "account_stats")]
+#[near_bindgen]
+impl Contract {
+    pub fn get_account_stats(&self, account_id: AccountId) -> Option<AccountStats> {
+        account_stats().get(&account_id)
+    }
+}
+
 /// Implementation of a FungibleToken standard.
 /// Allows to include NEP-141 compatible token to any contract.
 /// There are next traits that any contract may implement:
@@ -66,12 +118,11 @@ impl FungibleTokenFreeStorage {
         let balance = self.internal_unwrap_balance_of(account_id);
         if let Some(new_balance) = balance.checked_add(amount) {
             self.internal_save_balance(account_id, new_balance);
-            self.total_supply = self
-                .total_supply
-                .checked_add(amount)
-                .unwrap_or_else(|| env::panic_str("Total supply overflow"));
+            self.total_supply = self.total_supply.checked_add(amount).unwrap_or_else(|| {
+                rounding::overflow("ft::internal_deposit", "Total supply overflow")
+            });
         } else {
-            env::panic_str("Balance overflow");
+            rounding::overflow("ft::internal_deposit", "Balance overflow");
         }
     }
 
@@ -79,10 +130,9 @@ impl FungibleTokenFreeStorage {
         let balance = self.internal_unwrap_balance_of(account_id);
         if let Some(new_balance) = balance.checked_sub(amount) {
             self.internal_save_balance(account_id, new_balance);
-            self.total_supply = self
-                .total_supply
-                .checked_sub(amount)
-                .unwrap_or_else(|| env::panic_str("Total supply overflow"));
+            self.total_supply = self.total_supply.checked_sub(amount).unwrap_or_else(|| {
+                rounding::overflow("ft::internal_withdraw", "Total supply overflow")
+            });
         } else {
             env::panic_str("The account doesn't have enough balance");
         }
@@ -102,6 +152,8 @@ impl FungibleTokenFreeStorage {
         require!(amount > 0, "The amount should be a positive number");
         self.internal_withdraw(sender_id, amount);
         self.internal_deposit(receiver_id, amount);
+        #[cfg(feature = "account_stats")]
+        track_transfer(sender_id, receiver_id, amount);
         FtTransfer {
             old_owner_id: sender_id,
             new_owner_id: receiver_id,
@@ -218,3 +270,101 @@ impl FungibleTokenFreeStorage {
         (amount, 0)
     }
 }
+
+/// ERC-20 fashioned allowances on top of NEP-141.
+/// The spender can transfer up to the approved amount on behalf of the owner.
+#[near_bindgen]
+impl Contract {
+    /// Sets the amount which `spender_id` is allowed to transfer from the caller's account.
+    #[payable]
+    pub fn approve(&mut self, spender_id: AccountId, amount: U128) {
+        assert_one_yocto();
+        self.abort_if_pause();
+        let owner_id = env::predecessor_account_id();
+        self.abort_if_blacklisted(&owner_id);
+        self.internal_set_allowance(&owner_id, &spender_id, amount.into());
+    }
+
+    #[payable]
+    pub fn increase_allowance(&mut self, spender_id: AccountId, amount: U128) {
+        assert_one_yocto();
+        self.abort_if_pause();
+        let owner_id = env::predecessor_account_id();
+        self.abort_if_blacklisted(&owner_id);
+        let allowance = self
+            .internal_allowance(&owner_id, &spender_id)
+            .checked_add(amount.into())
+            .unwrap_or_else(|| rounding::overflow("ft::increase_allowance", "Allowance overflow"));
+        self.internal_set_allowance(&owner_id, &spender_id, allowance);
+    }
+
+    #[payable]
+    pub fn decrease_allowance(&mut self, spender_id: AccountId, amount: U128) {
+        assert_one_yocto();
+        self.abort_if_pause();
+        let owner_id = env::predecessor_account_id();
+        let allowance = self
+            .internal_allowance(&owner_id, &spender_id)
+            .checked_sub(amount.into())
+            .unwrap_or_else(|| env::panic_str("Decreased allowance below zero"));
+        self.internal_set_allowance(&owner_id, &spender_id, allowance);
+    }
+
+    /// Transfers tokens from `owner_id` to `receiver_id` spending the caller's allowance.
+    #[payable]
+    pub fn transfer_from(
+        &mut self,
+        owner_id: AccountId,
+        receiver_id: AccountId,
+        amount: U128,
+        memo: Option<String>,
+    ) {
+        assert_one_yocto();
+        self.abort_if_pause();
+        let spender_id = env::predecessor_account_id();
+        self.abort_if_blacklisted(&spender_id);
+        self.abort_if_blacklisted(&owner_id);
+
+        let allowance = self
+            .internal_allowance(&owner_id, &spender_id)
+            .checked_sub(amount.into())
+            .unwrap_or_else(|| env::panic_str("Insufficient allowance"));
+        if let Some(rejection) = self.check_compliance(&owner_id, &receiver_id, amount.into()) {
+            rejection.as_return();
+            return;
+        }
+        self.internal_set_allowance(&owner_id, &spender_id, allowance);
+
+        self.token
+            .internal_transfer(&owner_id, &receiver_id, amount.into(), memo);
+    }
+
+    pub fn allowance(&self, owner_id: AccountId, spender_id: AccountId) -> U128 {
+        self.internal_allowance(&owner_id, &spender_id).into()
+    }
+
+    fn internal_allowance(&self, owner_id: &AccountId, spender_id: &AccountId) -> Balance {
+        self.allowances
+            .get(&(owner_id.clone(), spender_id.clone()))
+            .unwrap_or(0)
+    }
+
+    fn internal_set_allowance(
+        &mut self,
+        owner_id: &AccountId,
+        spender_id: &AccountId,
+        amount: Balance,
+    ) {
+        require!(
+            owner_id != spender_id,
+            "Owner and spender should be different"
+        );
+        let key = (owner_id.clone(), spender_id.clone());
+        if amount > 0 {
+            self.allowances.insert(&key, &amount);
+        } else {
+            self.allowances.remove(&key);
+        }
+        event::emit::ft_approve(owner_id, spender_id, amount);
+    }
+}
@@ -0,0 +1,161 @@
+//! Gas of cross-contract calls, adjustable by owner as the protocol costs change.
+//!
+//! Callbacks which only run the code of this contract keep constant gas, it changes
+//! only with an upgrade of the code. Callbacks making further calls add their gas.
+//!
+//! With the `gas_profiling` feature callbacks log the gas they used, see `GasProfile`.
+
+use crate::*;
+
+use near_sdk::require;
+
+/// Gas of cross-contract calls.
+#[derive(
+    BorshSerialize, BorshDeserialize, Serialize, Deserialize, Clone, Copy, Debug, PartialEq,
+)]
+#[serde(crate = "near_sdk::serde")]
+pub struct GasConfig {
+    /// Transfer of a stable asset on withdrawal.
+    pub ft_transfer: Gas,
+    /// Callbacks refunding failed withdrawals and NEAR.
+    pub refund: Gas,
+    /// Callback minting USN for NEAR at the oracle price.
+    pub mint_callback: Gas,
+    /// Deposit of a token to ref.finance.
+    pub ref_ft_transfer_call: Gas,
+    pub ref_add_liquidity: Gas,
+    pub ref_remove_liquidity: Gas,
+    /// Withdrawal of a token from ref.finance.
+    pub ref_withdraw: Gas,
+    /// Storage balance views of a stable asset on withdrawal.
+    pub storage_view: Gas,
+    /// Registration of the receiver on a stable asset on withdrawal.
+    pub storage_deposit: Gas,
+    /// View of the contract account in a staking pool.
+    pub staking_view: Gas,
+    pub stake: Gas,
+    pub unstake: Gas,
+    /// Withdrawal of the unstaked NEAR from a staking pool.
+    pub staking_withdraw: Gas,
+}
+
+impl Default for GasConfig {
+    fn default() -> Self {
+        Self {
+            ft_transfer: Gas(25_000_000_000_000),
+            refund: Gas(5_000_000_000_000),
+            mint_callback: Gas(10_000_000_000_000),
+            ref_ft_transfer_call: Gas(45_000_000_000_000),
+            ref_add_liquidity: Gas(17_000_000_000_000),
+            ref_remove_liquidity: Gas(17_000_000_000_000),
+            ref_withdraw: Gas(55_000_000_000_000),
+            storage_view: Gas(5_000_000_000_000),
+            storage_deposit: Gas(10_000_000_000_000),
+            staking_view: Gas(7_000_000_000_000),
+            stake: Gas(35_000_000_000_000),
+            unstake: Gas(35_000_000_000_000),
+            staking_withdraw: Gas(35_000_000_000_000),
+        }
+    }
+}
+
+impl GasConfig {
+    fn values(&self) -> [Gas; 13] {
+        [
+            self.ft_transfer,
+            self.refund,
+            self.mint_callback,
+            self.ref_ft_transfer_call,
+            self.ref_add_liquidity,
+            self.ref_remove_liquidity,
+            self.ref_withdraw,
+            self.storage_view,
+            self.storage_deposit,
+            self.staking_view,
+            self.stake,
+            self.unstake,
+            self.staking_withdraw,
+        ]
+    }
+
+    /// Each value is from a half to 4 times its default.
+    pub fn is_valid(&self) -> bool {
+        self.values()
+            .iter()
+            .zip(Self::default().values().iter())
+            .all(|(gas, default)| gas.0 >= default.0 / 2 && gas.0 <= default.0 * 4)
+    }
+
+    pub fn assert_valid(&self) {
+        require!(self.is_valid(), "Invalid gas config");
+    }
+}
+
+/// Logs the gas used by the call when dropped, if built with the `gas_profiling` feature.
+/// A callback keeps it for its whole body to check the gas configured for it.
+pub struct GasProfile {
+    #[cfg_attr(not(feature = "gas_profiling"), allow(dead_code))]
+    method: &'static str,
+}
+
+impl GasProfile {
+    pub fn start(method: &'static str) -> Self {
+        Self { method }
+    }
+}
+
+#[cfg(feature = "gas_profiling")]
+impl Drop for GasProfile {
+    fn drop(&mut self) {
+        event::emit::gas_profile(self.method, env::used_gas(), env::prepaid_gas());
+    }
+}
+
+#[near_bindgen]
+impl Contract {
+    /// Only can be called by owner.
+    pub fn set_gas_config(&mut self, config: GasConfig) {
+        self.assert_owner("set_gas_config");
+        config.assert_valid();
+        event::emit::gas_config(&config);
+        self.gas_config = config;
+    }
+
+    pub fn gas_config(&self) -> GasConfig {
+        self.gas_config
+    }
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use super::*;
+    use crate::testing::setup_contract;
+    use near_sdk::test_utils::accounts;
+
+    #[test]
+    fn test_set_gas_config() {
+        let (_, mut contract) = setup_contract(accounts(1));
+        let mut config = contract.gas_config();
+        config.ft_transfer = Gas(40_000_000_000_000);
+        contract.set_gas_config(config);
+        assert_eq!(contract.gas_config(), config);
+    }
+
+    #[test]
+    #[should_panic(expected = "Invalid gas config")]
+    fn test_set_gas_config_out_of_bounds() {
+        let (_, mut contract) = setup_contract(accounts(1));
+        let mut config = contract.gas_config();
+        config.refund = Gas(1_000_000_000_000);
+        contract.set_gas_config(config);
+    }
+
+    #[test]
+    #[should_panic(expected = "Invalid gas config")]
+    fn test_set_staking_gas_out_of_bounds() {
+        let (_, mut contract) = setup_contract(accounts(1));
+        let mut config = contract.gas_config();
+        config.stake = Gas(150_000_000_000_000);
+        contract.set_gas_config(config);
+    }
+}
@@ -0,0 +1,327 @@
+//! M-of-N confirmation of guardian actions.
+//!
+//! When a policy is set, an action of a guardian with at least `min_severity`
+//! is executed only after `threshold` distinct guardians confirmed it within
+//! the `window`. The owner's actions are executed immediately.
+
+use crate::*;
+
+const MAX_WINDOW_SEC: u64 = 7 * 24 * 60 * 60;
+
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(crate = "near_sdk::serde")]
+pub enum GuardianAction {
+    Pause,
+    FreezeAccount {
+        account_id: AccountId,
+        duration: u64,
+    },
+}
+
+#[derive(
+    BorshDeserialize,
+    BorshSerialize,
+    Serialize,
+    Deserialize,
+    Clone,
+    Copy,
+    Debug,
+    PartialEq,
+    PartialOrd,
+)]
+#[serde(crate = "near_sdk::serde")]
+pub enum Severity {
+    Low,
+    Medium,
+    High,
+}
+
+impl GuardianAction {
+    pub fn severity(&self) -> Severity {
+        match self {
+            GuardianAction::Pause => Severity::High,
+            GuardianAction::FreezeAccount { .. } => Severity::Medium,
+        }
+    }
+
+    fn assert_valid(&self) {
+        if let GuardianAction::FreezeAccount { duration, .. } = self {
+            assert!(
+                *duration > 0 && *duration <= MAX_FREEZE_DURATION_SEC,
+                "Freeze duration is out of bounds"
+            );
+        }
+    }
+}
+
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(crate = "near_sdk::serde")]
+pub struct GuardianPolicy {
+    /// Number of distinct guardians to confirm an action.
+    pub threshold: u8,
+    pub min_severity: Severity,
+    /// Seconds since the first confirmation during which the action can be confirmed,
+    /// at most 7 days.
+    pub window: U64,
+}
+
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct PendingGuardianAction {
+    pub confirmations: Vec<AccountId>,
+    /// Timestamp in nanoseconds.
+    pub expires_at: U64,
+}
+
+#[near_bindgen]
+impl Contract {
+    /// Sets or removes the confirmation policy of guardian actions. Only can be called by owner.
+    pub fn set_guardian_policy(&mut self, policy: Option<GuardianPolicy>) {
+        self.assert_owner("set_guardian_policy");
+        if let Some(policy) = policy.as_ref() {
+            assert!(
+                policy.threshold > 0 && policy.threshold as u64 <= self.guardians.len(),
+                "The threshold is out of bounds"
+            );
+            assert!(policy.window.0 > 0, "The window should be positive");
+            assert!(
+                policy.window.0 <= MAX_WINDOW_SEC,
+                "The window should be at most {} seconds",
+                MAX_WINDOW_SEC
+            );
+        }
+        self.guardian_policy = policy;
+        self.pending_guardian_actions.clear();
+    }
+
+    pub fn guardian_policy(&self) -> Option<GuardianPolicy> {
+        self.guardian_policy.clone()
+    }
+
+    pub fn pending_guardian_actions(&self) -> Vec<(GuardianAction, PendingGuardianAction)> {
+        self.pending_guardian_actions.to_vec()
+    }
+
+    /// Confirms the action, it's executed once enough guardians confirmed it.
+    /// Only can be called by owner or guardians.
+    #[payable]
+    pub fn confirm_guardian_action(&mut self, action: GuardianAction) {
+        assert_one_yocto();
+        self.internal_guardian_action(action, "confirm_guardian_action");
+    }
+
+    /// Revokes the caller's confirmation of the pending action.
+    #[payable]
+    pub fn revoke_guardian_confirmation(&mut self, action: GuardianAction) {
+        assert_one_yocto();
+        let guardian_id = env::predecessor_account_id();
+        let mut pending = self
+            .pending_guardian_actions
+            .get(&action)
+            .unwrap_or_else(|| env::panic_str("The action is not pending"));
+        let len = pending.confirmations.len();
+        pending.confirmations.retain(|id| id != &guardian_id);
+        assert!(
+            pending.confirmations.len() < len,
+            "The action is not confirmed by the caller"
+        );
+        if pending.confirmations.is_empty() {
+            self.pending_guardian_actions.remove(&action);
+        } else {
+            self.pending_guardian_actions.insert(&action, &pending);
+        }
+        event::emit::guardian_action_revoke(&action, &guardian_id);
+    }
+
+    /// Executes the action of owner or guardian if it doesn't need or has enough confirmations.
+    /// `method` is recorded in the admin log.
+    pub(crate) fn internal_guardian_action(&mut self, action: GuardianAction, method: &str) {
+        self.assert_owner_or_guardian(method);
+        action.assert_valid();
+        if self.confirm(&action) {
+            match action {
+                GuardianAction::Pause => self.internal_pause(),
+                GuardianAction::FreezeAccount {
+                    account_id,
+                    duration,
+                } => self.internal_freeze_account(&account_id, duration),
+            }
+        }
+    }
+
+    /// Records the caller's confirmation, returns true if the action should be executed.
+    fn confirm(&mut self, action: &GuardianAction) -> bool {
+        let guardian_id = env::predecessor_account_id();
+        let policy = match self.guardian_policy.as_ref() {
+            Some(policy)
+                if guardian_id != self.owner_id && action.severity() >= policy.min_severity =>
+            {
+                policy.clone()
+            }
+            _ => return true,
+        };
+
+        let now = env::block_timestamp();
+        let expires_at = policy
+            .window
+            .0
+            .checked_mul(10u64.pow(9))
+            .and_then(|window| now.checked_add(window))
+            .unwrap_or_else(|| rounding::overflow("guardian::confirm", "Addition overflow"));
+        let mut pending = self
+            .pending_guardian_actions
+            .get(action)
+            .filter(|pending| pending.expires_at.0 > now)
+            .unwrap_or_else(|| PendingGuardianAction {
+                confirmations: vec![],
+                expires_at: expires_at.into(),
+            });
+        // Confirmations of removed guardians don't count.
+        pending
+            .confirmations
+            .retain(|id| self.guardians.contains(id));
+        assert!(
+            !pending.confirmations.contains(&guardian_id),
+            "The action is already confirmed by the caller"
+        );
+        pending.confirmations.push(guardian_id.clone());
+        event::emit::guardian_action_confirm(action, &guardian_id, pending.confirmations.len());
+
+        if pending.confirmations.len() >= policy.threshold as usize {
+            self.pending_guardian_actions.remove(action);
+            true
+        } else {
+            self.pending_guardian_actions.insert(action, &pending);
+            false
+        }
+    }
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use super::*;
+    use crate::testing::setup_contract;
+    use near_sdk::test_utils::{accounts, VMContextBuilder};
+    use near_sdk::{testing_env, ONE_YOCTO};
+
+    fn setup_guardians() -> (VMContextBuilder, Contract) {
+        let (mut context, mut contract) = setup_contract(accounts(0));
+        testing_env!(context.attached_deposit(ONE_YOCTO).build());
+        contract.extend_guardians(vec![accounts(1), accounts(2), accounts(3)]);
+        contract.set_guardian_policy(Some(GuardianPolicy {
+            threshold: 2,
+            min_severity: Severity::High,
+            window: U64(60),
+        }));
+        (context, contract)
+    }
+
+    #[test]
+    fn test_pause_requires_confirmations() {
+        let (mut context, mut contract) = setup_guardians();
+
+        testing_env!(context.predecessor_account_id(accounts(1)).build());
+        contract.pause();
+        assert_eq!(contract.contract_status(), ContractStatus::Working);
+        assert_eq!(contract.pending_guardian_actions().len(), 1);
+
+        testing_env!(context.predecessor_account_id(accounts(2)).build());
+        contract.confirm_guardian_action(GuardianAction::Pause);
+        assert_eq!(contract.contract_status(), ContractStatus::Paused);
+        assert!(contract.pending_guardian_actions().is_empty());
+    }
+
+    #[test]
+    fn test_confirmation_window() {
+        let (mut context, mut contract) = setup_guardians();
+
+        testing_env!(context.predecessor_account_id(accounts(1)).build());
+        contract.pause();
+
+        // The first confirmation has expired.
+        testing_env!(context
+            .predecessor_account_id(accounts(2))
+            .block_timestamp(61_000_000_000)
+            .build());
+        contract.pause();
+        assert_eq!(contract.contract_status(), ContractStatus::Working);
+        assert_eq!(
+            contract.pending_guardian_actions()[0].1.confirmations,
+            vec![accounts(2)]
+        );
+    }
+
+    #[test]
+    fn test_revoke_confirmation() {
+        let (mut context, mut contract) = setup_guardians();
+
+        testing_env!(context.predecessor_account_id(accounts(1)).build());
+        contract.pause();
+        contract.revoke_guardian_confirmation(GuardianAction::Pause);
+        assert!(contract.pending_guardian_actions().is_empty());
+
+        testing_env!(context.predecessor_account_id(accounts(2)).build());
+        contract.pause();
+        assert_eq!(contract.contract_status(), ContractStatus::Working);
+    }
+
+    #[test]
+    #[should_panic(expected = "The action is already confirmed by the caller")]
+    fn test_confirm_twice() {
+        let (mut context, mut contract) = setup_guardians();
+
+        testing_env!(context.predecessor_account_id(accounts(1)).build());
+        contract.pause();
+        contract.pause();
+    }
+
+    #[test]
+    fn test_removed_guardian_confirmation() {
+        let (mut context, mut contract) = setup_guardians();
+
+        testing_env!(context.predecessor_account_id(accounts(1)).build());
+        contract.pause();
+
+        testing_env!(context.predecessor_account_id(accounts(0)).build());
+        contract.remove_guardians(vec![accounts(1)]);
+
+        testing_env!(context.predecessor_account_id(accounts(2)).build());
+        contract.pause();
+        assert_eq!(contract.contract_status(), ContractStatus::Working);
+        assert_eq!(
+            contract.pending_guardian_actions()[0].1.confirmations,
+            vec![accounts(2)]
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "The window should be at most 604800 seconds")]
+    fn test_guardian_policy_window_too_long() {
+        let (_, mut contract) = setup_guardians();
+        contract.set_guardian_policy(Some(GuardianPolicy {
+            threshold: 2,
+            min_severity: Severity::High,
+            window: U64(u64::MAX / 10u64.pow(9)),
+        }));
+    }
+
+    #[test]
+    #[should_panic(expected = "The guardian policy threshold would be unreachable")]
+    fn test_remove_guardians_below_threshold() {
+        let (_, mut contract) = setup_guardians();
+        contract.remove_guardians(vec![accounts(1), accounts(2)]);
+    }
+
+    #[test]
+    fn test_low_severity_and_owner_actions() {
+        let (mut context, mut contract) = setup_guardians();
+
+        testing_env!(context.predecessor_account_id(accounts(1)).build());
+        contract.freeze_account(accounts(4), 60);
+        assert!(contract.frozen_until(accounts(4)).is_some());
+
+        testing_env!(context.predecessor_account_id(accounts(0)).build());
+        contract.pause();
+        assert_eq!(contract.contract_status(), ContractStatus::Paused);
+    }
+}
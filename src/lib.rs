@@ -1,12 +1,34 @@
 #![deny(warnings)]
+mod admin_log;
+mod backing;
+mod bridge;
+mod claims;
+mod compliance;
+mod delegate;
+mod diagnostics;
 mod event;
 mod ft;
+mod gas_config;
+mod guardian;
+mod limits;
+mod migration;
+mod minters;
 mod oracle;
 mod owner;
+mod peg;
+mod reconcile;
+mod reserves;
+mod revenue;
+mod rounding;
 mod stable;
 mod staking;
 mod storage;
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod testing;
 mod treasury;
+mod upgrade;
+mod vesting;
+mod wrapped;
 
 use near_contract_standards::fungible_token::core::FungibleTokenCore;
 use near_contract_standards::fungible_token::metadata::{
@@ -14,20 +36,40 @@ use near_contract_standards::fungible_token::metadata::{
 };
 use near_contract_standards::fungible_token::receiver::FungibleTokenReceiver;
 use near_contract_standards::fungible_token::resolver::FungibleTokenResolver;
+use near_contract_standards::storage_management::{StorageBalance, StorageBalanceBounds};
 use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
-use near_sdk::collections::{LazyOption, LookupMap, UnorderedSet};
-use near_sdk::json_types::U128;
-use near_sdk::serde::{Deserialize, Serialize};
+use near_sdk::collections::{LazyOption, LookupMap, LookupSet, UnorderedMap, UnorderedSet, Vector};
+use near_sdk::json_types::{U128, U64};
+use near_sdk::serde::{de::DeserializeOwned, Deserialize, Serialize};
 use near_sdk::{
-    assert_one_yocto, env, ext_contract, is_promise_success, near_bindgen, sys, AccountId, Balance,
-    BorshStorageKey, Gas, PanicOnDefault, Promise, PromiseOrValue, ONE_YOCTO,
+    assert_one_yocto, env, ext_contract, is_promise_success, near_bindgen, AccountId, Balance,
+    BorshStorageKey, CryptoHash, Gas, PanicOnDefault, Promise, PromiseOrValue, PromiseResult,
+    PublicKey, Timestamp, ONE_YOCTO,
 };
-use oracle::{ExchangeRate, Oracle, PriceData};
+use oracle::{ExchangeRate, Oracle, OracleConfig, PriceData, RatePolicy, RateSource};
 
 use std::fmt::Debug;
 
 use crate::ft::FungibleTokenFreeStorage;
-use stable::{usdt_id, AssetInfo, CommissionRate, StableTreasury};
+use claims::Airdrop;
+use compliance::{ComplianceRuleConfig, DailyTransfers};
+use gas_config::{GasConfig, GasProfile};
+use guardian::{GuardianAction, GuardianPolicy, PendingGuardianAction};
+use limits::BlacklistLimiter;
+use minters::Minter;
+use owner::Recovery;
+use reserves::{ReserveSnapshot, ReserveSnapshots};
+use revenue::Revenue;
+use stable::{
+    commission_epoch, usdt_id, AssetInfo, AssetLimits, AssetPeg, CommissionEpoch, CommissionRate,
+    StableQuote, StableTreasury, WithdrawRefundMode,
+};
+use staking::StakingPosition;
+use treasury::{
+    LockedOperation, LpPosition, PoolInfo, PoolSnapshot, SimpleLiquidity, TreasuryJournal,
+    TreasuryOperation,
+};
+use vesting::VestingSchedule;
 
 uint::construct_uint!(
     pub struct U256(4);
@@ -35,12 +77,18 @@ uint::construct_uint!(
 
 const NO_DEPOSIT: Balance = 0;
 const USN_DECIMALS: u8 = 18;
-const GAS_FOR_REFUND_PROMISE: Gas = Gas(5_000_000_000_000);
-const GAS_FOR_FT_TRANSFER: Gas = Gas(25_000_000_000_000);
-const GAS_FOR_BUY_PROMISE: Gas = Gas(10_000_000_000_000);
+const GAS_FOR_WITHDRAW_REGISTRATION_CALLBACK: Gas = Gas(5_000_000_000_000);
+/// Gas of `handle_withdraw_storage` besides the calls it makes.
+const GAS_FOR_WITHDRAW_STORAGE_CALLBACK: Gas = Gas(15_000_000_000_000);
+const GAS_FOR_REJECTION: Gas = Gas(5_000_000_000_000);
 const MIN_COLLATERAL_RATIO: u32 = 100;
 const MAX_COLLATERAL_RATIO: u32 = 1000;
 const PERCENT_MULTIPLIER: u128 = 100;
+const MAX_FREEZE_DURATION_SEC: u64 = 30 * 24 * 60 * 60;
+/// Each leg of `withdraw_multi` takes the `ft_transfer` and `refund` gas.
+const MAX_WITHDRAW_LEGS: usize = 3;
+const DEFAULT_COMMISSION_HISTORY: u64 = 12;
+const MAX_COMMISSION_HISTORY: u64 = 60;
 
 #[derive(BorshStorageKey, BorshSerialize)]
 enum StorageKey {
@@ -50,6 +98,31 @@ enum StorageKey {
     Blacklist,
     _TreasuryData,
     StableTreasury,
+    TransferNonces,
+    SessionKeys,
+    Allowances,
+    FrozenAccounts,
+    Operators,
+    PoolRegistry,
+    StakingPools,
+    PendingGuardianActions,
+    TreasuryJournal,
+    ComplianceRules,
+    AccountTags,
+    DailyTransfers,
+    Airdrops,
+    AirdropClaims,
+    Vesting,
+    LpPositions,
+    SimpleLiquidity,
+    Minters,
+    EthProofs,
+    WusnAccounts,
+    #[cfg_attr(not(feature = "account_stats"), allow(dead_code))]
+    AccountStats,
+    ReserveSnapshots,
+    AdminLog,
+    OperationLocks,
 }
 
 #[derive(BorshDeserialize, BorshSerialize, Clone, Eq, PartialEq, Debug, Serialize, Deserialize)]
@@ -113,6 +186,9 @@ impl From<CommissionV1> for CommissionV1Output {
 #[serde(crate = "near_sdk::serde")]
 pub struct CommissionV2Output {
     usn: U128,
+    lifetime: CommissionEpoch,
+    epoch: U64,
+    current_epoch: CommissionEpoch,
 }
 
 impl From<&StableTreasury> for CommissionV2Output {
@@ -122,8 +198,12 @@ impl From<&StableTreasury> for CommissionV2Output {
             .iter()
             .map(|asset| asset.1.commission().0)
             .sum();
+        let epoch = commission_epoch();
         Self {
             usn: commission.into(),
+            lifetime: treasury.lifetime_commission(),
+            epoch: epoch.into(),
+            current_epoch: treasury.commission_epoch(epoch),
         }
     }
 }
@@ -135,6 +215,41 @@ pub struct CommissionOutput {
     v2: CommissionV2Output,
 }
 
+/// Compile-time configuration of the build.
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+#[serde(crate = "near_sdk::serde")]
+pub struct BuildInfo {
+    pub network: String,
+    pub git_commit: String,
+    pub usdt_id: AccountId,
+    pub oracle_id: String,
+    pub oracle_asset_id: String,
+    pub ref_id: String,
+}
+
+/// A snapshot of the whole protocol state for dashboards.
+#[derive(Serialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct ProtocolStatus {
+    pub version: String,
+    pub status: ContractStatus,
+    pub owner_id: AccountId,
+    pub guardians: Vec<AccountId>,
+    pub total_supply: U128,
+    pub treasury: Vec<(AccountId, AssetInfo)>,
+    pub commission: CommissionOutput,
+    pub exchange_rate: Option<ExchangeRate>,
+    pub pool: Option<PoolSnapshot>,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct CommissionTransfer {
+    pub receiver_id: AccountId,
+    pub amount: U128,
+    pub memo: Option<String>,
+}
+
 impl CommissionOutput {
     pub fn new(v1: CommissionV1Output, v2: CommissionV2Output) -> Self {
         Self { v1, v2 }
@@ -154,6 +269,44 @@ pub struct Contract {
     commission: CommissionV1,
     stable_treasury: StableTreasury,
     oracle: Oracle,
+    transfer_nonces: LookupMap<AccountId, u64>,
+    session_keys: LookupMap<AccountId, Vec<PublicKey>>,
+    allowances: LookupMap<(AccountId, AccountId), Balance>,
+    frozen_accounts: LookupMap<AccountId, Timestamp>,
+    pool_snapshot: Option<PoolSnapshot>,
+    operators: LookupMap<AccountId, Vec<String>>,
+    pool_registry: UnorderedMap<u64, PoolInfo>,
+    staking_pools: UnorderedMap<AccountId, StakingPosition>,
+    revenue: Revenue,
+    guardian_policy: Option<GuardianPolicy>,
+    pending_guardian_actions: UnorderedMap<GuardianAction, PendingGuardianAction>,
+    recovery: Recovery,
+    treasury_journal: TreasuryJournal,
+    withdraw_refund_mode: WithdrawRefundMode,
+    compliance_rules: UnorderedMap<String, ComplianceRuleConfig>,
+    account_tags: LookupMap<AccountId, Vec<String>>,
+    daily_transfers: LookupMap<AccountId, DailyTransfers>,
+    airdrops: Vector<Airdrop>,
+    airdrop_claims: LookupMap<(u64, u64), u64>,
+    vesting: LookupMap<AccountId, VestingSchedule>,
+    blacklist_limiter: BlacklistLimiter,
+    lp_positions: UnorderedMap<u64, LpPosition>,
+    simple_liquidity: UnorderedMap<u64, SimpleLiquidity>,
+    minters: UnorderedMap<AccountId, Minter>,
+    eth_connector: Option<AccountId>,
+    eth_transfer_nonce: u64,
+    eth_proofs: LookupSet<CryptoHash>,
+    wusn_accounts: LookupMap<AccountId, Balance>,
+    wusn_total_supply: Balance,
+    backing_floor: Option<u32>,
+    reserve_snapshots: ReserveSnapshots,
+    oracle_config: OracleConfig,
+    rate_policy: RatePolicy,
+    operation_locks: UnorderedMap<LockedOperation, Timestamp>,
+    vesting_escrow: Balance,
+    gas_config: GasConfig,
+    airdrop_escrow: Balance,
+    vesting_escrow_synced: bool,
 }
 
 const DATA_IMAGE_SVG_NEAR_ICON: &str =
@@ -167,6 +320,18 @@ pub trait FtApi {
         amount: U128,
         memo: Option<String>,
     ) -> PromiseOrValue<U128>;
+
+    fn ft_balance_of(&self, account_id: AccountId) -> U128;
+
+    fn storage_balance_of(&self, account_id: AccountId) -> Option<StorageBalance>;
+
+    fn storage_balance_bounds(&self) -> StorageBalanceBounds;
+
+    fn storage_deposit(
+        &mut self,
+        account_id: Option<AccountId>,
+        registration_only: Option<bool>,
+    ) -> StorageBalance;
 }
 
 #[ext_contract(ext_self)]
@@ -183,7 +348,47 @@ trait ContractCallback {
     fn handle_refund(&mut self, attached_deposit: U128);
 
     #[private]
-    fn handle_withdraw_refund(&mut self, account_id: AccountId, token_id: AccountId, amount: U128);
+    fn handle_withdraw_refund(
+        &mut self,
+        account_id: AccountId,
+        token_id: AccountId,
+        amount: U128,
+        asset_amount: U128,
+    );
+
+    #[private]
+    fn handle_withdraw_storage(
+        &mut self,
+        account_id: AccountId,
+        asset_id: AccountId,
+        amount: U128,
+        asset_amount: U128,
+        storage_deposit: U128,
+    ) -> Promise;
+
+    #[private]
+    fn handle_withdraw_registration(
+        &mut self,
+        account_id: AccountId,
+        asset_id: AccountId,
+        amount: U128,
+        asset_amount: U128,
+        storage_deposit: U128,
+    ) -> Promise;
+
+    #[private]
+    fn handle_commission_refund(&mut self, asset_id: AccountId, amount: U128);
+
+    #[private]
+    fn handle_failed_withdrawal_claim(
+        &mut self,
+        account_id: AccountId,
+        asset_id: AccountId,
+        asset_amount: U128,
+    );
+
+    #[private]
+    fn fail_rejected(&self, reason: String);
 }
 
 trait ContractCallback {
@@ -196,7 +401,42 @@ trait ContractCallback {
 
     fn handle_refund(&mut self, attached_deposit: U128);
 
-    fn handle_withdraw_refund(&mut self, account_id: AccountId, token_id: AccountId, amount: U128);
+    fn handle_withdraw_refund(
+        &mut self,
+        account_id: AccountId,
+        token_id: AccountId,
+        amount: U128,
+        asset_amount: U128,
+    );
+
+    fn handle_withdraw_storage(
+        &mut self,
+        account_id: AccountId,
+        asset_id: AccountId,
+        amount: U128,
+        asset_amount: U128,
+        storage_deposit: U128,
+    ) -> Promise;
+
+    fn handle_withdraw_registration(
+        &mut self,
+        account_id: AccountId,
+        asset_id: AccountId,
+        amount: U128,
+        asset_amount: U128,
+        storage_deposit: U128,
+    ) -> Promise;
+
+    fn handle_commission_refund(&mut self, asset_id: AccountId, amount: U128);
+
+    fn handle_failed_withdrawal_claim(
+        &mut self,
+        account_id: AccountId,
+        asset_id: AccountId,
+        asset_amount: U128,
+    );
+
+    fn fail_rejected(&self, reason: String);
 }
 
 #[near_bindgen]
@@ -208,15 +448,20 @@ impl ContractCallback for Contract {
         collateral_ratio: u32,
         #[callback] price: PriceData,
     ) -> U128 {
-        let rate: ExchangeRate = price.into();
+        let _profile = GasProfile::start("mint_with_price_callback");
+        let rates = self.oracle_config.exchange_rates(price);
         assert!(near.0 > 0, "Amount should be positive");
+        let (rate, source) = rates.mint_rate(self.rate_policy);
+        event::emit::oracle_rate(rate, source);
+        self.oracle.last_report = Some(rates.current().clone());
 
-        self.finish_mint_by_near(near.0, rate, collateral_ratio)
+        self.finish_mint_by_near(near.0, rate.clone(), collateral_ratio)
             .into()
     }
 
     #[private]
     fn handle_refund(&mut self, attached_deposit: U128) {
+        let _profile = GasProfile::start("handle_refund");
         if !is_promise_success() {
             Promise::new(self.owner_id.clone())
                 .transfer(attached_deposit.0)
@@ -225,16 +470,122 @@ impl ContractCallback for Contract {
     }
 
     #[private]
-    fn handle_withdraw_refund(&mut self, account_id: AccountId, token_id: AccountId, amount: U128) {
+    fn handle_withdraw_refund(
+        &mut self,
+        account_id: AccountId,
+        token_id: AccountId,
+        amount: U128,
+        asset_amount: U128,
+    ) {
+        let _profile = GasProfile::start("handle_withdraw_refund");
         if !is_promise_success() {
+            self.internal_withdraw_refund(account_id, token_id, amount, asset_amount);
+        }
+    }
+
+    /// Registers the receiver on the asset token if needed and transfers the withdrawn asset.
+    /// Never panics: the USN is already burned, failures are handled by `handle_withdraw_refund`.
+    #[private]
+    fn handle_withdraw_storage(
+        &mut self,
+        account_id: AccountId,
+        asset_id: AccountId,
+        amount: U128,
+        asset_amount: U128,
+        storage_deposit: U128,
+    ) -> Promise {
+        let _profile = GasProfile::start("handle_withdraw_storage");
+        let registered = promise_result_json::<Option<StorageBalance>>(0)
+            .map(|balance| balance.is_some())
+            .unwrap_or(true);
+        let min_deposit = promise_result_json::<StorageBalanceBounds>(1).map(|bounds| bounds.min.0);
+
+        let required_deposit = match min_deposit {
+            Some(min_deposit) if !registered && min_deposit <= storage_deposit.0 => min_deposit,
+            _ => 0,
+        };
+        let unused_deposit = storage_deposit.0 - required_deposit;
+        if unused_deposit > 0 {
+            Promise::new(account_id.clone()).transfer(unused_deposit);
+        }
+
+        if required_deposit > 0 {
+            ext_ft_api::storage_deposit(
+                Some(account_id.clone()),
+                Some(true),
+                asset_id.clone(),
+                required_deposit,
+                self.gas_config.storage_deposit,
+            )
+            .then(ext_self::handle_withdraw_registration(
+                account_id,
+                asset_id,
+                amount,
+                asset_amount,
+                required_deposit.into(),
+                env::current_account_id(),
+                NO_DEPOSIT,
+                GAS_FOR_WITHDRAW_REGISTRATION_CALLBACK
+                    + self.gas_config.ft_transfer
+                    + self.gas_config.refund,
+            ))
+        } else {
+            self.internal_withdraw_transfer(account_id, asset_id, amount, asset_amount.0)
+        }
+    }
+
+    /// Transfers the withdrawn asset once the receiver is registered on the asset token.
+    /// Otherwise refunds the withdrawal and the storage deposit.
+    #[private]
+    fn handle_withdraw_registration(
+        &mut self,
+        account_id: AccountId,
+        asset_id: AccountId,
+        amount: U128,
+        asset_amount: U128,
+        storage_deposit: U128,
+    ) -> Promise {
+        let _profile = GasProfile::start("handle_withdraw_registration");
+        if is_promise_success() {
+            self.internal_withdraw_transfer(account_id, asset_id, amount, asset_amount.0)
+        } else {
+            self.internal_withdraw_refund(account_id.clone(), asset_id, amount, asset_amount);
+            Promise::new(account_id).transfer(storage_deposit.0)
+        }
+    }
+
+    #[private]
+    fn handle_commission_refund(&mut self, asset_id: AccountId, amount: U128) {
+        if is_promise_success() {
             self.stable_treasury
-                .refund(&mut self.token, &account_id, &token_id, amount.into());
+                .record_commission_transfer(amount.into());
+        } else {
+            self.stable_treasury
+                .restore_commission(&asset_id, amount.into());
             env::log_str(&format!(
-                "Refund ${} of USN to {} after {} error",
-                amount.0, account_id, token_id,
+                "Restore ${} of {} commission after transfer error",
+                amount.0, asset_id,
             ));
         }
     }
+
+    #[private]
+    fn handle_failed_withdrawal_claim(
+        &mut self,
+        account_id: AccountId,
+        asset_id: AccountId,
+        asset_amount: U128,
+    ) {
+        if !is_promise_success() {
+            self.stable_treasury
+                .add_failed_withdrawal(&account_id, &asset_id, asset_amount.into());
+        }
+    }
+
+    #[private]
+    fn fail_rejected(&self, reason: String) {
+        env::panic_str(&reason)
+    }
 }
 
 #[near_bindgen]
@@ -263,13 +614,52 @@ impl Contract {
             commission: CommissionV1::default(),
             stable_treasury: StableTreasury::new(StorageKey::StableTreasury),
             oracle: Oracle::default(),
+            transfer_nonces: LookupMap::new(StorageKey::TransferNonces),
+            session_keys: LookupMap::new(StorageKey::SessionKeys),
+            allowances: LookupMap::new(StorageKey::Allowances),
+            frozen_accounts: LookupMap::new(StorageKey::FrozenAccounts),
+            pool_snapshot: None,
+            operators: LookupMap::new(StorageKey::Operators),
+            pool_registry: treasury::pool_registry(StorageKey::PoolRegistry),
+            staking_pools: UnorderedMap::new(StorageKey::StakingPools),
+            revenue: Revenue::default(),
+            guardian_policy: None,
+            pending_guardian_actions: UnorderedMap::new(StorageKey::PendingGuardianActions),
+            recovery: Recovery::default(),
+            treasury_journal: TreasuryJournal::new(StorageKey::TreasuryJournal),
+            withdraw_refund_mode: WithdrawRefundMode::default(),
+            compliance_rules: UnorderedMap::new(StorageKey::ComplianceRules),
+            account_tags: LookupMap::new(StorageKey::AccountTags),
+            daily_transfers: LookupMap::new(StorageKey::DailyTransfers),
+            airdrops: Vector::new(StorageKey::Airdrops),
+            airdrop_claims: LookupMap::new(StorageKey::AirdropClaims),
+            vesting: LookupMap::new(StorageKey::Vesting),
+            blacklist_limiter: BlacklistLimiter::default(),
+            lp_positions: UnorderedMap::new(StorageKey::LpPositions),
+            simple_liquidity: UnorderedMap::new(StorageKey::SimpleLiquidity),
+            minters: UnorderedMap::new(StorageKey::Minters),
+            eth_connector: None,
+            eth_transfer_nonce: 0,
+            eth_proofs: LookupSet::new(StorageKey::EthProofs),
+            wusn_accounts: LookupMap::new(StorageKey::WusnAccounts),
+            wusn_total_supply: 0,
+            backing_floor: None,
+            reserve_snapshots: ReserveSnapshots::new(StorageKey::ReserveSnapshots),
+            oracle_config: OracleConfig::default(),
+            rate_policy: RatePolicy::default(),
+            operation_locks: UnorderedMap::new(StorageKey::OperationLocks),
+            vesting_escrow: 0,
+            gas_config: GasConfig::default(),
+            airdrop_escrow: 0,
+            vesting_escrow_synced: true,
         };
 
+        migration::set_state_version(migration::STATE_VERSION);
         this
     }
 
     pub fn upgrade_name_symbol(&mut self, name: String, symbol: String) {
-        self.assert_owner();
+        self.assert_owner("upgrade_name_symbol");
         let mut metadata = self.metadata.take().unwrap();
         metadata.name = name;
         metadata.symbol = symbol;
@@ -277,7 +667,7 @@ impl Contract {
     }
 
     pub fn upgrade_icon(&mut self, data: String) {
-        self.assert_owner();
+        self.assert_owner("upgrade_icon");
         let mut metadata = self.metadata.take().unwrap();
         metadata.icon = Some(data);
         self.metadata.replace(&metadata);
@@ -291,42 +681,97 @@ impl Contract {
     }
 
     pub fn add_to_blacklist(&mut self, account_id: &AccountId) {
-        self.assert_owner();
+        self.assert_owner("add_to_blacklist");
+        if self.blacklist_status(account_id) != BlackListStatus::Banned {
+            if let Some(rejection) = self.blacklist_limiter.use_accounts() {
+                rejection.as_return();
+                return;
+            }
+        }
         self.black_list.insert(account_id, &BlackListStatus::Banned);
+        event::emit::blacklist_add(account_id);
     }
 
     pub fn remove_from_blacklist(&mut self, account_id: &AccountId) {
-        self.assert_owner();
+        self.assert_owner("remove_from_blacklist");
         self.black_list.remove(account_id);
+        event::emit::blacklist_remove(account_id);
+    }
+
+    /// Freezes the account for `duration` seconds: it can't transfer or withdraw until expiry.
+    /// Unlike the blacklist, it doesn't allow to destroy funds of the account.
+    /// Only can be called by owner or guardians.
+    /// Guardians may need to confirm it together, see `set_guardian_policy`.
+    pub fn freeze_account(&mut self, account_id: AccountId, duration: u64) {
+        self.internal_guardian_action(
+            GuardianAction::FreezeAccount {
+                account_id,
+                duration,
+            },
+            "freeze_account",
+        );
+    }
+
+    fn internal_freeze_account(&mut self, account_id: &AccountId, duration: u64) {
+        let expiry = env::block_timestamp() + duration * 10u64.pow(9);
+        self.frozen_accounts.insert(account_id, &expiry);
+        event::emit::account_freeze(account_id, expiry);
+    }
+
+    /// Unfreezes the account before expiry. Only can be called by owner.
+    pub fn unfreeze_account(&mut self, account_id: AccountId) {
+        self.assert_owner("unfreeze_account");
+        self.frozen_accounts.remove(&account_id);
+        event::emit::account_unfreeze(&account_id);
+    }
+
+    /// Returns the timestamp (in nanoseconds) until which the account is frozen.
+    pub fn frozen_until(&self, account_id: AccountId) -> Option<U64> {
+        self.frozen_accounts
+            .get(&account_id)
+            .filter(|&expiry| expiry > env::block_timestamp())
+            .map(U64::from)
     }
 
     pub fn destroy_black_funds(&mut self, account_id: &AccountId) {
-        self.assert_owner();
+        self.assert_owner("destroy_black_funds");
         assert_eq!(self.blacklist_status(&account_id), BlackListStatus::Banned);
         let black_balance = self.ft_balance_of(account_id.clone());
         if black_balance.0 <= 0 {
             env::panic_str("The account doesn't have enough balance");
         }
+        if let Some(rejection) = self.blacklist_limiter.use_destroyed(black_balance.into()) {
+            rejection.as_return();
+            return;
+        }
         self.token.accounts.insert(account_id, &0u128);
         self.token.total_supply = self
             .token
             .total_supply
             .checked_sub(u128::from(black_balance))
             .expect("Failed to decrease total supply");
+        event::emit::ft_burn(account_id, black_balance.into(), None);
+        event::emit::black_funds_destroy(account_id, black_balance.into());
     }
 
     /// Pauses the contract. Only can be called by owner or guardians.
+    /// Guardians may need to confirm it together, see `set_guardian_policy`.
     #[payable]
     pub fn pause(&mut self) {
         assert_one_yocto();
-        self.assert_owner_or_guardian();
+        self.internal_guardian_action(GuardianAction::Pause, "pause");
+    }
+
+    fn internal_pause(&mut self) {
         self.status = ContractStatus::Paused;
+        event::emit::contract_pause(&env::predecessor_account_id());
     }
 
     /// Resumes the contract. Only can be called by owner.
     pub fn resume(&mut self) {
-        self.assert_owner();
+        self.assert_owner("resume");
         self.status = ContractStatus::Working;
+        event::emit::contract_resume(&env::predecessor_account_id());
     }
 
     pub fn contract_status(&self) -> ContractStatus {
@@ -352,6 +797,26 @@ impl Contract {
         format!("{}:{}", env!("CARGO_PKG_NAME"), env!("CARGO_PKG_VERSION"))
     }
 
+    /// Returns the feature set and external accounts the contract has been built with.
+    pub fn build_info(&self) -> BuildInfo {
+        let network = if cfg!(feature = "mainnet") {
+            "mainnet"
+        } else if cfg!(feature = "testnet") {
+            "testnet"
+        } else {
+            "sandbox"
+        };
+
+        BuildInfo {
+            network: network.to_string(),
+            git_commit: env!("USN_GIT_COMMIT").to_string(),
+            usdt_id: usdt_id(),
+            oracle_id: OracleConfig::default().oracle_id.to_string(),
+            oracle_asset_id: OracleConfig::default().asset_id.to_string(),
+            ref_id: treasury::ref_address().to_string(),
+        }
+    }
+
     pub fn commission(&self) -> CommissionOutput {
         CommissionOutput::new(
             self.commission.clone().into(),
@@ -359,15 +824,44 @@ impl Contract {
         )
     }
 
-    /// This is NOOP implementation. KEEP IT if you haven't changed contract state.
+    /// Returns commission v2 of up to `limit` epochs ending with `to_epoch` (the current by default).
+    pub fn commission_history(
+        &self,
+        to_epoch: Option<U64>,
+        limit: Option<u64>,
+    ) -> Vec<(U64, CommissionEpoch)> {
+        let to_epoch = to_epoch.map_or_else(commission_epoch, |epoch| epoch.0);
+        let limit = limit
+            .unwrap_or(DEFAULT_COMMISSION_HISTORY)
+            .min(MAX_COMMISSION_HISTORY);
+        (to_epoch.saturating_add(1).saturating_sub(limit)..=to_epoch)
+            .map(|epoch| (epoch.into(), self.stable_treasury.commission_epoch(epoch)))
+            .collect()
+    }
+
+    /// Returns the protocol state in a single call.
+    pub fn get_protocol_status(&self) -> ProtocolStatus {
+        ProtocolStatus {
+            version: self.version(),
+            status: self.contract_status(),
+            owner_id: self.owner(),
+            guardians: self.guardians(),
+            total_supply: self.ft_total_supply(),
+            treasury: self.treasury(),
+            commission: self.commission(),
+            exchange_rate: self.oracle.last_report.clone(),
+            pool: self.pool_snapshot(),
+        }
+    }
+
+    /// Migrates the state of any previous version, see `migration::migrate_state`.
     /// Should only be called by this contract on migration.
-    /// This method is called from `upgrade()` method.
-    /// For next version upgrades, change this function.
+    /// This method is called from `commit_upgrade()`.
     #[init(ignore_state)]
     #[private]
     pub fn migrate() -> Self {
-        let contract: Contract = env::state_read().expect("Contract is not initialized");
-        contract
+        upgrade::clear_staged_code();
+        migration::migrate_state()
     }
 
     fn abort_if_pause(&self) {
@@ -380,6 +874,12 @@ impl Contract {
         if self.blacklist_status(account_id) != BlackListStatus::Allowable {
             env::panic_str(&format!("Account '{}' is banned", account_id));
         }
+        if let Some(expiry) = self.frozen_until(account_id.clone()) {
+            env::panic_str(&format!(
+                "Account '{}' is frozen until {}",
+                account_id, expiry.0
+            ));
+        }
     }
 
     fn on_tokens_burned(&mut self, account_id: AccountId, amount: Balance) {
@@ -387,38 +887,23 @@ impl Contract {
     }
 }
 
-#[no_mangle]
-pub fn upgrade() {
-    env::setup_panic_hook();
-
-    let contract: Contract = env::state_read().expect("Contract is not initialized");
-    contract.assert_owner();
-
-    const MIGRATE_METHOD_NAME: &[u8; 7] = b"migrate";
-    const UPDATE_GAS_LEFTOVER: Gas = Gas(5_000_000_000_000);
+/// Returns the promise failing the call with `reason`.
+/// The rejected call returns it instead of panicking, so the receipt succeeds
+/// and keeps the logged rejection event, while the caller still sees the failure.
+fn reject(reason: String) -> Promise {
+    ext_self::fail_rejected(
+        reason,
+        env::current_account_id(),
+        NO_DEPOSIT,
+        GAS_FOR_REJECTION,
+    )
+}
 
-    unsafe {
-        // Load code into register 0 result from the input argument if factory call or from promise if callback.
-        sys::input(0);
-        // Create a promise batch to update current contract with code from register 0.
-        let promise_id = sys::promise_batch_create(
-            env::current_account_id().as_bytes().len() as u64,
-            env::current_account_id().as_bytes().as_ptr() as u64,
-        );
-        // Deploy the contract code from register 0.
-        sys::promise_batch_action_deploy_contract(promise_id, u64::MAX, 0);
-        // Call promise to migrate the state.
-        // Batched together to fail upgrade if migration fails.
-        sys::promise_batch_action_function_call(
-            promise_id,
-            MIGRATE_METHOD_NAME.len() as u64,
-            MIGRATE_METHOD_NAME.as_ptr() as u64,
-            0,
-            0,
-            0,
-            (env::prepaid_gas() - env::used_gas() - UPDATE_GAS_LEFTOVER).0,
-        );
-        sys::promise_return(promise_id);
+/// Deserializes the JSON result of the promise, `None` if it failed or can't be parsed.
+fn promise_result_json<T: DeserializeOwned>(index: u64) -> Option<T> {
+    match env::promise_result(index) {
+        PromiseResult::Successful(data) => near_sdk::serde_json::from_slice(&data).ok(),
+        _ => None,
     }
 }
 
@@ -431,6 +916,13 @@ impl FungibleTokenCore for Contract {
     fn ft_transfer(&mut self, receiver_id: AccountId, amount: U128, memo: Option<String>) {
         self.abort_if_pause();
         self.abort_if_blacklisted(&env::predecessor_account_id());
+        assert_one_yocto();
+        if let Some(rejection) =
+            self.check_compliance(&env::predecessor_account_id(), &receiver_id, amount.into())
+        {
+            rejection.as_return();
+            return;
+        }
         self.token.ft_transfer(receiver_id, amount, memo);
     }
 
@@ -444,6 +936,12 @@ impl FungibleTokenCore for Contract {
     ) -> PromiseOrValue<U128> {
         self.abort_if_pause();
         self.abort_if_blacklisted(&env::predecessor_account_id());
+        assert_one_yocto();
+        if let Some(rejection) =
+            self.check_compliance(&env::predecessor_account_id(), &receiver_id, amount.into())
+        {
+            return PromiseOrValue::Promise(rejection);
+        }
         self.token
             .ft_transfer_call(receiver_id.clone(), amount, memo, msg)
     }
@@ -502,7 +1000,7 @@ impl Contract {
     // Owner only
     #[payable]
     pub fn mint_by_near(&mut self, collateral_ratio: u32) {
-        self.assert_owner();
+        self.assert_owner("mint_by_near");
         self.abort_if_pause();
         assert!(
             collateral_ratio >= MIN_COLLATERAL_RATIO && collateral_ratio <= MAX_COLLATERAL_RATIO,
@@ -511,13 +1009,14 @@ impl Contract {
 
         let near = env::attached_deposit();
 
-        Oracle::get_exchange_rate_promise()
+        self.oracle_config
+            .get_exchange_rate_promise()
             .then(ext_self::mint_with_price_callback(
                 near.into(),
                 collateral_ratio,
                 env::current_account_id(),
                 NO_DEPOSIT,
-                GAS_FOR_BUY_PROMISE,
+                self.gas_config.mint_callback,
             ))
             // Returning callback promise, so the transaction will return the value or a failure.
             // But the refund will still happen.
@@ -526,7 +1025,7 @@ impl Contract {
                 near.into(),
                 env::current_account_id(),
                 NO_DEPOSIT,
-                GAS_FOR_REFUND_PROMISE,
+                self.gas_config.refund,
             ));
     }
 
@@ -541,14 +1040,22 @@ impl Contract {
         let collateral_ratio = U256::from(collateral_ratio);
 
         // Make exchange: NEAR -> USN
-        let amount = near * multiplier / 10u128.pow(u32::from(rate.decimals() - USN_DECIMALS));
+        let amount = rounding::div(
+            near * multiplier,
+            U256::from(10u128.pow(u32::from(rate.decimals() - USN_DECIMALS))),
+            rounding::MINT_BY_NEAR,
+        );
 
         // Apply collateral rate
-        let amount = amount * U256::from(PERCENT_MULTIPLIER) / collateral_ratio;
+        let amount = rounding::div(
+            amount * U256::from(PERCENT_MULTIPLIER),
+            collateral_ratio,
+            rounding::MINT_BY_NEAR,
+        );
 
         // Expected result (128-bit) can have 20 digits before and 18 after the decimal point.
         // We don't expect more than 10^20 tokens on a single account. It panics if overflows.
-        let amount = amount.as_u128();
+        let amount = rounding::to_u128(amount, "mint_by_near");
 
         if amount == 0 {
             env::panic_str("Not enough NEAR: attached deposit exchanges to 0 tokens");
@@ -556,6 +1063,7 @@ impl Contract {
 
         self.token.internal_deposit(&self.owner_id, amount);
         event::emit::ft_mint(&self.owner_id, amount, None);
+        self.check_backing();
 
         amount
     }
@@ -568,71 +1076,354 @@ impl Contract {
         assert_one_yocto();
         self.abort_if_pause();
         self.abort_if_blacklisted(&account_id);
+        if let Some(rejection) = self.check_withdraw_peg(&account_id, &asset_id) {
+            return rejection;
+        }
+
+        let asset_amount =
+            self.stable_treasury
+                .withdraw(&mut self.token, &account_id, &asset_id, amount.into());
+
+        self.internal_withdraw_transfer(account_id, asset_id, amount, asset_amount)
+    }
+
+    /// Withdraws several different assets at once burning their total USN.
+    /// Each leg is transferred and refunded independently.
+    #[payable]
+    pub fn withdraw_multi(&mut self, withdrawals: Vec<(AccountId, U128)>) -> Promise {
+        let account_id = env::predecessor_account_id();
+
+        assert_one_yocto();
+        self.abort_if_pause();
+        self.abort_if_blacklisted(&account_id);
+        assert!(
+            !withdrawals.is_empty() && withdrawals.len() <= MAX_WITHDRAW_LEGS,
+            "Specify from 1 to {} withdrawals",
+            MAX_WITHDRAW_LEGS
+        );
+        for (i, (asset_id, _)) in withdrawals.iter().enumerate() {
+            if withdrawals[..i]
+                .iter()
+                .any(|(prev_id, _)| prev_id == asset_id)
+            {
+                env::panic_str(&format!("Duplicate withdrawal of {}", asset_id));
+            }
+        }
+        for (asset_id, _) in withdrawals.iter() {
+            if let Some(rejection) = self.check_withdraw_peg(&account_id, asset_id) {
+                return rejection;
+            }
+        }
+
+        let withdrawals: Vec<(AccountId, Balance)> = withdrawals
+            .into_iter()
+            .map(|(asset_id, amount)| (asset_id, amount.into()))
+            .collect();
+        let asset_amounts =
+            self.stable_treasury
+                .withdraw_multi(&mut self.token, &account_id, &withdrawals);
+
+        withdrawals
+            .into_iter()
+            .zip(asset_amounts)
+            .map(|((asset_id, amount), asset_amount)| {
+                ext_ft_api::ft_transfer(
+                    account_id.clone(),
+                    asset_amount.into(),
+                    None,
+                    asset_id.clone(),
+                    ONE_YOCTO,
+                    self.gas_config.ft_transfer,
+                )
+                .then(ext_self::handle_withdraw_refund(
+                    account_id.clone(),
+                    asset_id,
+                    amount.into(),
+                    asset_amount.into(),
+                    env::current_account_id(),
+                    NO_DEPOSIT,
+                    self.gas_config.refund,
+                ))
+            })
+            .reduce(|all, leg| all.and(leg))
+            .unwrap()
+    }
+
+    /// Withdraws exactly `asset_amount` of the asset burning USN with the commission,
+    /// see `quote_withdraw_exact`.
+    #[payable]
+    pub fn withdraw_exact(&mut self, asset_id: AccountId, asset_amount: U128) -> Promise {
+        let account_id = env::predecessor_account_id();
+
+        assert_one_yocto();
+        self.abort_if_pause();
+        self.abort_if_blacklisted(&account_id);
+        if let Some(rejection) = self.check_withdraw_peg(&account_id, &asset_id) {
+            return rejection;
+        }
+
+        let quote = self
+            .stable_treasury
+            .quote_withdraw_exact(&asset_id, asset_amount.into());
+        let withdrawn = self.stable_treasury.withdraw(
+            &mut self.token,
+            &account_id,
+            &asset_id,
+            quote.amount.into(),
+        );
+        assert_eq!(
+            withdrawn, asset_amount.0,
+            "The withdrawn amount doesn't match the exact amount"
+        );
+
+        self.internal_withdraw_transfer(account_id, asset_id, quote.amount, withdrawn)
+    }
+
+    /// Withdraws like `withdraw`, but registers the caller on the asset token first if needed.
+    /// The attached deposit except one yocto pays for the storage, the rest of it is refunded.
+    #[payable]
+    pub fn withdraw_with_storage(&mut self, asset_id: Option<AccountId>, amount: U128) -> Promise {
+        let account_id = env::predecessor_account_id();
+        let asset_id = asset_id.unwrap_or(usdt_id());
+        let deposit = env::attached_deposit();
+
+        assert!(
+            deposit >= ONE_YOCTO,
+            "Requires attached deposit of at least 1 yoctoNEAR"
+        );
+        self.abort_if_pause();
+        self.abort_if_blacklisted(&account_id);
+        if let Some(rejection) = self.check_withdraw_peg(&account_id, &asset_id) {
+            // The receipt succeeds, so the deposit isn't refunded by the runtime.
+            return Promise::new(account_id).transfer(deposit).then(rejection);
+        }
 
         let asset_amount =
             self.stable_treasury
                 .withdraw(&mut self.token, &account_id, &asset_id, amount.into());
 
+        ext_ft_api::storage_balance_of(
+            account_id.clone(),
+            asset_id.clone(),
+            NO_DEPOSIT,
+            self.gas_config.storage_view,
+        )
+        .and(ext_ft_api::storage_balance_bounds(
+            asset_id.clone(),
+            NO_DEPOSIT,
+            self.gas_config.storage_view,
+        ))
+        .then(ext_self::handle_withdraw_storage(
+            account_id,
+            asset_id,
+            amount,
+            asset_amount.into(),
+            (deposit - ONE_YOCTO).into(),
+            env::current_account_id(),
+            NO_DEPOSIT,
+            GAS_FOR_WITHDRAW_STORAGE_CALLBACK
+                + self.gas_config.storage_deposit
+                + GAS_FOR_WITHDRAW_REGISTRATION_CALLBACK
+                + self.gas_config.ft_transfer
+                + self.gas_config.refund,
+        ))
+    }
+
+    /// Transfers the withdrawn asset refunding the withdrawal if the transfer fails.
+    fn internal_withdraw_transfer(
+        &self,
+        account_id: AccountId,
+        asset_id: AccountId,
+        amount: U128,
+        asset_amount: Balance,
+    ) -> Promise {
         ext_ft_api::ft_transfer(
             account_id.clone(),
             asset_amount.into(),
             None,
             asset_id.clone(),
             ONE_YOCTO,
-            GAS_FOR_FT_TRANSFER,
+            self.gas_config.ft_transfer,
         )
         .as_return()
         .then(ext_self::handle_withdraw_refund(
             account_id,
             asset_id,
             amount,
+            asset_amount.into(),
+            env::current_account_id(),
+            NO_DEPOSIT,
+            self.gas_config.refund,
+        ))
+    }
+
+    /// Refunds the failed withdrawal according to the refund mode.
+    fn internal_withdraw_refund(
+        &mut self,
+        account_id: AccountId,
+        token_id: AccountId,
+        amount: U128,
+        asset_amount: U128,
+    ) {
+        // A delisted asset has been removed with its reserve and commission,
+        // so its failed withdrawal is kept to be claimed in the asset instead.
+        let mode = if self.stable_treasury.is_supported(&token_id) {
+            self.withdraw_refund_mode
+        } else {
+            WithdrawRefundMode::Claim
+        };
+        match mode {
+            WithdrawRefundMode::Remint => {
+                self.stable_treasury
+                    .refund(&mut self.token, &account_id, &token_id, amount.into());
+                env::log_str(&format!(
+                    "Refund ${} of USN to {} after {} error",
+                    amount.0, account_id, token_id,
+                ));
+            }
+            WithdrawRefundMode::Claim => {
+                self.stable_treasury.add_failed_withdrawal(
+                    &account_id,
+                    &token_id,
+                    asset_amount.into(),
+                );
+            }
+        }
+    }
+
+    /// Retries the asset transfer of failed withdrawals kept in the `Claim` refund mode.
+    #[payable]
+    pub fn claim_failed_withdrawal(&mut self, asset_id: AccountId) -> Promise {
+        let account_id = env::predecessor_account_id();
+
+        assert_one_yocto();
+        self.abort_if_pause();
+        self.abort_if_blacklisted(&account_id);
+
+        let asset_amount = self
+            .stable_treasury
+            .take_failed_withdrawal(&account_id, &asset_id);
+
+        ext_ft_api::ft_transfer(
+            account_id.clone(),
+            asset_amount.into(),
+            None,
+            asset_id.clone(),
+            ONE_YOCTO,
+            self.gas_config.ft_transfer,
+        )
+        .as_return()
+        .then(ext_self::handle_failed_withdrawal_claim(
+            account_id,
+            asset_id,
+            asset_amount.into(),
             env::current_account_id(),
             NO_DEPOSIT,
-            GAS_FOR_REFUND_PROMISE,
+            self.gas_config.refund,
         ))
     }
 
+    /// Returns asset amounts of failed withdrawals to be claimed by the account.
+    pub fn failed_withdrawals(&self, account_id: AccountId) -> Vec<(AccountId, U128)> {
+        self.stable_treasury.failed_withdrawals(&account_id)
+    }
+
+    /// Sets whether failed withdrawals are refunded in USN or kept to be claimed in the asset.
+    pub fn set_withdraw_refund_mode(&mut self, mode: WithdrawRefundMode) {
+        self.assert_owner("set_withdraw_refund_mode");
+        self.withdraw_refund_mode = mode;
+    }
+
+    pub fn withdraw_refund_mode(&self) -> WithdrawRefundMode {
+        self.withdraw_refund_mode
+    }
+
     pub fn stake(&self, amount: U128, pool_id: AccountId) -> Promise {
-        self.assert_owner();
-        staking::stake(amount, pool_id)
+        self.assert_owner("stake");
+        staking::stake(amount, pool_id, &self.gas_config)
     }
 
     pub fn unstake(&self, amount: U128, pool_id: AccountId) -> Promise {
-        self.assert_owner();
-        staking::unstake(amount, pool_id)
+        self.assert_owner("unstake");
+        staking::unstake(amount, pool_id, &self.gas_config)
     }
 
     pub fn unstake_all(&self, pool_id: AccountId) -> Promise {
-        self.assert_owner();
-        staking::unstake_all(pool_id)
+        self.assert_owner("unstake_all");
+        staking::unstake_all(pool_id, &self.gas_config)
     }
 
     pub fn withdraw_all(&self, pool_id: AccountId) -> Promise {
-        self.assert_owner();
-        staking::withdraw_all(pool_id)
+        self.assert_owner("withdraw_all");
+        staking::withdraw_all(pool_id, &self.gas_config)
     }
 
     pub fn add_stable_asset(&mut self, asset_id: &AccountId, decimals: u8) {
-        self.assert_owner();
+        self.assert_owner("add_stable_asset");
         self.stable_treasury.add_asset(asset_id, decimals);
     }
 
     pub fn enable_stable_asset(&mut self, asset_id: &AccountId) {
-        self.assert_owner();
+        self.assert_owner("enable_stable_asset");
         self.stable_treasury.enable_asset(asset_id);
     }
 
     pub fn disable_stable_asset(&mut self, asset_id: &AccountId) {
-        self.assert_owner();
+        self.assert_owner("disable_stable_asset");
         self.stable_treasury.disable_asset(asset_id);
     }
 
+    /// Disables deposits of the asset, keeping withdrawals open until `deadline` (in nanoseconds).
+    pub fn begin_delisting(&mut self, asset_id: &AccountId, deadline: U64) {
+        self.assert_owner("begin_delisting");
+        self.stable_treasury
+            .begin_delisting(asset_id, deadline.into());
+    }
+
+    /// Removes the asset after the delisting deadline moving its commission to `successor_id`.
+    /// Remaining reserves of the asset stay on the contract account to be converted by the DAO.
+    /// Withdrawals of the asset failing afterwards are kept to be claimed in the asset.
+    pub fn finish_delisting(&mut self, asset_id: &AccountId, successor_id: &AccountId) {
+        self.assert_owner("finish_delisting");
+        self.stable_treasury
+            .finish_delisting(asset_id, successor_id);
+    }
+
     pub fn treasury(&self) -> Vec<(AccountId, AssetInfo)> {
         self.stable_treasury.supported_assets()
     }
 
+    /// Returns USN minted for the deposit of `asset_amount` of the stable asset.
+    pub fn quote_deposit(&self, asset_id: AccountId, asset_amount: U128) -> StableQuote {
+        self.stable_treasury
+            .quote_deposit(&asset_id, asset_amount.into())
+    }
+
+    /// Returns the stable asset amount withdrawn for `amount` of USN.
+    pub fn quote_withdraw(&self, asset_id: AccountId, amount: U128) -> StableQuote {
+        self.stable_treasury
+            .quote_withdraw(&asset_id, amount.into())
+    }
+
+    /// Returns USN burned by the withdrawal of exactly `asset_amount` of the stable asset.
+    pub fn quote_withdraw_exact(&self, asset_id: AccountId, asset_amount: U128) -> StableQuote {
+        self.stable_treasury
+            .quote_withdraw_exact(&asset_id, asset_amount.into())
+    }
+
+    /// Returns the amount of the asset backing USN: deposited minus withdrawn.
+    pub fn stable_reserve(&self, asset_id: &AccountId) -> U128 {
+        self.stable_treasury.reserve(asset_id).into()
+    }
+
+    /// Sets the tracked reserve of the asset, e.g. once after the migration.
+    pub fn set_stable_reserve(&mut self, asset_id: &AccountId, reserve: U128) {
+        self.assert_owner("set_stable_reserve");
+        self.stable_treasury.set_reserve(asset_id, reserve.into());
+    }
+
     pub fn set_commission_rate(&mut self, asset_id: &AccountId, rate: CommissionRate) {
-        self.assert_owner();
+        self.assert_owner("set_commission_rate");
         self.stable_treasury.set_commission_rate(asset_id, rate);
     }
 
@@ -640,28 +1431,129 @@ impl Contract {
         self.stable_treasury.commission_rate(asset_id)
     }
 
-    pub fn transfer_commission(&mut self, account_id: AccountId, amount: U128) {
-        self.assert_owner();
-        let amount = amount.0;
-        assert!(amount > 0, "Amount should be positive");
-
-        let (usn_commission_v1, near_commission_v1) = if self.commission.usn > amount {
-            let near_amount = (U256::from(self.commission.near) * U256::from(amount)
-                / U256::from(self.commission.usn))
-            .as_u128();
-            (amount, near_amount)
-        } else {
-            (self.commission.usn, self.commission.near)
-        };
-
-        let mut usn_commission_v2 = amount - usn_commission_v1;
-        assert!(
-            usn_commission_v2 <= self.commission().v2.usn.0,
-            "Exceeded the commission v2 amount"
+    /// Sets minimal amounts of deposits (in the asset) and withdrawals (in USN).
+    /// Only can be called by owner.
+    pub fn set_asset_limits(&mut self, asset_id: AccountId, min_deposit: U128, min_withdraw: U128) {
+        self.assert_owner("set_asset_limits");
+        self.stable_treasury.set_limits(
+            &asset_id,
+            AssetLimits {
+                min_deposit,
+                min_withdraw,
+            },
         );
+    }
 
-        self.commission.usn -= usn_commission_v1;
-        self.commission.near -= near_commission_v1;
+    /// Switches the price feed, e.g. to another oracle deployment. Only can be called by owner.
+    /// The last reported rate is dropped if the asset changes.
+    pub fn set_oracle_config(&mut self, config: OracleConfig) {
+        self.assert_owner("set_oracle_config");
+        config.assert_valid();
+        if config.asset_id != self.oracle_config.asset_id {
+            self.oracle.last_report = None;
+        }
+        event::emit::oracle_config(&config);
+        self.oracle_config = config;
+    }
+
+    pub fn oracle_config(&self) -> OracleConfig {
+        self.oracle_config.clone()
+    }
+
+    /// Sets which of the reported rates `mint_by_near` uses. Only can be called by owner.
+    pub fn set_rate_policy(&mut self, policy: RatePolicy) {
+        self.assert_owner("set_rate_policy");
+        self.rate_policy = policy;
+    }
+
+    pub fn rate_policy(&self) -> RatePolicy {
+        self.rate_policy
+    }
+
+    pub fn transfer_commission(&mut self, account_id: AccountId, amount: U128) {
+        self.assert_owner("transfer_commission");
+        self.internal_transfer_commission(&account_id, amount.into(), None);
+    }
+
+    /// Mints the commission to several receivers atomically.
+    /// Each receiver gets its own `ft_mint` event with the given memo.
+    pub fn transfer_commission_batch(&mut self, transfers: Vec<CommissionTransfer>) {
+        self.assert_owner("transfer_commission_batch");
+        assert!(!transfers.is_empty(), "Transfers should not be empty");
+        for transfer in transfers {
+            self.internal_transfer_commission(
+                &transfer.receiver_id,
+                transfer.amount.into(),
+                transfer.memo.as_deref(),
+            );
+        }
+    }
+
+    /// Pays out `amount` (in USN) of the asset commission in the underlying asset.
+    /// The commission is restored if the asset transfer fails.
+    pub fn withdraw_commission_as_asset(
+        &mut self,
+        asset_id: AccountId,
+        amount: U128,
+        receiver_id: AccountId,
+    ) -> Promise {
+        self.assert_owner("withdraw_commission_as_asset");
+        let asset_amount = self
+            .stable_treasury
+            .withdraw_commission_as_asset(&asset_id, amount.into());
+        event::emit::commission_withdraw(&receiver_id, &asset_id, amount.into(), asset_amount);
+
+        ext_ft_api::ft_transfer(
+            receiver_id,
+            asset_amount.into(),
+            None,
+            asset_id.clone(),
+            ONE_YOCTO,
+            self.gas_config.ft_transfer,
+        )
+        .then(ext_self::handle_commission_refund(
+            asset_id,
+            amount,
+            env::current_account_id(),
+            NO_DEPOSIT,
+            self.gas_config.refund,
+        ))
+    }
+
+    fn internal_transfer_commission(
+        &mut self,
+        account_id: &AccountId,
+        amount: Balance,
+        memo: Option<&str>,
+    ) {
+        assert!(amount > 0, "Amount should be positive");
+
+        let (usn_commission_v1, near_commission_v1) = if self.commission.usn > amount {
+            let near_amount = rounding::mul_div(
+                self.commission.near,
+                amount,
+                self.commission.usn,
+                rounding::NEAR_COMMISSION,
+            );
+            (amount, near_amount)
+        } else {
+            (self.commission.usn, self.commission.near)
+        };
+
+        let mut usn_commission_v2 = amount - usn_commission_v1;
+        assert!(
+            usn_commission_v2 <= self.commission().v2.usn.0,
+            "Exceeded the commission v2 amount"
+        );
+
+        self.commission.usn -= usn_commission_v1;
+        self.commission.near -= near_commission_v1;
+
+        let epoch = commission_epoch();
+        if usn_commission_v2 > 0 {
+            self.stable_treasury
+                .record_commission_transfer(usn_commission_v2);
+        }
 
         for asset in self.treasury().iter() {
             if usn_commission_v2 > asset.1.commission().0 {
@@ -675,14 +1567,35 @@ impl Contract {
             }
         }
 
-        self.token.internal_deposit(&account_id, amount);
-        event::emit::ft_mint(&account_id, amount, None);
+        // Commission v1 was accrued before the split was introduced.
+        let shares = self.revenue.apply(amount - usn_commission_v1);
+        let receiver_amount = usn_commission_v1 + shares.dao;
+        if receiver_amount > 0 {
+            self.token.internal_deposit(account_id, receiver_amount);
+            event::emit::ft_mint(account_id, receiver_amount, memo);
+        }
+        if shares.insurance > 0 {
+            let insurance_id = self.revenue.split.insurance_id.clone().unwrap();
+            self.token.internal_deposit(&insurance_id, shares.insurance);
+            event::emit::ft_mint(&insurance_id, shares.insurance, Some("Insurance"));
+        }
+        event::emit::commission_transfer(
+            account_id,
+            amount,
+            usn_commission_v1,
+            near_commission_v1,
+            epoch,
+            memo,
+        );
+        if shares.insurance > 0 || shares.burn > 0 {
+            event::emit::commission_split(shares.dao, shares.insurance, shares.burn);
+        }
     }
 
     #[payable]
     pub fn transfer_near(&mut self, account_id: AccountId, amount: U128) -> Promise {
         assert_one_yocto();
-        self.assert_owner();
+        self.assert_owner("transfer_near");
         Promise::new(account_id).transfer(amount.into())
     }
 }
@@ -770,6 +1683,30 @@ mod tests {
         assert_eq!(contract.ft_balance_of(accounts(1)).0, transfer_amount);
     }
 
+    #[cfg(feature = "account_stats")]
+    #[test]
+    fn test_account_stats() {
+        let mut context = get_context(accounts(2));
+        testing_env!(context.build());
+        let mut contract = Contract::new(accounts(2));
+        contract.token.internal_deposit(&accounts(2), 1000);
+
+        testing_env!(context
+            .attached_deposit(ONE_YOCTO)
+            .block_timestamp(100)
+            .build());
+        contract.ft_transfer(accounts(1), U128(300), None);
+        contract.ft_transfer(accounts(1), U128(200), None);
+
+        let sender = contract.get_account_stats(accounts(2)).unwrap();
+        assert_eq!(sender.sent, U128(500));
+        assert_eq!(sender.transfer_count, 2);
+        assert_eq!(sender.last_activity, U64(100));
+        let receiver = contract.get_account_stats(accounts(1)).unwrap();
+        assert_eq!(receiver.received, U128(500));
+        assert_eq!(receiver.sent, U128(0));
+    }
+
     #[test]
     fn test_blacklist() {
         let mut context = get_context(accounts(1));
@@ -1013,6 +1950,35 @@ mod tests {
         assert_eq!(contract.commission().v2.usn, U128(10000000000000));
     }
 
+    #[test]
+    fn test_commission_epochs() {
+        const EPOCH_NS: u64 = 30 * 24 * 60 * 60 * 1_000_000_000;
+        let mut context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = Contract::new(accounts(1));
+
+        contract
+            .stable_treasury
+            .deposit(&mut contract.token, &accounts(2), &usdt_id(), 100000);
+
+        testing_env!(context.block_timestamp(EPOCH_NS).build());
+        contract.transfer_commission(accounts(3), U128(4000000000000));
+
+        let commission = contract.commission().v2;
+        assert_eq!(commission.epoch, U64(1));
+        assert_eq!(commission.current_epoch.accrued, U128(0));
+        assert_eq!(commission.current_epoch.transferred, U128(4000000000000));
+        assert_eq!(commission.lifetime.accrued, U128(10000000000000));
+        assert_eq!(commission.lifetime.transferred, U128(4000000000000));
+
+        let history = contract.commission_history(None, Some(3));
+        assert_eq!(history.len(), 2);
+        assert_eq!(history[0].0, U64(0));
+        assert_eq!(history[0].1.accrued, U128(10000000000000));
+        assert_eq!(history[1].0, U64(1));
+        assert!(contract.commission_history(None, Some(0)).is_empty());
+    }
+
     #[test]
     fn test_commission_two_assets() {
         let context = get_context(accounts(1));
@@ -1392,4 +2358,755 @@ mod tests {
 
         contract.transfer_commission(accounts(3), U128(1000000000000000));
     }
+
+    #[test]
+    fn test_allowance() {
+        let mut context = get_context(accounts(2));
+        testing_env!(context.attached_deposit(ONE_YOCTO).build());
+        let mut contract = Contract::new(accounts(1));
+        contract.token.internal_deposit(&accounts(2), 1000);
+
+        contract.approve(accounts(3), U128(300));
+        contract.increase_allowance(accounts(3), U128(200));
+        contract.decrease_allowance(accounts(3), U128(100));
+        assert_eq!(contract.allowance(accounts(2), accounts(3)), U128(400));
+
+        testing_env!(context.predecessor_account_id(accounts(3)).build());
+        contract.transfer_from(accounts(2), accounts(4), U128(250), None);
+
+        assert_eq!(contract.allowance(accounts(2), accounts(3)), U128(150));
+        assert_eq!(contract.ft_balance_of(accounts(2)), U128(750));
+        assert_eq!(contract.ft_balance_of(accounts(4)), U128(250));
+    }
+
+    #[test]
+    #[should_panic(expected = "Insufficient allowance")]
+    fn test_transfer_from_exceeding_allowance() {
+        let mut context = get_context(accounts(2));
+        testing_env!(context.attached_deposit(ONE_YOCTO).build());
+        let mut contract = Contract::new(accounts(1));
+        contract.token.internal_deposit(&accounts(2), 1000);
+        contract.approve(accounts(3), U128(300));
+
+        testing_env!(context.predecessor_account_id(accounts(3)).build());
+        contract.transfer_from(accounts(2), accounts(4), U128(301), None);
+    }
+
+    #[test]
+    #[should_panic(expected = "Decreased allowance below zero")]
+    fn test_decrease_allowance_below_zero() {
+        let context = get_context(accounts(2));
+        testing_env!(context.attached_deposit(ONE_YOCTO).build());
+        let mut contract = Contract::new(accounts(1));
+        contract.approve(accounts(3), U128(300));
+        contract.decrease_allowance(accounts(3), U128(301));
+    }
+
+    #[test]
+    #[should_panic(expected = "Account 'charlie' is banned")]
+    fn test_transfer_from_blacklisted_owner() {
+        let mut context = get_context(accounts(2));
+        testing_env!(context.attached_deposit(ONE_YOCTO).build());
+        let mut contract = Contract::new(accounts(1));
+        contract.token.internal_deposit(&accounts(2), 1000);
+        contract.approve(accounts(3), U128(300));
+
+        testing_env!(context.predecessor_account_id(accounts(1)).build());
+        contract.add_to_blacklist(&accounts(2));
+
+        testing_env!(context.predecessor_account_id(accounts(3)).build());
+        contract.transfer_from(accounts(2), accounts(4), U128(100), None);
+    }
+
+    #[test]
+    fn test_transfer_from_violating_compliance() {
+        let mut context = get_context(accounts(2));
+        testing_env!(context.attached_deposit(ONE_YOCTO).build());
+        let mut contract = Contract::new(accounts(1));
+        contract.token.internal_deposit(&accounts(2), 1000);
+        contract.approve(accounts(3), U128(300));
+
+        testing_env!(context.predecessor_account_id(accounts(1)).build());
+        contract.set_compliance_rule(
+            "max".to_string(),
+            compliance::ComplianceRule::MaxTransfer { amount: U128(100) },
+        );
+
+        testing_env!(context.predecessor_account_id(accounts(3)).build());
+        contract.transfer_from(accounts(2), accounts(4), U128(101), None);
+        assert_eq!(contract.ft_balance_of(accounts(4)), U128(0));
+        assert_eq!(contract.allowance(accounts(2), accounts(3)), U128(300));
+        assert!(near_sdk::test_utils::get_logs()
+            .last()
+            .unwrap()
+            .contains(r#""event":"compliance_reject""#));
+    }
+
+    #[test]
+    fn test_freeze_account() {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.block_timestamp(1_000_000_000).build());
+        let mut contract = Contract::new(accounts(1));
+        contract.extend_guardians(vec![accounts(3)]);
+        contract.token.internal_deposit(&accounts(2), 1000);
+
+        testing_env!(context.predecessor_account_id(accounts(3)).build());
+        contract.freeze_account(accounts(2), 60);
+        assert_eq!(
+            contract.frozen_until(accounts(2)),
+            Some(U64(61_000_000_000))
+        );
+
+        // The freeze expires automatically.
+        testing_env!(context
+            .block_timestamp(61_000_000_000)
+            .predecessor_account_id(accounts(2))
+            .attached_deposit(ONE_YOCTO)
+            .build());
+        assert_eq!(contract.frozen_until(accounts(2)), None);
+        contract.ft_transfer(accounts(4), U128(100), None);
+        assert_eq!(contract.ft_balance_of(accounts(4)), U128(100));
+    }
+
+    #[test]
+    #[should_panic(expected = "Account 'charlie' is frozen until 61000000000")]
+    fn test_frozen_account_cannot_transfer() {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.block_timestamp(1_000_000_000).build());
+        let mut contract = Contract::new(accounts(1));
+        contract.token.internal_deposit(&accounts(2), 1000);
+        contract.freeze_account(accounts(2), 60);
+
+        testing_env!(context
+            .predecessor_account_id(accounts(2))
+            .attached_deposit(ONE_YOCTO)
+            .build());
+        contract.ft_transfer(accounts(4), U128(100), None);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_cannot_destroy_frozen_funds() {
+        let context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = Contract::new(accounts(1));
+        contract.token.internal_deposit(&accounts(2), 1000);
+        contract.freeze_account(accounts(2), 60);
+        contract.destroy_black_funds(&accounts(2));
+    }
+
+    #[test]
+    #[should_panic(expected = "This method can be called only by owner or guardian")]
+    fn test_user_cannot_freeze_account() {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = Contract::new(accounts(1));
+        testing_env!(context.predecessor_account_id(accounts(2)).build());
+        contract.freeze_account(accounts(3), 60);
+    }
+
+    #[test]
+    fn test_transfer_commission_batch() {
+        let context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = Contract::new(accounts(1));
+
+        contract.commission.usn = 10000000000;
+        contract.commission.near = 1000000000000000;
+
+        contract
+            .stable_treasury
+            .deposit(&mut contract.token, &accounts(2), &usdt_id(), 100000);
+
+        contract.transfer_commission_batch(vec![
+            CommissionTransfer {
+                receiver_id: accounts(3),
+                amount: U128(5000000000),
+                memo: Some("DAO".to_string()),
+            },
+            CommissionTransfer {
+                receiver_id: accounts(4),
+                amount: U128(5000000000000),
+                memo: Some("Insurance".to_string()),
+            },
+        ]);
+
+        assert_eq!(contract.commission().v1.usn, U128(0));
+        assert_eq!(contract.commission().v1.near, U128(0));
+        assert_eq!(contract.commission().v2.usn, U128(5005000000000));
+        assert_eq!(contract.ft_balance_of(accounts(3)), U128(5000000000));
+        assert_eq!(contract.ft_balance_of(accounts(4)), U128(5000000000000));
+    }
+
+    #[test]
+    #[should_panic(expected = "Exceeded the commission v2 amount")]
+    fn test_transfer_commission_batch_exceeded() {
+        let context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = Contract::new(accounts(1));
+
+        contract
+            .stable_treasury
+            .deposit(&mut contract.token, &accounts(2), &usdt_id(), 100000);
+
+        contract.transfer_commission_batch(vec![
+            CommissionTransfer {
+                receiver_id: accounts(3),
+                amount: U128(6000000000000),
+                memo: None,
+            },
+            CommissionTransfer {
+                receiver_id: accounts(4),
+                amount: U128(6000000000000),
+                memo: None,
+            },
+        ]);
+    }
+
+    #[test]
+    fn test_events() {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.attached_deposit(ONE_YOCTO).build());
+        let mut contract = Contract::new(accounts(1));
+
+        contract.pause();
+        contract.extend_guardians(vec![accounts(2)]);
+        contract.add_to_blacklist(&accounts(3));
+
+        assert_eq!(
+            near_sdk::test_utils::get_logs(),
+            vec![
+                r#"EVENT_JSON:{"data":[{"account_id":"bob"}],"event":"contract_pause","standard":"usn","version":"1.0.0"}"#,
+                r#"EVENT_JSON:{"data":[{"guardians":["charlie"]}],"event":"guardians_add","standard":"usn","version":"1.0.0"}"#,
+                r#"EVENT_JSON:{"data":[{"account_id":"danny"}],"event":"blacklist_add","standard":"usn","version":"1.0.0"}"#,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_protocol_status() {
+        let context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = Contract::new(accounts(1));
+        contract.extend_guardians(vec![accounts(2)]);
+        contract
+            .stable_treasury
+            .deposit(&mut contract.token, &accounts(2), &usdt_id(), 100000);
+
+        let status = contract.get_protocol_status();
+        assert_eq!(status.status, ContractStatus::Working);
+        assert_eq!(status.owner_id, accounts(1));
+        assert_eq!(status.guardians, vec![accounts(2)]);
+        assert_eq!(status.total_supply, U128(99990000000000000));
+        assert_eq!(status.treasury.len(), 1);
+        assert_eq!(status.commission.v2.usn, U128(10000000000000));
+        assert!(status.exchange_rate.is_none());
+        assert!(status.pool.is_none());
+    }
+
+    #[test]
+    fn test_build_info() {
+        let context = get_context(accounts(1));
+        testing_env!(context.build());
+        let contract = Contract::new(accounts(1));
+        let build_info = contract.build_info();
+        assert_eq!(build_info.network, "sandbox");
+        assert_eq!(build_info.usdt_id, usdt_id());
+        assert_eq!(build_info.oracle_id, "priceoracle.test.near");
+        assert_eq!(build_info.oracle_asset_id, "wrap.test.near");
+        assert_eq!(build_info.ref_id, "ref.test.near");
+        assert!(!build_info.git_commit.is_empty());
+    }
+
+    #[test]
+    fn test_set_oracle_config() {
+        let context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = Contract::new(accounts(1));
+        contract.oracle.last_report = Some(ExchangeRate::test_fresh_rate());
+
+        let mut config = contract.oracle_config();
+        config.smooth_asset_id = Some("wrap.test.near#1800".to_string());
+        contract.set_oracle_config(config.clone());
+        assert_eq!(contract.oracle_config(), config);
+        assert!(contract.oracle.last_report.is_some());
+
+        config.asset_id = "wrap2.test.near".parse().unwrap();
+        contract.set_oracle_config(config);
+        assert!(contract.oracle.last_report.is_none());
+        // The build info still reports the default feed.
+        assert_eq!(contract.build_info().oracle_asset_id, "wrap.test.near");
+    }
+
+    #[test]
+    #[should_panic(expected = "Invalid oracle config")]
+    fn test_set_oracle_config_gas() {
+        let context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = Contract::new(accounts(1));
+        let mut config = contract.oracle_config();
+        config.gas = Gas(100_000_000_000_000);
+        contract.set_oracle_config(config);
+    }
+
+    #[test]
+    fn test_operators() {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = Contract::new(accounts(1));
+        contract.set_operator(accounts(2), vec!["withdraw_stable_pool".to_string()]);
+        assert_eq!(
+            contract.operator_methods(accounts(2)),
+            vec!["withdraw_stable_pool".to_string()]
+        );
+
+        testing_env!(context.predecessor_account_id(accounts(2)).build());
+        contract.assert_operator("withdraw_stable_pool");
+
+        testing_env!(context.predecessor_account_id(accounts(1)).build());
+        contract.remove_operator(accounts(2));
+        assert!(contract.operator_methods(accounts(2)).is_empty());
+    }
+
+    #[test]
+    #[should_panic(
+        expected = "This method can be called only by owner or operator of 'transfer_stable_liquidity'"
+    )]
+    fn test_operator_out_of_scope() {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = Contract::new(accounts(1));
+        contract.set_operator(accounts(2), vec!["withdraw_stable_pool".to_string()]);
+
+        testing_env!(context.predecessor_account_id(accounts(2)).build());
+        contract.assert_operator("transfer_stable_liquidity");
+    }
+
+    #[test]
+    #[should_panic(expected = "The method 'pause' can't be delegated")]
+    fn test_operator_not_delegatable_method() {
+        let context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = Contract::new(accounts(1));
+        contract.set_operator(accounts(2), vec!["pause".to_string()]);
+    }
+
+    #[test]
+    fn test_storage_is_free() {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = Contract::new(accounts(1));
+
+        testing_env!(context
+            .predecessor_account_id(accounts(2))
+            .attached_deposit(ONE_NEAR)
+            .build());
+        let balance = contract.storage_deposit(None, None);
+        assert_eq!(balance.available, U128(0));
+        assert_eq!(
+            contract.storage_balance_of(accounts(3)).unwrap().total,
+            balance.total
+        );
+        assert_eq!(contract.storage_balance_bounds().max, Some(U128(0)));
+
+        testing_env!(context.attached_deposit(ONE_YOCTO).build());
+        contract.storage_withdraw(None);
+        assert!(!contract.storage_unregister(Some(true)));
+    }
+
+    #[test]
+    #[should_panic(expected = "The amount is greater than the available storage balance")]
+    fn test_storage_withdraw() {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = Contract::new(accounts(1));
+
+        testing_env!(context.attached_deposit(ONE_YOCTO).build());
+        contract.storage_withdraw(Some(U128(1)));
+    }
+
+    #[test]
+    #[should_panic(expected = "Requires attached deposit of at least 1 yoctoNEAR")]
+    fn test_withdraw_with_storage_no_deposit() {
+        let context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = Contract::new(accounts(1));
+        contract.withdraw_with_storage(None, U128(1));
+    }
+
+    #[test]
+    fn test_withdraw_refund_after_delisting() {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.block_timestamp(100).build());
+        let mut contract = Contract::new(accounts(1));
+        contract.add_stable_asset(&accounts(2), 6);
+        contract
+            .stable_treasury
+            .deposit(&mut contract.token, &accounts(3), &accounts(2), 1000000);
+        contract.begin_delisting(&accounts(2), U64(200));
+
+        testing_env!(context
+            .predecessor_account_id(accounts(3))
+            .attached_deposit(ONE_YOCTO)
+            .build());
+        contract.withdraw(Some(accounts(2)), U128(999900000000000000));
+
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .block_timestamp(200)
+            .build());
+        contract.finish_delisting(&accounts(2), &usdt_id());
+
+        // The withdrawal in flight fails after the asset is removed.
+        testing_env!(
+            context.predecessor_account_id(accounts(0)).build(),
+            near_sdk::VMConfig::test(),
+            near_sdk::RuntimeFeesConfig::test(),
+            Default::default(),
+            vec![PromiseResult::Failed],
+        );
+        contract.handle_withdraw_refund(
+            accounts(3),
+            accounts(2),
+            U128(999900000000000000),
+            U128(999800),
+        );
+        assert_eq!(contract.ft_balance_of(accounts(3)), U128(0));
+        assert_eq!(
+            contract.failed_withdrawals(accounts(3)),
+            vec![(accounts(2), U128(999800))]
+        );
+    }
+
+    #[test]
+    fn test_withdraw_registration_failure() {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = Contract::new(accounts(1));
+        contract
+            .stable_treasury
+            .deposit(&mut contract.token, &accounts(3), &usdt_id(), 1000000);
+
+        testing_env!(context
+            .predecessor_account_id(accounts(3))
+            .attached_deposit(ONE_YOCTO + 1000)
+            .build());
+        contract.withdraw_with_storage(None, U128(500000000000000000));
+        assert_eq!(
+            contract.ft_balance_of(accounts(3)),
+            U128(999900000000000000 - 500000000000000000)
+        );
+
+        testing_env!(
+            context.predecessor_account_id(accounts(0)).build(),
+            near_sdk::VMConfig::test(),
+            near_sdk::RuntimeFeesConfig::test(),
+            Default::default(),
+            vec![PromiseResult::Failed],
+        );
+        contract.handle_withdraw_registration(
+            accounts(3),
+            usdt_id(),
+            U128(500000000000000000),
+            U128(499950),
+            U128(1000),
+        );
+        assert_eq!(
+            contract.ft_balance_of(accounts(3)),
+            U128(999900000000000000)
+        );
+    }
+
+    #[test]
+    fn test_withdraw_multi() {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = Contract::new(accounts(1));
+        contract.add_stable_asset(&accounts(2), 6);
+        contract
+            .stable_treasury
+            .deposit(&mut contract.token, &accounts(3), &usdt_id(), 1000000);
+        contract
+            .stable_treasury
+            .deposit(&mut contract.token, &accounts(3), &accounts(2), 1000000);
+
+        testing_env!(context
+            .predecessor_account_id(accounts(3))
+            .attached_deposit(ONE_YOCTO)
+            .build());
+        contract.withdraw_multi(vec![
+            (usdt_id(), U128(500000000000000000)),
+            (accounts(2), U128(1000000000000000000)),
+        ]);
+        assert_eq!(
+            contract.ft_balance_of(accounts(3)),
+            U128(2 * 999900000000000000 - 1500000000000000000)
+        );
+        assert_eq!(
+            near_sdk::test_utils::get_logs()
+                .iter()
+                .filter(|log| log.contains("\"event\":\"ft_burn\""))
+                .count(),
+            1
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Duplicate withdrawal of usdt.test.near")]
+    fn test_withdraw_multi_duplicate_legs() {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = Contract::new(accounts(1));
+        contract
+            .stable_treasury
+            .deposit(&mut contract.token, &accounts(3), &usdt_id(), 1000000);
+
+        testing_env!(context
+            .predecessor_account_id(accounts(3))
+            .attached_deposit(ONE_YOCTO)
+            .build());
+        contract.withdraw_multi(vec![
+            (usdt_id(), U128(400000000000000000)),
+            (usdt_id(), U128(400000000000000000)),
+        ]);
+    }
+
+    #[test]
+    fn test_withdraw_multi_stale_peg() {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = Contract::new(accounts(1));
+        contract.add_stable_asset(&accounts(2), 6);
+        contract
+            .stable_treasury
+            .deposit(&mut contract.token, &accounts(3), &usdt_id(), 1000000);
+        contract
+            .stable_treasury
+            .deposit(&mut contract.token, &accounts(3), &accounts(2), 1000000);
+        // The price of the second asset was never synced.
+        contract.set_peg_tolerance(accounts(2), Some(50));
+
+        testing_env!(context
+            .predecessor_account_id(accounts(3))
+            .attached_deposit(ONE_YOCTO)
+            .build());
+        contract.withdraw_multi(vec![
+            (usdt_id(), U128(500000000000000000)),
+            (accounts(2), U128(500000000000000000)),
+        ]);
+        assert_eq!(
+            contract.ft_balance_of(accounts(3)),
+            U128(2 * 999900000000000000)
+        );
+        assert_eq!(
+            near_sdk::test_utils::get_logs().last().unwrap(),
+            r#"EVENT_JSON:{"data":[{"account_id":"danny","asset_id":"charlie","price":null,"reason":"The price of charlie is stale, call sync_stable_prices","tolerance":50,"updated_at":"0"}],"event":"stable_peg_reject","standard":"usn","version":"1.0.0"}"#
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Specify from 1 to 3 withdrawals")]
+    fn test_withdraw_multi_no_legs() {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.attached_deposit(ONE_YOCTO).build());
+        let mut contract = Contract::new(accounts(1));
+        contract.withdraw_multi(vec![]);
+    }
+
+    #[test]
+    fn test_pool_registry() {
+        let context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = Contract::new(accounts(1));
+        assert_eq!(contract.pools(), vec![0, 1]);
+
+        contract.register_pool(
+            7,
+            "ref.test.near".parse().unwrap(),
+            vec![accounts(0), accounts(4)],
+            vec![18, 8],
+        );
+        assert_eq!(contract.pools(), vec![0, 1, 7]);
+        assert_eq!(
+            contract.list_pools()[2].1.tokens,
+            vec![accounts(0), accounts(4)]
+        );
+
+        contract.remove_pool(1);
+        assert_eq!(contract.pools(), vec![0, 7]);
+        assert_eq!(contract.list_pools()[1].1.decimals, vec![18, 8]);
+    }
+
+    #[test]
+    #[should_panic(expected = "The stable pool can't be removed")]
+    fn test_remove_stable_pool() {
+        let context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = Contract::new(accounts(1));
+        contract.remove_pool(0);
+    }
+
+    #[test]
+    #[should_panic(expected = "The pool must contain USN")]
+    fn test_register_pool_without_usn() {
+        let context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = Contract::new(accounts(1));
+        contract.register_pool(
+            7,
+            "ref.test.near".parse().unwrap(),
+            vec![accounts(3), accounts(4)],
+            vec![18, 8],
+        );
+    }
+
+    #[test]
+    fn test_revenue_split() {
+        let context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = Contract::new(accounts(1));
+        contract
+            .stable_treasury
+            .deposit(&mut contract.token, &accounts(2), &usdt_id(), 100000);
+        let total_supply = contract.ft_total_supply();
+
+        contract.set_revenue_split(revenue::RevenueSplit {
+            insurance: 20,
+            burn: 30,
+            insurance_id: Some(accounts(4)),
+        });
+        contract.transfer_commission(accounts(3), U128(1000000000001));
+
+        assert_eq!(contract.ft_balance_of(accounts(3)), U128(500000000001));
+        assert_eq!(contract.ft_balance_of(accounts(4)), U128(200000000000));
+        assert_eq!(
+            contract.ft_total_supply(),
+            U128(total_supply.0 + 700000000001)
+        );
+        assert_eq!(contract.commission().v2.usn, U128(8999999999999));
+        assert_eq!(contract.revenue().accrued.burned, U128(300000000000));
+    }
+
+    #[test]
+    fn test_revenue_split_excludes_commission_v1() {
+        let context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = Contract::new(accounts(1));
+        contract
+            .stable_treasury
+            .deposit(&mut contract.token, &accounts(2), &usdt_id(), 100000);
+        contract.commission.usn = 1000;
+        contract.commission.near = 10;
+
+        contract.set_revenue_split(revenue::RevenueSplit {
+            insurance: 20,
+            burn: 30,
+            insurance_id: Some(accounts(4)),
+        });
+        contract.transfer_commission(accounts(3), U128(1000000001000));
+
+        assert_eq!(contract.ft_balance_of(accounts(3)), U128(500000001000));
+        assert_eq!(contract.ft_balance_of(accounts(4)), U128(200000000000));
+        assert_eq!(contract.revenue().accrued.burned, U128(300000000000));
+        assert_eq!(contract.commission().v1.usn, U128(0));
+    }
+
+    #[test]
+    #[should_panic(expected = "The insurance fund account is not specified")]
+    fn test_revenue_split_without_insurance_fund() {
+        let context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = Contract::new(accounts(1));
+        contract.set_revenue_split(revenue::RevenueSplit {
+            insurance: 20,
+            burn: 0,
+            insurance_id: None,
+        });
+    }
+
+    #[test]
+    fn test_ownership_recovery() {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = Contract::new(accounts(1));
+        contract.set_recovery_guardians(vec![accounts(2), accounts(3), accounts(4)], 2);
+
+        testing_env!(context.predecessor_account_id(accounts(2)).build());
+        contract.approve_recovery(accounts(0));
+        assert!(contract.recovery().proposal.unwrap().unlocks_at.is_none());
+
+        testing_env!(context.predecessor_account_id(accounts(3)).build());
+        contract.approve_recovery(accounts(0));
+        let unlocks_at = contract.recovery().proposal.unwrap().unlocks_at.unwrap();
+
+        testing_env!(context.block_timestamp(unlocks_at.0).build());
+        contract.finish_recovery();
+        assert_eq!(contract.owner(), accounts(0));
+        assert!(contract.recovery().proposal.is_none());
+    }
+
+    #[test]
+    fn test_ownership_recovery_expired_proposal() {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = Contract::new(accounts(1));
+        contract.set_recovery_guardians(vec![accounts(2), accounts(3), accounts(4)], 2);
+
+        testing_env!(context.predecessor_account_id(accounts(2)).build());
+        contract.approve_recovery(accounts(0));
+        let expires_at = contract.recovery().proposal.unwrap().expires_at;
+
+        testing_env!(context
+            .predecessor_account_id(accounts(3))
+            .block_timestamp(expires_at.0)
+            .build());
+        contract.approve_recovery(accounts(4));
+        let proposal = contract.recovery().proposal.unwrap();
+        assert_eq!(proposal.new_owner_id, accounts(4));
+        assert_eq!(proposal.approvals, vec![accounts(3)]);
+    }
+
+    #[test]
+    #[should_panic(expected = "Another recovery is in progress")]
+    fn test_ownership_recovery_another_proposal() {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = Contract::new(accounts(1));
+        contract.set_recovery_guardians(vec![accounts(2), accounts(3), accounts(4)], 2);
+
+        testing_env!(context.predecessor_account_id(accounts(2)).build());
+        contract.approve_recovery(accounts(0));
+
+        testing_env!(context.predecessor_account_id(accounts(3)).build());
+        contract.approve_recovery(accounts(4));
+    }
+
+    #[test]
+    #[should_panic(expected = "The recovery is timelocked")]
+    fn test_ownership_recovery_timelock() {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = Contract::new(accounts(1));
+        contract.set_recovery_guardians(vec![accounts(2)], 1);
+
+        testing_env!(context.predecessor_account_id(accounts(2)).build());
+        contract.approve_recovery(accounts(0));
+        contract.finish_recovery();
+    }
+
+    #[test]
+    #[should_panic(expected = "There is no recovery in progress")]
+    fn test_ownership_recovery_cancel() {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = Contract::new(accounts(1));
+        contract.set_recovery_guardians(vec![accounts(2)], 1);
+
+        testing_env!(context.predecessor_account_id(accounts(2)).build());
+        contract.approve_recovery(accounts(0));
+
+        testing_env!(context.predecessor_account_id(accounts(1)).build());
+        contract.cancel_recovery();
+        contract.finish_recovery();
+    }
 }
@@ -0,0 +1,202 @@
+//! Daily limits of blacklisting and destroying black funds.
+//!
+//! The limits reduce the damage of a compromised owner key, so they can be
+//! changed only after a timelock, giving time to react to a malicious proposal.
+//! A call exceeding a limit is rejected like a non-compliant transfer: it logs
+//! a `blacklist_limit_hit` event and fails by the returned promise.
+
+use crate::*;
+
+const LIMITS_TIMELOCK_SEC: u64 = 2 * 24 * 60 * 60;
+const DAY_NS: u64 = 24 * 60 * 60 * 1_000_000_000;
+
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(crate = "near_sdk::serde")]
+pub struct BlacklistLimits {
+    /// Number of accounts which can be blacklisted during a UTC day.
+    pub max_accounts_per_day: u32,
+    /// Total USN which can be destroyed by `destroy_black_funds` during a UTC day.
+    pub max_destroyed_per_day: U128,
+}
+
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(crate = "near_sdk::serde")]
+pub struct PendingBlacklistLimits {
+    pub limits: Option<BlacklistLimits>,
+    /// Timestamp in nanoseconds.
+    pub activates_at: U64,
+}
+
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug, Default)]
+#[serde(crate = "near_sdk::serde")]
+pub struct BlacklistUsage {
+    pub day: u64,
+    pub accounts: u32,
+    pub destroyed: U128,
+}
+
+/// No limits are applied until the first proposal is activated.
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug, Default)]
+#[serde(crate = "near_sdk::serde")]
+pub struct BlacklistLimiter {
+    pub limits: Option<BlacklistLimits>,
+    pub pending: Option<PendingBlacklistLimits>,
+    pub usage: BlacklistUsage,
+}
+
+impl BlacklistLimiter {
+    fn usage_today(&self) -> BlacklistUsage {
+        let day = env::block_timestamp() / DAY_NS;
+        if self.usage.day == day {
+            self.usage.clone()
+        } else {
+            BlacklistUsage {
+                day,
+                ..Default::default()
+            }
+        }
+    }
+
+    /// Accounts a new blacklisted account.
+    /// Returns the promise failing the call if the daily limit is reached.
+    pub fn use_accounts(&mut self) -> Option<Promise> {
+        let mut usage = self.usage_today();
+        usage.accounts += 1;
+        if let Some(limits) = self.limits.as_ref() {
+            if usage.accounts > limits.max_accounts_per_day {
+                event::emit::blacklist_limit_hit("accounts");
+                return Some(reject(
+                    "The daily limit of blacklisted accounts is reached".to_string(),
+                ));
+            }
+        }
+        self.usage = usage;
+        None
+    }
+
+    /// Accounts the destroyed black funds.
+    /// Returns the promise failing the call if the daily limit is reached.
+    pub fn use_destroyed(&mut self, amount: Balance) -> Option<Promise> {
+        let mut usage = self.usage_today();
+        usage.destroyed = usage.destroyed.0.saturating_add(amount).into();
+        if let Some(limits) = self.limits.as_ref() {
+            if usage.destroyed.0 > limits.max_destroyed_per_day.0 {
+                event::emit::blacklist_limit_hit("destroyed");
+                return Some(reject(
+                    "The daily limit of destroyed black funds is reached".to_string(),
+                ));
+            }
+        }
+        self.usage = usage;
+        None
+    }
+}
+
+#[near_bindgen]
+impl Contract {
+    /// Proposes new limits, `None` removes them. They can be applied after the timelock.
+    /// Only can be called by owner.
+    pub fn propose_blacklist_limits(&mut self, limits: Option<BlacklistLimits>) {
+        self.assert_owner("propose_blacklist_limits");
+        let activates_at = env::block_timestamp() + LIMITS_TIMELOCK_SEC * 10u64.pow(9);
+        self.blacklist_limiter.pending = Some(PendingBlacklistLimits {
+            limits,
+            activates_at: activates_at.into(),
+        });
+        event::emit::blacklist_limits_propose(activates_at);
+    }
+
+    /// Activates the proposed limits after the timelock. Only can be called by owner.
+    pub fn apply_blacklist_limits(&mut self) {
+        self.assert_owner("apply_blacklist_limits");
+        let pending = self
+            .blacklist_limiter
+            .pending
+            .take()
+            .unwrap_or_else(|| env::panic_str("No blacklist limits are proposed"));
+        assert!(
+            env::block_timestamp() >= pending.activates_at.0,
+            "The blacklist limits are timelocked"
+        );
+        self.blacklist_limiter.limits = pending.limits;
+    }
+
+    /// Cancels the proposal. Only can be called by owner or guardians.
+    pub fn cancel_blacklist_limits(&mut self) {
+        self.assert_owner_or_guardian("cancel_blacklist_limits");
+        self.blacklist_limiter.pending = None;
+    }
+
+    pub fn blacklist_limiter(&self) -> BlacklistLimiter {
+        let mut limiter = self.blacklist_limiter.clone();
+        limiter.usage = limiter.usage_today();
+        limiter
+    }
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use super::*;
+    use crate::testing::setup_contract;
+    use near_sdk::test_utils::{accounts, get_logs, VMContextBuilder};
+    use near_sdk::testing_env;
+
+    const TIMELOCK_NS: u64 = LIMITS_TIMELOCK_SEC * 1_000_000_000;
+
+    fn setup_limits() -> (VMContextBuilder, Contract) {
+        let (mut context, mut contract) = setup_contract(accounts(1));
+        contract.propose_blacklist_limits(Some(BlacklistLimits {
+            max_accounts_per_day: 1,
+            max_destroyed_per_day: U128(100),
+        }));
+        testing_env!(context.block_timestamp(TIMELOCK_NS).build());
+        contract.apply_blacklist_limits();
+        (context, contract)
+    }
+
+    #[test]
+    #[should_panic(expected = "The blacklist limits are timelocked")]
+    fn test_limits_timelock() {
+        let (_, mut contract) = setup_limits();
+        contract.propose_blacklist_limits(None);
+        contract.apply_blacklist_limits();
+    }
+
+    #[test]
+    fn test_accounts_limit_resets_daily() {
+        let (mut context, mut contract) = setup_limits();
+        contract.add_to_blacklist(&accounts(2));
+        // Already banned accounts aren't counted.
+        contract.add_to_blacklist(&accounts(2));
+        assert_eq!(contract.blacklist_limiter().usage.accounts, 1);
+
+        testing_env!(context.block_timestamp(TIMELOCK_NS + DAY_NS).build());
+        contract.add_to_blacklist(&accounts(3));
+    }
+
+    #[test]
+    fn test_accounts_limit() {
+        let (_, mut contract) = setup_limits();
+        contract.add_to_blacklist(&accounts(2));
+        contract.add_to_blacklist(&accounts(3));
+        assert_eq!(
+            contract.blacklist_status(&accounts(3)),
+            BlackListStatus::Allowable
+        );
+        assert_eq!(contract.blacklist_limiter().usage.accounts, 1);
+        assert_eq!(
+            get_logs().last().unwrap(),
+            r#"EVENT_JSON:{"data":[{"limit":"accounts"}],"event":"blacklist_limit_hit","standard":"usn","version":"1.0.0"}"#
+        );
+    }
+
+    #[test]
+    fn test_destroyed_limit() {
+        let (_, mut contract) = setup_limits();
+        contract.token.internal_deposit(&accounts(2), 101);
+        contract.add_to_blacklist(&accounts(2));
+        contract.destroy_black_funds(&accounts(2));
+        assert_eq!(contract.ft_balance_of(accounts(2)), U128(101));
+        assert_eq!(contract.blacklist_limiter().usage.destroyed, U128(0));
+    }
+}
@@ -0,0 +1,619 @@
+//! Versioned contract state.
+//!
+//! The version of the state layout is recorded under a raw storage key,
+//! the state before versioning is version 1. `migrate()` applies migration
+//! steps one by one from the recorded version up to `STATE_VERSION`,
+//! so an upgrade can skip intermediate releases.
+//!
+//! Later layouts mostly add fields to the end of `Contract` or `StableTreasury`,
+//! so a step inserts the initial values of the added fields into the raw state
+//! of the previous version, see `migrate_layout`. Other steps rewrite the
+//! changed field in place.
+
+use std::collections::HashMap;
+
+use crate::*;
+
+use near_sdk::IntoStorageKey;
+
+use owner::RecoveryProposal;
+use stable::StableTreasuryV1;
+
+/// Version of the current `Contract` layout.
+pub const STATE_VERSION: u32 = 19;
+const STATE_VERSION_KEY: &[u8] = b"STATE_VERSION";
+const STATE_KEY: &[u8] = b"STATE";
+
+/// The layout of v2.3.4.
+#[derive(BorshDeserialize)]
+pub struct ContractV1 {
+    owner_id: AccountId,
+    proposed_owner_id: AccountId,
+    guardians: UnorderedSet<AccountId>,
+    token: FungibleTokenFreeStorage,
+    metadata: LazyOption<FungibleTokenMetadata>,
+    black_list: LookupMap<AccountId, BlackListStatus>,
+    status: ContractStatus,
+    commission: CommissionV1,
+    stable_treasury: StableTreasuryV1,
+    oracle: Oracle,
+}
+
+impl From<ContractV1> for Contract {
+    fn from(prev: ContractV1) -> Self {
+        Self {
+            owner_id: prev.owner_id,
+            proposed_owner_id: prev.proposed_owner_id,
+            guardians: prev.guardians,
+            token: prev.token,
+            metadata: prev.metadata,
+            black_list: prev.black_list,
+            status: prev.status,
+            commission: prev.commission,
+            stable_treasury: StableTreasury::migrate(
+                prev.stable_treasury,
+                StorageKey::StableTreasury,
+            ),
+            oracle: prev.oracle,
+            transfer_nonces: LookupMap::new(StorageKey::TransferNonces),
+            session_keys: LookupMap::new(StorageKey::SessionKeys),
+            allowances: LookupMap::new(StorageKey::Allowances),
+            frozen_accounts: LookupMap::new(StorageKey::FrozenAccounts),
+            pool_snapshot: None,
+            operators: LookupMap::new(StorageKey::Operators),
+            pool_registry: treasury::pool_registry(StorageKey::PoolRegistry),
+            staking_pools: UnorderedMap::new(StorageKey::StakingPools),
+            revenue: Revenue::default(),
+            guardian_policy: None,
+            pending_guardian_actions: UnorderedMap::new(StorageKey::PendingGuardianActions),
+            recovery: Recovery::default(),
+            treasury_journal: TreasuryJournal::new(StorageKey::TreasuryJournal),
+            withdraw_refund_mode: WithdrawRefundMode::default(),
+            compliance_rules: UnorderedMap::new(StorageKey::ComplianceRules),
+            account_tags: LookupMap::new(StorageKey::AccountTags),
+            daily_transfers: LookupMap::new(StorageKey::DailyTransfers),
+            airdrops: Vector::new(StorageKey::Airdrops),
+            airdrop_claims: LookupMap::new(StorageKey::AirdropClaims),
+            vesting: LookupMap::new(StorageKey::Vesting),
+            blacklist_limiter: BlacklistLimiter::default(),
+            lp_positions: UnorderedMap::new(StorageKey::LpPositions),
+            simple_liquidity: UnorderedMap::new(StorageKey::SimpleLiquidity),
+            minters: UnorderedMap::new(StorageKey::Minters),
+            eth_connector: None,
+            eth_transfer_nonce: 0,
+            eth_proofs: LookupSet::new(StorageKey::EthProofs),
+            wusn_accounts: LookupMap::new(StorageKey::WusnAccounts),
+            wusn_total_supply: 0,
+            backing_floor: None,
+            reserve_snapshots: ReserveSnapshots::new(StorageKey::ReserveSnapshots),
+            oracle_config: OracleConfig::default(),
+            rate_policy: RatePolicy::default(),
+            operation_locks: UnorderedMap::new(StorageKey::OperationLocks),
+            vesting_escrow: 0,
+            gas_config: GasConfig::default(),
+            airdrop_escrow: 0,
+            vesting_escrow_synced: true,
+        }
+    }
+}
+
+/// The fields of `Contract` up to the fields added to `StableTreasury` after v2.
+#[derive(BorshDeserialize)]
+struct ContractHeadV2 {
+    _owner_id: AccountId,
+    _proposed_owner_id: AccountId,
+    _guardians: UnorderedSet<AccountId>,
+    _token: FungibleTokenFreeStorage,
+    _metadata: LazyOption<FungibleTokenMetadata>,
+    _black_list: LookupMap<AccountId, BlackListStatus>,
+    _status: ContractStatus,
+    _commission: CommissionV1,
+    _assets: UnorderedMap<AccountId, AssetInfo>,
+    _reserves: LookupMap<AccountId, Balance>,
+    _failed_withdrawals: LookupMap<AccountId, HashMap<AccountId, Balance>>,
+}
+
+/// The fields of `Contract` up to `recovery` in v15.
+#[derive(BorshDeserialize)]
+struct ContractHeadV15 {
+    _owner_id: AccountId,
+    _proposed_owner_id: AccountId,
+    _guardians: UnorderedSet<AccountId>,
+    _token: FungibleTokenFreeStorage,
+    _metadata: LazyOption<FungibleTokenMetadata>,
+    _black_list: LookupMap<AccountId, BlackListStatus>,
+    _status: ContractStatus,
+    _commission: CommissionV1,
+    _stable_treasury: StableTreasury,
+    _oracle: Oracle,
+    _transfer_nonces: LookupMap<AccountId, u64>,
+    _session_keys: LookupMap<AccountId, Vec<PublicKey>>,
+    _allowances: LookupMap<(AccountId, AccountId), Balance>,
+    _frozen_accounts: LookupMap<AccountId, Timestamp>,
+    _pool_snapshot: Option<PoolSnapshot>,
+    _operators: LookupMap<AccountId, Vec<String>>,
+    _pool_registry: UnorderedMap<u64, PoolInfo>,
+    _staking_pools: UnorderedMap<AccountId, StakingPosition>,
+    _revenue: Revenue,
+    _guardian_policy: Option<GuardianPolicy>,
+    _pending_guardian_actions: UnorderedMap<GuardianAction, PendingGuardianAction>,
+}
+
+/// The layout of `Recovery` in v15, its proposal doesn't expire.
+#[derive(BorshDeserialize)]
+struct RecoveryV15 {
+    guardians: Vec<AccountId>,
+    threshold: u8,
+    proposal: Option<(AccountId, Vec<AccountId>, Option<U64>)>,
+}
+
+/// The fields of `Contract` up to `airdrops` in v16.
+#[derive(BorshDeserialize)]
+struct ContractHeadV16 {
+    _head: ContractHeadV15,
+    _recovery: Recovery,
+    _treasury_journal: TreasuryJournal,
+    _withdraw_refund_mode: WithdrawRefundMode,
+    _compliance_rules: UnorderedMap<String, ComplianceRuleConfig>,
+    _account_tags: LookupMap<AccountId, Vec<String>>,
+    _daily_transfers: LookupMap<AccountId, DailyTransfers>,
+    airdrops: Vector<Airdrop>,
+}
+
+/// The fields of `Contract` up to `gas_config` in v18.
+#[derive(BorshDeserialize)]
+struct ContractHeadV18 {
+    _head: ContractHeadV16,
+    _airdrop_claims: LookupMap<(u64, u64), u64>,
+    _vesting: LookupMap<AccountId, VestingSchedule>,
+    _blacklist_limiter: BlacklistLimiter,
+    _lp_positions: UnorderedMap<u64, LpPosition>,
+    _simple_liquidity: UnorderedMap<u64, SimpleLiquidity>,
+    _minters: UnorderedMap<AccountId, Minter>,
+    _eth_connector: Option<AccountId>,
+    _eth_transfer_nonce: u64,
+    _eth_proofs: LookupSet<CryptoHash>,
+    _wusn_accounts: LookupMap<AccountId, Balance>,
+    _wusn_total_supply: Balance,
+    _backing_floor: Option<u32>,
+    _reserve_snapshots: ReserveSnapshots,
+    _oracle_config: OracleConfig,
+    _rate_policy: RatePolicy,
+    _operation_locks: UnorderedMap<LockedOperation, Timestamp>,
+    _vesting_escrow: Balance,
+}
+
+/// The layout of `GasConfig` in v18, before the storage and staking gas.
+#[derive(BorshDeserialize, BorshSerialize)]
+struct GasConfigV18 {
+    ft_transfer: Gas,
+    refund: Gas,
+    mint_callback: Gas,
+    ref_ft_transfer_call: Gas,
+    ref_add_liquidity: Gas,
+    ref_remove_liquidity: Gas,
+    ref_withdraw: Gas,
+}
+
+impl Default for GasConfigV18 {
+    fn default() -> Self {
+        let config = GasConfig::default();
+        Self {
+            ft_transfer: config.ft_transfer,
+            refund: config.refund,
+            mint_callback: config.mint_callback,
+            ref_ft_transfer_call: config.ref_ft_transfer_call,
+            ref_add_liquidity: config.ref_add_liquidity,
+            ref_remove_liquidity: config.ref_remove_liquidity,
+            ref_withdraw: config.ref_withdraw,
+        }
+    }
+}
+
+fn read_state<T: BorshDeserialize>() -> T {
+    env::state_read().expect("Contract is not initialized")
+}
+
+pub(crate) fn state_version() -> u32 {
+    env::storage_read(STATE_VERSION_KEY).map_or(1, |data| {
+        u32::try_from_slice(&data).expect("Invalid state version")
+    })
+}
+
+pub(crate) fn set_state_version(version: u32) {
+    env::storage_write(STATE_VERSION_KEY, &version.try_to_vec().unwrap());
+}
+
+/// Appends the initial value of a field added to the end of `Contract`.
+fn append<T: BorshSerialize>(state: &mut Vec<u8>, value: T) {
+    value.serialize(state).unwrap();
+}
+
+/// Inserts the initial value of a field added to `StableTreasury` after its fields
+/// added after v2. All of them are maps, serialized as their storage prefixes, so
+/// the previous fields are skipped by their expected `prev_prefixes` in order.
+fn insert_into_stable_treasury<T: BorshSerialize>(
+    state: &mut Vec<u8>,
+    prev_prefixes: &[Vec<u8>],
+    value: T,
+) {
+    let mut rest = &state[..];
+    ContractHeadV2::deserialize(&mut rest).expect("Invalid state");
+    for expected in prev_prefixes {
+        let prefix = Vec::<u8>::deserialize(&mut rest).expect("Invalid state");
+        assert_eq!(&prefix, expected, "Unexpected layout of StableTreasury");
+    }
+    let offset = state.len() - rest.len();
+    state.splice(offset..offset, value.try_to_vec().unwrap());
+}
+
+fn stable_prefix() -> Vec<u8> {
+    StorageKey::StableTreasury.into_storage_key()
+}
+
+/// Rewrites the recovery of v15, the proposal in progress expires from now.
+fn migrate_recovery(state: &mut Vec<u8>) {
+    let mut rest = &state[..];
+    ContractHeadV15::deserialize(&mut rest).expect("Invalid state");
+    let start = state.len() - rest.len();
+    let prev = RecoveryV15::deserialize(&mut rest).expect("Invalid state");
+    let end = state.len() - rest.len();
+    let recovery = Recovery {
+        guardians: prev.guardians,
+        threshold: prev.threshold,
+        proposal: prev.proposal.map(|(new_owner_id, approvals, unlocks_at)| {
+            RecoveryProposal::new(new_owner_id, approvals, unlocks_at)
+        }),
+    };
+    state.splice(start..end, recovery.try_to_vec().unwrap());
+}
+
+/// Rewrites the gas config of v18, the storage and staking gas are the defaults.
+fn migrate_gas_config(state: &mut Vec<u8>) {
+    let mut rest = &state[..];
+    ContractHeadV18::deserialize(&mut rest).expect("Invalid state");
+    let start = state.len() - rest.len();
+    let prev = GasConfigV18::deserialize(&mut rest).expect("Invalid state");
+    let end = state.len() - rest.len();
+    let config = GasConfig {
+        ft_transfer: prev.ft_transfer,
+        refund: prev.refund,
+        mint_callback: prev.mint_callback,
+        ref_ft_transfer_call: prev.ref_ft_transfer_call,
+        ref_add_liquidity: prev.ref_add_liquidity,
+        ref_remove_liquidity: prev.ref_remove_liquidity,
+        ref_withdraw: prev.ref_withdraw,
+        ..GasConfig::default()
+    };
+    state.splice(start..end, config.try_to_vec().unwrap());
+}
+
+/// Unclaimed USN of open airdrops in the raw state of v16.
+fn airdrop_escrow(state: &[u8]) -> Balance {
+    let head = ContractHeadV16::deserialize(&mut &state[..]).expect("Invalid state");
+    head.airdrops
+        .iter()
+        .filter(|airdrop| !airdrop.closed)
+        .map(|airdrop| airdrop.funded.0 - airdrop.claimed.0)
+        .sum()
+}
+
+/// Converts the raw state of `version - 1` into the layout of `version`,
+/// the migration started from the state of `from`.
+fn migrate_layout(from: u32, version: u32, state: &mut Vec<u8>) {
+    match version {
+        3 => {
+            append::<UnorderedMap<AccountId, Minter>>(
+                state,
+                UnorderedMap::new(StorageKey::Minters),
+            );
+        }
+        4 => {
+            append::<Option<AccountId>>(state, None);
+            append::<u64>(state, 0);
+            append::<LookupSet<CryptoHash>>(state, LookupSet::new(StorageKey::EthProofs));
+        }
+        5 => {
+            append::<LookupMap<AccountId, Balance>>(
+                state,
+                LookupMap::new(StorageKey::WusnAccounts),
+            );
+            append::<Balance>(state, 0);
+        }
+        6 => {
+            insert_into_stable_treasury::<LookupMap<AccountId, stable::AssetLimits>>(
+                state,
+                &[],
+                LookupMap::new(stable::limits_prefix(stable_prefix())),
+            );
+        }
+        7 => {
+            append::<Option<u32>>(state, None);
+        }
+        8 => {
+            append::<ReserveSnapshots>(state, ReserveSnapshots::new(StorageKey::ReserveSnapshots));
+        }
+        9 => {
+            append::<OracleConfig>(state, OracleConfig::default());
+        }
+        10 => {
+            insert_into_stable_treasury::<LookupMap<AccountId, stable::AssetPeg>>(
+                state,
+                &[stable::limits_prefix(stable_prefix())],
+                LookupMap::new(stable::pegs_prefix(stable_prefix())),
+            );
+        }
+        11 => {
+            append::<RatePolicy>(state, RatePolicy::default());
+        }
+        12 => {
+            insert_into_stable_treasury::<LookupMap<u64, stable::CommissionEpoch>>(
+                state,
+                &[
+                    stable::limits_prefix(stable_prefix()),
+                    stable::pegs_prefix(stable_prefix()),
+                ],
+                LookupMap::new(stable::commission_epochs_prefix(stable_prefix())),
+            );
+            insert_into_stable_treasury::<stable::CommissionEpoch>(
+                state,
+                &[
+                    stable::limits_prefix(stable_prefix()),
+                    stable::pegs_prefix(stable_prefix()),
+                    stable::commission_epochs_prefix(stable_prefix()),
+                ],
+                stable::CommissionEpoch::default(),
+            );
+        }
+        13 => {
+            append::<UnorderedMap<LockedOperation, Timestamp>>(
+                state,
+                UnorderedMap::new(StorageKey::OperationLocks),
+            );
+        }
+        14 => {
+            append::<Balance>(state, 0);
+        }
+        15 => {
+            append::<GasConfigV18>(state, GasConfigV18::default());
+        }
+        16 => {
+            migrate_recovery(state);
+        }
+        17 => {
+            let escrow = airdrop_escrow(state);
+            append::<Balance>(state, escrow);
+        }
+        18 => {
+            // v14 started counting the vesting escrow from 0, while the schedules
+            // created before it can't be enumerated, the owner sets it once.
+            append::<bool>(state, from >= 14);
+        }
+        19 => {
+            migrate_gas_config(state);
+        }
+        _ => env::panic_str(&format!("Unknown state version {}", version)),
+    }
+}
+
+/// Reads the state of the recorded version and migrates it to the current one.
+pub(crate) fn migrate_state() -> Contract {
+    let version = state_version();
+    let contract = match version {
+        1 => read_state::<ContractV1>().into(),
+        2..=STATE_VERSION => {
+            let mut state = env::storage_read(STATE_KEY).expect("Contract is not initialized");
+            for next in version + 1..=STATE_VERSION {
+                migrate_layout(version, next, &mut state);
+            }
+            Contract::try_from_slice(&state).expect("Invalid state")
+        }
+        _ => env::panic_str(&format!("Unknown state version {}", version)),
+    };
+    set_state_version(STATE_VERSION);
+    contract
+}
+
+#[near_bindgen]
+impl Contract {
+    /// Returns the version of the state layout.
+    pub fn state_version(&self) -> u32 {
+        state_version()
+    }
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use super::*;
+    use crate::testing::setup_contract;
+    use near_sdk::test_utils::accounts;
+    use near_sdk::testing_env;
+
+    /// Rewrites the gas config of the current state in the layout of v18.
+    fn downgrade_gas_config(state: &mut Vec<u8>) {
+        let mut rest = &state[..];
+        ContractHeadV18::deserialize(&mut rest).unwrap();
+        let start = state.len() - rest.len();
+        let config = GasConfig::deserialize(&mut rest).unwrap();
+        let end = state.len() - rest.len();
+        let prev = GasConfigV18 {
+            ft_transfer: config.ft_transfer,
+            ..GasConfigV18::default()
+        };
+        state.splice(start..end, prev.try_to_vec().unwrap());
+    }
+
+    #[test]
+    fn test_state_version() {
+        let (mut context, contract) = setup_contract(accounts(1));
+        assert_eq!(contract.state_version(), STATE_VERSION);
+
+        env::state_write(&contract);
+        // `migrate()` is private.
+        testing_env!(context.predecessor_account_id(accounts(0)).build());
+        let migrated = Contract::migrate();
+        assert_eq!(migrated.owner(), accounts(1));
+        assert_eq!(migrated.state_version(), STATE_VERSION);
+    }
+
+    #[test]
+    fn test_migrate_layout_steps() {
+        let (mut context, contract) = setup_contract(accounts(1));
+        let mut state = contract.try_to_vec().unwrap();
+
+        // v13 to v15, v17 and v18 appended fields to `Contract`.
+        let appended = (
+            UnorderedMap::<LockedOperation, Timestamp>::new(StorageKey::OperationLocks),
+            0u128,
+            GasConfig::default(),
+            0u128,
+            true,
+        )
+            .try_to_vec()
+            .unwrap();
+        state.truncate(state.len() - appended.len());
+
+        // v12 inserted fields into `StableTreasury`.
+        let mut rest = &state[..];
+        ContractHeadV2::deserialize(&mut rest).unwrap();
+        for _ in 0..2 {
+            Vec::<u8>::deserialize(&mut rest).unwrap();
+        }
+        let offset = state.len() - rest.len();
+        let inserted = (
+            LookupMap::<u64, stable::CommissionEpoch>::new(stable::commission_epochs_prefix(
+                stable_prefix(),
+            )),
+            stable::CommissionEpoch::default(),
+        )
+            .try_to_vec()
+            .unwrap();
+        state.drain(offset..offset + inserted.len());
+
+        env::storage_write(STATE_KEY, &state);
+        set_state_version(11);
+        testing_env!(context.predecessor_account_id(accounts(0)).build());
+        let mut migrated = Contract::migrate();
+        assert!(!migrated.is_vesting_escrow_synced());
+        migrated.vesting_escrow_synced = true;
+        assert_eq!(
+            migrated.try_to_vec().unwrap(),
+            contract.try_to_vec().unwrap()
+        );
+    }
+
+    #[test]
+    fn test_migrate_airdrop_escrow() {
+        let (mut context, mut contract) = setup_contract(accounts(1));
+        testing_env!(context.attached_deposit(1).build());
+        contract.token.internal_deposit(&accounts(1), 1000);
+        contract.create_airdrop(vec![0; 32].into(), U128(600));
+
+        // v17 appended the airdrop escrow, v18 the vesting escrow flag.
+        let mut state = contract.try_to_vec().unwrap();
+        state.truncate(state.len() - 17);
+        downgrade_gas_config(&mut state);
+        env::storage_write(STATE_KEY, &state);
+        set_state_version(16);
+
+        testing_env!(context.predecessor_account_id(accounts(0)).build());
+        let migrated = Contract::migrate();
+        assert_eq!(migrated.escrowed_contract_usn(), 600);
+        assert!(migrated.is_vesting_escrow_synced());
+    }
+
+    #[test]
+    fn test_migrate_vesting_escrow() {
+        let (mut context, mut contract) = setup_contract(accounts(1));
+        testing_env!(context.attached_deposit(1).build());
+        contract.token.internal_deposit(&accounts(1), 1000);
+        contract.create_vesting(accounts(2), U128(1000), U64(0), U64(0), U64(1000));
+        contract.token.internal_deposit(&accounts(0), 50);
+
+        // v14 appended the vesting escrow, the schedule of v13 isn't counted.
+        let mut state = contract.try_to_vec().unwrap();
+        let appended = (0u128, GasConfig::default(), 0u128, true)
+            .try_to_vec()
+            .unwrap();
+        state.truncate(state.len() - appended.len());
+        env::storage_write(STATE_KEY, &state);
+        set_state_version(13);
+
+        testing_env!(context
+            .predecessor_account_id(accounts(0))
+            .block_timestamp(300)
+            .build());
+        let mut contract = Contract::migrate();
+        assert!(!contract.is_vesting_escrow_synced());
+        assert_eq!(contract.burn_untracked_usn(Balance::MAX), 0);
+
+        // Claims don't underflow the unsynced escrow.
+        testing_env!(context.predecessor_account_id(accounts(2)).build());
+        assert_eq!(contract.claim_vested(), U128(300));
+
+        testing_env!(context.predecessor_account_id(accounts(1)).build());
+        contract.set_vesting_escrow(U128(700));
+        assert_eq!(contract.untracked_contract_usn(), U128(50));
+        assert_eq!(contract.reconcile_contract_usn(), U128(50));
+        assert_eq!(contract.ft_balance_of(accounts(0)), U128(700));
+    }
+
+    #[test]
+    fn test_migrate_gas_config() {
+        let (mut context, mut contract) = setup_contract(accounts(1));
+        let mut config = contract.gas_config();
+        config.ft_transfer = Gas(40_000_000_000_000);
+        contract.set_gas_config(config);
+
+        let mut state = contract.try_to_vec().unwrap();
+        downgrade_gas_config(&mut state);
+        env::storage_write(STATE_KEY, &state);
+        set_state_version(18);
+
+        testing_env!(context.predecessor_account_id(accounts(0)).build());
+        let migrated = Contract::migrate();
+        assert_eq!(migrated.gas_config(), config);
+    }
+
+    #[test]
+    fn test_migrate_recovery_proposal() {
+        let (mut context, mut contract) = setup_contract(accounts(1));
+        contract.set_recovery_guardians(vec![accounts(2), accounts(3)], 2);
+        testing_env!(context.predecessor_account_id(accounts(2)).build());
+        contract.approve_recovery(accounts(4));
+
+        // v17 appended the airdrop escrow, v18 the vesting escrow flag,
+        // the v15 proposal has no expiry.
+        let mut state = contract.try_to_vec().unwrap();
+        state.truncate(state.len() - 17);
+        downgrade_gas_config(&mut state);
+        let mut rest = &state[..];
+        ContractHeadV15::deserialize(&mut rest).unwrap();
+        Recovery::deserialize(&mut rest).unwrap();
+        let end = state.len() - rest.len();
+        state.drain(end - 8..end);
+        env::storage_write(STATE_KEY, &state);
+        set_state_version(15);
+
+        testing_env!(context
+            .predecessor_account_id(accounts(0))
+            .block_timestamp(100)
+            .build());
+        let migrated = Contract::migrate();
+        let proposal = migrated.recovery().proposal.unwrap();
+        assert_eq!(proposal.new_owner_id, accounts(4));
+        assert_eq!(proposal.approvals, vec![accounts(2)]);
+        assert_eq!(proposal.expires_at.0, 100 + 7 * 24 * 60 * 60 * 10u64.pow(9));
+        assert_eq!(migrated.owner(), accounts(1));
+    }
+
+    #[test]
+    #[should_panic(expected = "Unknown state version 100")]
+    fn test_unknown_state_version() {
+        let (mut context, contract) = setup_contract(accounts(1));
+        env::state_write(&contract);
+        set_state_version(100);
+        testing_env!(context.predecessor_account_id(accounts(0)).build());
+        Contract::migrate();
+    }
+}
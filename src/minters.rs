@@ -0,0 +1,167 @@
+//! Permissioned minters, e.g. bridges of wrapped USN.
+//!
+//! A minter mints USN within the remaining cap and the daily limit set by
+//! the owner. Burned USN returns to the cap, so the cap bounds the net supply
+//! issued by the minter.
+
+use crate::*;
+
+use near_sdk::require;
+
+const DAY_NS: u64 = 24 * 60 * 60 * 1_000_000_000;
+
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(crate = "near_sdk::serde")]
+pub struct Minter {
+    /// USN which can be minted yet.
+    pub cap: U128,
+    pub daily_limit: U128,
+    /// UTC day of `minted_today`.
+    pub day: u64,
+    pub minted_today: U128,
+}
+
+impl Minter {
+    pub(crate) fn mint(&mut self, amount: Balance) {
+        let day = env::block_timestamp() / DAY_NS;
+        if self.day != day {
+            self.day = day;
+            self.minted_today = U128(0);
+        }
+        require!(amount <= self.cap.0, "The minter cap is exceeded");
+        let minted_today = self.minted_today.0.saturating_add(amount);
+        require!(
+            minted_today <= self.daily_limit.0,
+            "The minter daily limit is exceeded"
+        );
+        self.cap = (self.cap.0 - amount).into();
+        self.minted_today = minted_today.into();
+    }
+
+    pub(crate) fn burn(&mut self, amount: Balance) {
+        self.cap = self.cap.0.saturating_add(amount).into();
+    }
+}
+
+#[near_bindgen]
+impl Contract {
+    /// Adds or updates the minter. Only can be called by owner.
+    pub fn set_minter(&mut self, account_id: AccountId, cap: U128, daily_limit: U128) {
+        self.assert_owner("set_minter");
+        let mut minter = self.minters.get(&account_id).unwrap_or(Minter {
+            cap,
+            daily_limit,
+            day: 0,
+            minted_today: U128(0),
+        });
+        minter.cap = cap;
+        minter.daily_limit = daily_limit;
+        self.minters.insert(&account_id, &minter);
+        event::emit::minter_set(&account_id, cap.into(), daily_limit.into());
+    }
+
+    /// Only can be called by owner.
+    pub fn remove_minter(&mut self, account_id: AccountId) {
+        self.assert_owner("remove_minter");
+        if self.minters.remove(&account_id).is_none() {
+            env::panic_str(&format!("The minter '{}' doesn't exist", account_id));
+        }
+        event::emit::minter_remove(&account_id);
+    }
+
+    pub fn minters(&self) -> Vec<(AccountId, Minter)> {
+        self.minters.to_vec()
+    }
+
+    /// Mints `amount` of USN to the receiver. Only can be called by minters.
+    pub fn mint_bridged(&mut self, receiver_id: AccountId, amount: U128) {
+        self.abort_if_pause();
+        self.abort_if_blacklisted(&receiver_id);
+        let minter_id = env::predecessor_account_id();
+        let mut minter = self.minter_or_panic(&minter_id);
+        minter.mint(amount.into());
+        self.minters.insert(&minter_id, &minter);
+
+        self.token.internal_deposit(&receiver_id, amount.into());
+        event::emit::ft_mint(&receiver_id, amount.into(), Some("Bridge"));
+        event::emit::bridged_mint(&minter_id, &receiver_id, amount.into());
+        self.check_backing();
+    }
+
+    /// Burns `amount` of the minter's USN. Only can be called by minters.
+    #[payable]
+    pub fn burn_bridged(&mut self, amount: U128) {
+        assert_one_yocto();
+        self.abort_if_pause();
+        let minter_id = env::predecessor_account_id();
+        let mut minter = self.minter_or_panic(&minter_id);
+        minter.burn(amount.into());
+        self.minters.insert(&minter_id, &minter);
+
+        self.token.internal_withdraw(&minter_id, amount.into());
+        event::emit::ft_burn(&minter_id, amount.into(), Some("Bridge"));
+        event::emit::bridged_burn(&minter_id, amount.into());
+    }
+
+    pub(crate) fn minter_or_panic(&self, account_id: &AccountId) -> Minter {
+        self.minters
+            .get(account_id)
+            .unwrap_or_else(|| env::panic_str("This method can be called only by minter"))
+    }
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use super::*;
+    use crate::testing::setup_contract;
+    use near_sdk::test_utils::{accounts, VMContextBuilder};
+    use near_sdk::{testing_env, ONE_YOCTO};
+
+    fn setup_minter() -> (VMContextBuilder, Contract) {
+        let (mut context, mut contract) = setup_contract(accounts(1));
+        contract.set_minter(accounts(2), U128(1000), U128(600));
+        testing_env!(context.predecessor_account_id(accounts(2)).build());
+        (context, contract)
+    }
+
+    #[test]
+    fn test_mint_burn_bridged() {
+        let (mut context, mut contract) = setup_minter();
+        contract.mint_bridged(accounts(3), U128(500));
+        assert_eq!(contract.ft_balance_of(accounts(3)), U128(500));
+        assert_eq!(contract.ft_total_supply(), U128(500));
+
+        contract.mint_bridged(accounts(2), U128(100));
+        testing_env!(context.attached_deposit(ONE_YOCTO).build());
+        contract.burn_bridged(U128(100));
+        assert_eq!(contract.ft_total_supply(), U128(500));
+        assert_eq!(contract.minters()[0].1.cap, U128(500));
+
+        testing_env!(context.block_timestamp(DAY_NS).build());
+        contract.mint_bridged(accounts(3), U128(500));
+        assert_eq!(contract.minters()[0].1.cap, U128(0));
+    }
+
+    #[test]
+    #[should_panic(expected = "The minter daily limit is exceeded")]
+    fn test_minter_daily_limit() {
+        let (_, mut contract) = setup_minter();
+        contract.mint_bridged(accounts(3), U128(500));
+        contract.mint_bridged(accounts(3), U128(101));
+    }
+
+    #[test]
+    #[should_panic(expected = "The minter cap is exceeded")]
+    fn test_minter_cap() {
+        let (_, mut contract) = setup_minter();
+        contract.mint_bridged(accounts(3), U128(1001));
+    }
+
+    #[test]
+    #[should_panic(expected = "This method can be called only by minter")]
+    fn test_not_minter() {
+        let (mut context, mut contract) = setup_minter();
+        testing_env!(context.predecessor_account_id(accounts(3)).build());
+        contract.mint_bridged(accounts(3), U128(1));
+    }
+}
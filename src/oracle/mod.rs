@@ -5,4 +5,4 @@ pub use oracle::*;
 
 // Exposing original priceoracle DTO allows to decrease
 // gas consumption from 25 to 19 TGas (~24%).
-pub use priceoracle::PriceData;
+pub use priceoracle::{ext_priceoracle, Price, PriceData};
@@ -1,34 +1,143 @@
-use near_sdk::Timestamp;
+use near_sdk::{require, Timestamp};
 
-use crate::oracle::priceoracle::{ext_priceoracle, PriceData};
+use crate::oracle::priceoracle::{ext_priceoracle, AssetId, PriceData};
 use crate::*;
 
-struct OracleConfig {
+struct DefaultConfig {
     oracle_address: &'static str,
     asset_id: &'static str,
     gas: Gas,
 }
 
-const CONFIG: OracleConfig = if cfg!(feature = "mainnet") {
-    OracleConfig {
+const DEFAULT_CONFIG: DefaultConfig = if cfg!(feature = "mainnet") {
+    DefaultConfig {
         oracle_address: "priceoracle.near",
         asset_id: "wrap.near", // NEARUSDT
         gas: Gas(5_000_000_000_000),
     }
 } else if cfg!(feature = "testnet") {
-    OracleConfig {
+    DefaultConfig {
         oracle_address: "priceoracle.testnet",
         asset_id: "wrap.testnet", // NEARUSDT
         gas: Gas(5_000_000_000_000),
     }
 } else {
-    OracleConfig {
+    DefaultConfig {
         oracle_address: "priceoracle.test.near",
         asset_id: "wrap.test.near",
         gas: Gas(5_000_000_000_000),
     }
 };
 
+const MIN_ORACLE_GAS: Gas = Gas(2_000_000_000_000);
+const MAX_ORACLE_GAS: Gas = Gas(20_000_000_000_000);
+
+/// The price feed, initially the one the contract has been built with.
+#[derive(BorshSerialize, BorshDeserialize, Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(crate = "near_sdk::serde")]
+pub struct OracleConfig {
+    pub oracle_id: AccountId,
+    /// NEARUSDT feed: the wNEAR token.
+    pub asset_id: AccountId,
+    /// EMA feed of the same asset, e.g. `wrap.near#3600`.
+    pub smooth_asset_id: Option<String>,
+    pub gas: Gas,
+}
+
+impl Default for OracleConfig {
+    fn default() -> Self {
+        Self {
+            oracle_id: DEFAULT_CONFIG.oracle_address.parse().unwrap(),
+            asset_id: DEFAULT_CONFIG.asset_id.parse().unwrap(),
+            smooth_asset_id: None,
+            gas: DEFAULT_CONFIG.gas,
+        }
+    }
+}
+
+impl OracleConfig {
+    pub fn is_valid(&self) -> bool {
+        self.smooth_asset_id
+            .as_ref()
+            .map_or(true, |smooth_asset_id| {
+                !smooth_asset_id.is_empty() && smooth_asset_id != self.asset_id.as_str()
+            })
+            && self.gas >= MIN_ORACLE_GAS
+            && self.gas <= MAX_ORACLE_GAS
+    }
+
+    pub fn assert_valid(&self) {
+        require!(self.is_valid(), "Invalid oracle config");
+    }
+
+    pub fn get_exchange_rate_promise(&self) -> Promise {
+        let mut asset_ids = vec![self.asset_id.to_string()];
+        asset_ids.extend(self.smooth_asset_id.clone());
+        ext_priceoracle::get_price_data(asset_ids, self.oracle_id.clone(), 0, self.gas)
+    }
+
+    /// The smooth rate is omitted if it isn't configured or provided by the oracle.
+    pub fn exchange_rates(&self, price_data: PriceData) -> ExchangeRates {
+        ExchangeRates {
+            current: ExchangeRate::new(&price_data, &self.asset_id.to_string()),
+            smooth: self
+                .smooth_asset_id
+                .as_ref()
+                .filter(|smooth_asset_id| price_data.find_price(smooth_asset_id).is_some())
+                .map(|smooth_asset_id| ExchangeRate::new(&price_data, smooth_asset_id)),
+        }
+    }
+}
+
+/// Which of the reported rates is used for minting.
+#[derive(
+    BorshSerialize, BorshDeserialize, Serialize, Deserialize, Clone, Copy, Debug, PartialEq,
+)]
+#[serde(crate = "near_sdk::serde")]
+pub enum RatePolicy {
+    /// The current rate.
+    Current,
+    /// The lower of the current and the smooth rates, so a price spike doesn't mint
+    /// more USN. Falls back to the current rate without the smooth one.
+    Conservative,
+}
+
+impl Default for RatePolicy {
+    fn default() -> Self {
+        RatePolicy::Conservative
+    }
+}
+
+#[derive(Serialize, Clone, Copy, Debug, PartialEq)]
+#[serde(crate = "near_sdk::serde")]
+#[serde(rename_all = "snake_case")]
+pub enum RateSource {
+    Current,
+    Smooth,
+}
+
+/// The current and the smooth (EMA) rates of the same price data.
+pub struct ExchangeRates {
+    current: ExchangeRate,
+    smooth: Option<ExchangeRate>,
+}
+
+impl ExchangeRates {
+    pub fn current(&self) -> &ExchangeRate {
+        &self.current
+    }
+
+    /// The rate for minting USN for NEAR under the policy.
+    pub fn mint_rate(&self, policy: RatePolicy) -> (&ExchangeRate, RateSource) {
+        match (policy, &self.smooth) {
+            (RatePolicy::Conservative, Some(smooth)) if smooth.is_lower(&self.current) => {
+                (smooth, RateSource::Smooth)
+            }
+            _ => (&self.current, RateSource::Current),
+        }
+    }
+}
+
 #[derive(BorshSerialize, BorshDeserialize, Serialize, Deserialize, Clone)]
 #[serde(crate = "near_sdk::serde")]
 pub struct ExchangeRate {
@@ -50,6 +159,12 @@ impl ExchangeRate {
     pub fn timestamp(&self) -> Timestamp {
         self.timestamp
     }
+
+    /// Compares `multiplier / 10^decimals` of both rates.
+    fn is_lower(&self, other: &ExchangeRate) -> bool {
+        U256::from(self.multiplier) * U256::exp10(other.decimals.into())
+            < U256::from(other.multiplier) * U256::exp10(self.decimals.into())
+    }
 }
 
 #[derive(BorshSerialize, BorshDeserialize)]
@@ -63,20 +178,9 @@ impl Default for Oracle {
     }
 }
 
-impl Oracle {
-    pub fn get_exchange_rate_promise() -> Promise {
-        ext_priceoracle::get_price_data(
-            vec![CONFIG.asset_id.into()],
-            CONFIG.oracle_address.parse().unwrap(),
-            0,
-            CONFIG.gas,
-        )
-    }
-}
-
-impl From<PriceData> for ExchangeRate {
-    fn from(price_data: PriceData) -> Self {
-        let price = price_data.price(&CONFIG.asset_id.into());
+impl ExchangeRate {
+    fn new(price_data: &PriceData, asset_id: &AssetId) -> Self {
+        let price = price_data.price(asset_id);
 
         if env::block_timestamp() >= price_data.timestamp() + price_data.recency_duration() {
             env::panic_str("Oracle provided an outdated price data");
@@ -104,3 +208,47 @@ impl ExchangeRate {
         }
     }
 }
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use super::*;
+
+    fn rate(multiplier: u128, decimals: u8) -> ExchangeRate {
+        ExchangeRate {
+            multiplier,
+            decimals,
+            timestamp: 0,
+            recency_duration: 0,
+        }
+    }
+
+    #[test]
+    fn test_mint_rate() {
+        let rates = ExchangeRates {
+            current: rate(111439, 28),
+            smooth: Some(rate(1100000, 29)),
+        };
+        let (mint_rate, source) = rates.mint_rate(RatePolicy::Conservative);
+        assert_eq!(source, RateSource::Smooth);
+        assert_eq!(mint_rate.multiplier(), 1100000);
+        assert_eq!(rates.mint_rate(RatePolicy::Current).1, RateSource::Current);
+
+        let rates = ExchangeRates {
+            current: rate(111439, 28),
+            smooth: Some(rate(120000, 28)),
+        };
+        assert_eq!(
+            rates.mint_rate(RatePolicy::Conservative).1,
+            RateSource::Current
+        );
+
+        let rates = ExchangeRates {
+            current: rate(111439, 28),
+            smooth: None,
+        };
+        assert_eq!(
+            rates.mint_rate(RatePolicy::Conservative).1,
+            RateSource::Current
+        );
+    }
+}
@@ -5,7 +5,7 @@ use near_sdk::{ext_contract, json_types::U64, Timestamp};
 use crate::*;
 
 // From https://github.com/NearDeFi/price-oracle/blob/main/src/*.rs
-type AssetId = String;
+pub type AssetId = String;
 type DurationSec = u32;
 
 // From https://github.com/NearDeFi/price-oracle/blob/main/src/utils.rs
@@ -44,12 +44,14 @@ impl PriceData {
 
     pub fn price(&self, asset: &AssetId) -> Price {
         let asset_error = format!("Oracle has NOT provided an exchange rate for {}", asset);
+        self.find_price(asset).expect(&asset_error)
+    }
+
+    pub fn find_price(&self, asset: &AssetId) -> Option<Price> {
         self.prices
             .iter()
             .find(|aop| &aop.asset_id == asset)
-            .expect(&asset_error)
-            .price
-            .expect(&asset_error)
+            .and_then(|aop| aop.price)
     }
 }
 
@@ -1,22 +1,90 @@
 use crate::*;
 
+/// Delay between the approval of a new owner by recovery guardians and the ownership change.
+const RECOVERY_TIMELOCK_SEC: u64 = 14 * 24 * 60 * 60;
+const RECOVERY_PROPOSAL_TTL_SEC: u64 = 7 * 24 * 60 * 60;
+
+/// Social recovery of the ownership if the owner key is lost.
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug, Default)]
+#[serde(crate = "near_sdk::serde")]
+pub struct Recovery {
+    pub guardians: Vec<AccountId>,
+    pub threshold: u8,
+    pub proposal: Option<RecoveryProposal>,
+}
+
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct RecoveryProposal {
+    pub new_owner_id: AccountId,
+    pub approvals: Vec<AccountId>,
+    /// Set once the threshold is reached, timestamp in nanoseconds.
+    pub unlocks_at: Option<U64>,
+    /// Until the threshold is reached, timestamp in nanoseconds.
+    pub expires_at: U64,
+}
+
+impl RecoveryProposal {
+    pub(crate) fn new(
+        new_owner_id: AccountId,
+        approvals: Vec<AccountId>,
+        unlocks_at: Option<U64>,
+    ) -> Self {
+        Self {
+            new_owner_id,
+            approvals,
+            unlocks_at,
+            expires_at: (env::block_timestamp() + RECOVERY_PROPOSAL_TTL_SEC * 10u64.pow(9)).into(),
+        }
+    }
+}
+
+/// Maintenance methods which can be delegated to operators.
+const OPERATOR_METHODS: [&str; 3] = [
+    "transfer_stable_liquidity",
+    "transfer_simple_liquidity",
+    "withdraw_stable_pool",
+];
+
 #[near_bindgen]
 impl Contract {
-    pub(crate) fn assert_owner(&self) {
+    /// Allows owner and records the call of the `method` in the admin log.
+    pub(crate) fn assert_owner(&self, method: &str) {
         if env::predecessor_account_id() != self.owner_id {
             env::panic_str("This method can be called only by owner")
         }
+        admin_log::record(method);
     }
 
-    pub(crate) fn assert_owner_or_guardian(&self) {
+    /// Allows owner or guardians and records the call of the `method` in the admin log.
+    pub(crate) fn assert_owner_or_guardian(&self, method: &str) {
         let predecessor_id = env::predecessor_account_id();
         if predecessor_id != self.owner_id && !self.guardians.contains(&predecessor_id) {
             env::panic_str("This method can be called only by owner or guardian")
         }
+        admin_log::record(method);
+    }
+
+    /// Allows owner or an operator permitted to call the `method`
+    /// and records the call in the admin log.
+    pub(crate) fn assert_operator(&self, method: &str) {
+        let predecessor_id = env::predecessor_account_id();
+        if predecessor_id != self.owner_id
+            && !self
+                .operators
+                .get(&predecessor_id)
+                .map_or(false, |methods| methods.iter().any(|m| m == method))
+        {
+            env::panic_str(&format!(
+                "This method can be called only by owner or operator of '{}'",
+                method
+            ))
+        }
+        admin_log::record(method);
     }
 
     pub fn propose_new_owner(&mut self, proposed_owner_id: AccountId) {
-        self.assert_owner();
+        self.assert_owner("propose_new_owner");
         self.proposed_owner_id = proposed_owner_id;
     }
 
@@ -32,25 +100,150 @@ impl Contract {
 
     /// Extend guardians. Only can be called by owner.
     pub fn extend_guardians(&mut self, guardians: Vec<AccountId>) {
-        self.assert_owner();
-        for guardian in guardians {
-            if !self.guardians.insert(&guardian) {
+        self.assert_owner("extend_guardians");
+        for guardian in guardians.iter() {
+            if !self.guardians.insert(guardian) {
                 env::panic_str(&format!("The guardian '{}' already exists", guardian));
             }
         }
+        event::emit::guardians_add(&guardians);
     }
 
     /// Remove guardians. Only can be called by owner.
+    /// Fails if the guardian policy threshold becomes unreachable.
     pub fn remove_guardians(&mut self, guardians: Vec<AccountId>) {
-        self.assert_owner();
-        for guardian in guardians {
-            if !self.guardians.remove(&guardian) {
+        self.assert_owner("remove_guardians");
+        for guardian in guardians.iter() {
+            if !self.guardians.remove(guardian) {
                 env::panic_str(&format!("The guardian '{}' doesn't exist", guardian));
             }
         }
+        if let Some(policy) = self.guardian_policy.as_ref() {
+            assert!(
+                policy.threshold as u64 <= self.guardians.len(),
+                "The guardian policy threshold would be unreachable"
+            );
+        }
+        event::emit::guardians_remove(&guardians);
     }
 
     pub fn guardians(&self) -> Vec<AccountId> {
         self.guardians.to_vec()
     }
+
+    /// Sets recovery guardians who can jointly change the owner after a timelock.
+    /// Cancels the recovery in progress. Only can be called by owner.
+    pub fn set_recovery_guardians(&mut self, guardians: Vec<AccountId>, threshold: u8) {
+        self.assert_owner("set_recovery_guardians");
+        assert!(
+            guardians.is_empty() || (threshold > 0 && threshold as usize <= guardians.len()),
+            "The threshold is out of bounds"
+        );
+        assert!(
+            !guardians.contains(&self.owner_id),
+            "The owner can't be a recovery guardian"
+        );
+        self.recovery = Recovery {
+            guardians,
+            threshold,
+            proposal: None,
+        };
+    }
+
+    /// Approves the new owner. The ownership can be taken over with `finish_recovery`
+    /// after the timelock since enough recovery guardians approved it.
+    /// A proposal which doesn't reach the threshold in time can be replaced.
+    pub fn approve_recovery(&mut self, new_owner_id: AccountId) {
+        let guardian_id = env::predecessor_account_id();
+        assert!(
+            self.recovery.guardians.contains(&guardian_id),
+            "This method can be called only by recovery guardian"
+        );
+
+        let mut proposal = match self.recovery.proposal.take() {
+            Some(proposal)
+                if proposal.unlocks_at.is_some()
+                    || env::block_timestamp() < proposal.expires_at.0 =>
+            {
+                proposal
+            }
+            _ => RecoveryProposal::new(new_owner_id.clone(), vec![], None),
+        };
+        assert_eq!(
+            proposal.new_owner_id, new_owner_id,
+            "Another recovery is in progress"
+        );
+        assert!(
+            !proposal.approvals.contains(&guardian_id),
+            "The recovery is already approved by the caller"
+        );
+        proposal.approvals.push(guardian_id.clone());
+        if proposal.unlocks_at.is_none()
+            && proposal.approvals.len() >= self.recovery.threshold as usize
+        {
+            proposal.unlocks_at =
+                Some((env::block_timestamp() + RECOVERY_TIMELOCK_SEC * 10u64.pow(9)).into());
+        }
+        event::emit::recovery_approve(&guardian_id, &new_owner_id, proposal.unlocks_at);
+        self.recovery.proposal = Some(proposal);
+    }
+
+    /// Cancels the recovery in progress. Only can be called by owner.
+    pub fn cancel_recovery(&mut self) {
+        self.assert_owner("cancel_recovery");
+        let proposal = self
+            .recovery
+            .proposal
+            .take()
+            .unwrap_or_else(|| env::panic_str("There is no recovery in progress"));
+        event::emit::recovery_cancel(&proposal.new_owner_id);
+    }
+
+    /// Transfers the ownership to the approved owner after the timelock.
+    pub fn finish_recovery(&mut self) {
+        let proposal = self
+            .recovery
+            .proposal
+            .take()
+            .unwrap_or_else(|| env::panic_str("There is no recovery in progress"));
+        match proposal.unlocks_at {
+            Some(unlocks_at) if env::block_timestamp() >= unlocks_at.0 => {}
+            Some(_) => env::panic_str("The recovery is timelocked"),
+            None => env::panic_str("The recovery is not approved"),
+        }
+        let previous_owner_id = std::mem::replace(&mut self.owner_id, proposal.new_owner_id);
+        self.proposed_owner_id = self.owner_id.clone();
+        event::emit::ownership_recover(&previous_owner_id, &self.owner_id);
+    }
+
+    pub fn recovery(&self) -> Recovery {
+        self.recovery.clone()
+    }
+
+    /// Permits the operator to call only the listed maintenance methods,
+    /// replacing its previous permissions. Only can be called by owner.
+    pub fn set_operator(&mut self, account_id: AccountId, methods: Vec<String>) {
+        self.assert_owner("set_operator");
+        assert!(!methods.is_empty(), "The list of methods is empty");
+        for method in methods.iter() {
+            if !OPERATOR_METHODS.contains(&method.as_str()) {
+                env::panic_str(&format!("The method '{}' can't be delegated", method));
+            }
+        }
+        self.operators.insert(&account_id, &methods);
+        event::emit::operator_set(&account_id, &methods);
+    }
+
+    /// Remove operator. Only can be called by owner.
+    pub fn remove_operator(&mut self, account_id: AccountId) {
+        self.assert_owner("remove_operator");
+        if self.operators.remove(&account_id).is_none() {
+            env::panic_str(&format!("The operator '{}' doesn't exist", account_id));
+        }
+        event::emit::operator_remove(&account_id);
+    }
+
+    pub fn operator_methods(&self, account_id: AccountId) -> Vec<String> {
+        self.operators.get(&account_id).unwrap_or_default()
+    }
 }
@@ -0,0 +1,117 @@
+//! De-peg detection of the stable assets.
+//!
+//! Prices of the stable assets are synced from the oracle on demand and cached,
+//! so deposits and withdrawals check them synchronously. An asset below the
+//! tolerance band can't be deposited, an asset above it can't be withdrawn,
+//! and a price older than an hour blocks both until it's synced again.
+
+use crate::*;
+
+use near_sdk::require;
+
+use crate::oracle::ext_priceoracle;
+use crate::stable::AssetAction;
+
+const GAS_FOR_STABLE_PRICES_CALLBACK: Gas = Gas(10_000_000_000_000);
+/// 50%, a wider band doesn't protect anything.
+const MAX_PEG_TOLERANCE: u32 = 5_000;
+
+#[near_bindgen]
+impl Contract {
+    /// Sets the allowed deviation of the asset price from $1 in basis points,
+    /// `None` disables the check. Only can be called by owner.
+    pub fn set_peg_tolerance(&mut self, asset_id: AccountId, tolerance: Option<u32>) {
+        self.assert_owner("set_peg_tolerance");
+        require!(
+            tolerance.map_or(true, |tolerance| tolerance <= MAX_PEG_TOLERANCE),
+            "The peg tolerance is too wide"
+        );
+        self.stable_treasury.set_peg_tolerance(&asset_id, tolerance);
+    }
+
+    pub fn asset_peg(&self, asset_id: AccountId) -> AssetPeg {
+        self.stable_treasury.peg(&asset_id)
+    }
+
+    /// Refreshes the oracle prices of all stable assets. Anyone can call it.
+    pub fn sync_stable_prices(&mut self) -> Promise {
+        let asset_ids: Vec<AccountId> = self
+            .stable_treasury
+            .supported_assets()
+            .into_iter()
+            .map(|(asset_id, _)| asset_id)
+            .collect();
+
+        ext_priceoracle::get_price_data(
+            asset_ids
+                .iter()
+                .map(|asset_id| asset_id.to_string())
+                .collect(),
+            self.oracle_config.oracle_id.clone(),
+            NO_DEPOSIT,
+            self.oracle_config.gas,
+        )
+        .then(ext_self::handle_stable_prices(
+            asset_ids,
+            env::current_account_id(),
+            NO_DEPOSIT,
+            GAS_FOR_STABLE_PRICES_CALLBACK,
+        ))
+    }
+}
+
+impl Contract {
+    /// Checks the peg before the withdrawal of the asset.
+    /// Returns the promise failing the call if the peg doesn't allow it,
+    /// the caller returns it without withdrawing.
+    pub(crate) fn check_withdraw_peg(
+        &self,
+        account_id: &AccountId,
+        asset_id: &AccountId,
+    ) -> Option<Promise> {
+        let reason = self
+            .stable_treasury
+            .peg_violation(asset_id, AssetAction::Withdraw)?;
+        let peg = self.stable_treasury.peg(asset_id);
+        event::emit::stable_peg_reject(account_id, asset_id, &peg, &reason);
+        Some(reject(reason))
+    }
+}
+
+#[ext_contract(ext_self)]
+trait StablePricesHandler {
+    #[private]
+    fn handle_stable_prices(
+        &mut self,
+        asset_ids: Vec<AccountId>,
+        #[callback] price_data: PriceData,
+    );
+}
+
+trait StablePricesHandler {
+    fn handle_stable_prices(&mut self, asset_ids: Vec<AccountId>, price_data: PriceData);
+}
+
+#[near_bindgen]
+impl StablePricesHandler for Contract {
+    /// Assets without the oracle price keep the previous one.
+    #[private]
+    fn handle_stable_prices(
+        &mut self,
+        asset_ids: Vec<AccountId>,
+        #[callback] price_data: PriceData,
+    ) {
+        if env::block_timestamp() >= price_data.timestamp() + price_data.recency_duration() {
+            env::panic_str("Oracle provided an outdated price data");
+        }
+
+        for asset_id in asset_ids {
+            if let Some(price) = price_data.find_price(&asset_id.to_string()) {
+                let peg = self.stable_treasury.set_peg_price(&asset_id, &price);
+                if peg.is_depegged() {
+                    event::emit::stable_depeg(&asset_id, &peg);
+                }
+            }
+        }
+    }
+}
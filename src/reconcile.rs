@@ -0,0 +1,105 @@
+//! Burning of USN stranded on the contract account.
+//!
+//! The contract account escrows USN of airdrops, vesting schedules and wUSN.
+//! Anything above that is untracked, e.g. USN minted for a liquidity transfer
+//! which failed after the minting, and inflates the supply without backing.
+
+use crate::*;
+
+use near_sdk::require;
+
+impl Contract {
+    /// USN held by the contract account on behalf of users.
+    pub(crate) fn escrowed_contract_usn(&self) -> Balance {
+        self.airdrop_escrow + self.vesting_escrow + self.wusn_total_supply
+    }
+
+    /// Burns up to `limit` of untracked USN of the contract account.
+    /// Returns the burned amount, nothing while the vesting escrow is unsynced.
+    pub(crate) fn burn_untracked_usn(&mut self, limit: Balance) -> Balance {
+        if !self.vesting_escrow_synced {
+            return 0;
+        }
+        let amount = self.untracked_contract_usn().0.min(limit);
+        if amount > 0 {
+            self.burn_contract_usn(amount);
+        }
+        amount
+    }
+
+    /// Burns `amount` of USN of the contract account known to be untracked.
+    pub(crate) fn burn_contract_usn(&mut self, amount: Balance) {
+        let usn_id = env::current_account_id();
+        self.token.internal_withdraw(&usn_id, amount);
+        event::emit::ft_burn(&usn_id, amount, None);
+        self.treasury_journal.record(
+            TreasuryOperation::BurnUntracked {
+                amount: amount.into(),
+            },
+            true,
+        );
+    }
+}
+
+#[near_bindgen]
+impl Contract {
+    /// Returns USN of the contract account which isn't escrowed for anyone.
+    pub fn untracked_contract_usn(&self) -> U128 {
+        self.token
+            .internal_unwrap_balance_of(&env::current_account_id())
+            .saturating_sub(self.escrowed_contract_usn())
+            .into()
+    }
+
+    /// Burns all untracked USN of the contract account. Only can be called by owner.
+    pub fn reconcile_contract_usn(&mut self) -> U128 {
+        self.assert_owner("reconcile_contract_usn");
+        require!(
+            self.vesting_escrow_synced,
+            "The vesting escrow isn't synced, call set_vesting_escrow"
+        );
+        let amount = self.burn_untracked_usn(Balance::MAX);
+        require!(amount > 0, "Nothing to reconcile");
+        event::emit::usn_reconcile(None, amount);
+        amount.into()
+    }
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use super::*;
+    use crate::testing::setup_contract;
+    use near_sdk::test_utils::accounts;
+    use near_sdk::{testing_env, ONE_YOCTO};
+
+    #[test]
+    fn test_reconcile_contract_usn() {
+        let (mut context, mut contract) = setup_contract(accounts(1));
+        testing_env!(context.attached_deposit(ONE_YOCTO).build());
+        contract.token.internal_deposit(&accounts(1), 1000);
+        contract.create_vesting(accounts(2), U128(300), U64(0), U64(10), U64(20));
+        contract.token.internal_deposit(&accounts(0), 50);
+        assert_eq!(contract.untracked_contract_usn(), U128(50));
+
+        assert_eq!(contract.reconcile_contract_usn(), U128(50));
+        assert_eq!(contract.ft_balance_of(accounts(0)), U128(300));
+        assert_eq!(contract.ft_total_supply(), U128(1000));
+        assert_eq!(contract.untracked_contract_usn(), U128(0));
+    }
+
+    #[test]
+    #[should_panic(expected = "Nothing to reconcile")]
+    fn test_reconcile_nothing() {
+        let (_, mut contract) = setup_contract(accounts(1));
+        contract.reconcile_contract_usn();
+    }
+
+    #[test]
+    #[should_panic(expected = "The vesting escrow isn't synced, call set_vesting_escrow")]
+    fn test_reconcile_unsynced_vesting_escrow() {
+        let (_, mut contract) = setup_contract(accounts(1));
+        contract.token.internal_deposit(&accounts(0), 50);
+        contract.vesting_escrow_synced = false;
+        contract.reconcile_contract_usn();
+    }
+}
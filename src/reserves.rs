@@ -0,0 +1,248 @@
+//! Proof-of-reserve snapshots of the treasury balances reported by external contracts.
+//!
+//! Unlike `backing_ratio`, which uses the internal bookkeeping, snapshots query
+//! the FT contracts, ref.finance and the staking pools, so auditors can compare
+//! both. The contracts which didn't respond are listed in the snapshot.
+
+use std::collections::HashMap;
+
+use crate::*;
+
+use near_sdk::collections::LookupMap;
+use near_sdk::{require, IntoStorageKey};
+
+use crate::staking::{ext_pool, HumanReadableAccount};
+use crate::treasury::{ext_ref_finance, ref_address};
+
+/// Number of the latest snapshots kept.
+const SNAPSHOT_CAPACITY: u64 = 100;
+const DEFAULT_SNAPSHOT_LIMIT: u64 = 10;
+/// Each query takes `GAS_FOR_BALANCE_VIEW`, so the number of queried contracts is limited.
+const MAX_RESERVE_QUERIES: usize = 10;
+const GAS_FOR_BALANCE_VIEW: Gas = Gas(7_000_000_000_000);
+const GAS_FOR_SNAPSHOT_CALLBACK: Gas = Gas(15_000_000_000_000);
+
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(crate = "near_sdk::serde")]
+pub struct ReserveSnapshot {
+    pub index: U64,
+    pub timestamp: U64,
+    /// NEAR balance of the contract including the storage stake.
+    pub near: U128,
+    /// FT balances of the contract: stable assets and wNEAR.
+    pub tokens: Vec<(AccountId, U128)>,
+    pub ref_deposits: Vec<(AccountId, U128)>,
+    /// Staked and unstaked NEAR in each staking pool.
+    pub staked: Vec<(AccountId, U128)>,
+    /// Contracts which failed to report the balance.
+    pub failed: Vec<AccountId>,
+}
+
+/// Ring buffer of the latest reserve snapshots.
+#[derive(BorshDeserialize, BorshSerialize)]
+pub struct ReserveSnapshots {
+    snapshots: LookupMap<u64, ReserveSnapshot>,
+    /// Number of snapshots ever taken, i.e. the index of the next one.
+    len: u64,
+}
+
+impl ReserveSnapshots {
+    pub fn new<S>(prefix: S) -> Self
+    where
+        S: IntoStorageKey,
+    {
+        Self {
+            snapshots: LookupMap::new(prefix),
+            len: 0,
+        }
+    }
+
+    /// Stores the snapshot under the next index and returns it.
+    pub fn push(&mut self, mut snapshot: ReserveSnapshot) -> ReserveSnapshot {
+        snapshot.index = self.len.into();
+        self.snapshots
+            .insert(&(self.len % SNAPSHOT_CAPACITY), &snapshot);
+        self.len += 1;
+        snapshot
+    }
+
+    /// Returns up to `limit` snapshots, the latest first.
+    pub fn latest(&self, limit: u64) -> Vec<ReserveSnapshot> {
+        let from = self.len.saturating_sub(limit.min(SNAPSHOT_CAPACITY));
+        (from..self.len)
+            .rev()
+            .map(|index| self.snapshots.get(&(index % SNAPSHOT_CAPACITY)).unwrap())
+            .collect()
+    }
+}
+
+#[near_bindgen]
+impl Contract {
+    /// Queries balances of all treasury assets and stores the snapshot.
+    /// Only can be called by owner or guardians.
+    pub fn snapshot_reserves(&mut self) -> Promise {
+        self.assert_owner_or_guardian("snapshot_reserves");
+
+        let mut token_ids: Vec<AccountId> = self
+            .stable_treasury
+            .supported_assets()
+            .into_iter()
+            .map(|(asset_id, _)| asset_id)
+            .collect();
+        token_ids.push(self.oracle_config.asset_id.clone());
+        let pool_ids = self.staking_pools.keys_as_vector().to_vec();
+        let queries = token_ids.len() + 1 + pool_ids.len();
+        require!(
+            queries <= MAX_RESERVE_QUERIES,
+            &format!("Too many reserves to query: {}", queries)
+        );
+
+        let usn_id = env::current_account_id();
+        let mut promise = ext_ref_finance::get_deposits(
+            usn_id.clone(),
+            ref_address().parse().unwrap(),
+            NO_DEPOSIT,
+            GAS_FOR_BALANCE_VIEW,
+        );
+        for token_id in &token_ids {
+            promise = promise.and(ext_ft_api::ft_balance_of(
+                usn_id.clone(),
+                token_id.clone(),
+                NO_DEPOSIT,
+                GAS_FOR_BALANCE_VIEW,
+            ));
+        }
+        for pool_id in &pool_ids {
+            promise = promise.and(ext_pool::get_account(
+                usn_id.clone(),
+                pool_id.clone(),
+                NO_DEPOSIT,
+                GAS_FOR_BALANCE_VIEW,
+            ));
+        }
+
+        promise.then(ext_self::handle_reserve_snapshot(
+            token_ids,
+            pool_ids,
+            usn_id,
+            NO_DEPOSIT,
+            GAS_FOR_SNAPSHOT_CALLBACK,
+        ))
+    }
+
+    /// Returns up to `limit` reserve snapshots, the latest first.
+    pub fn get_reserve_snapshots(&self, limit: Option<u64>) -> Vec<ReserveSnapshot> {
+        self.reserve_snapshots
+            .latest(limit.unwrap_or(DEFAULT_SNAPSHOT_LIMIT))
+    }
+}
+
+#[ext_contract(ext_self)]
+trait ReserveSnapshotHandler {
+    #[private]
+    fn handle_reserve_snapshot(&mut self, token_ids: Vec<AccountId>, pool_ids: Vec<AccountId>);
+}
+
+trait ReserveSnapshotHandler {
+    fn handle_reserve_snapshot(&mut self, token_ids: Vec<AccountId>, pool_ids: Vec<AccountId>);
+}
+
+#[near_bindgen]
+impl ReserveSnapshotHandler for Contract {
+    /// Results come in the order of `snapshot_reserves` queries:
+    /// ref.finance deposits, token balances, staking pools.
+    #[private]
+    fn handle_reserve_snapshot(&mut self, token_ids: Vec<AccountId>, pool_ids: Vec<AccountId>) {
+        let mut failed = Vec::new();
+
+        let ref_deposits = match promise_result_json::<HashMap<AccountId, U128>>(0) {
+            Some(deposits) => {
+                let mut deposits: Vec<_> = deposits.into_iter().collect();
+                deposits.sort();
+                deposits
+            }
+            None => {
+                failed.push(ref_address().parse().unwrap());
+                vec![]
+            }
+        };
+
+        let mut index = 1;
+        let mut tokens = Vec::new();
+        for token_id in token_ids {
+            match promise_result_json::<U128>(index) {
+                Some(balance) => tokens.push((token_id, balance)),
+                None => failed.push(token_id),
+            }
+            index += 1;
+        }
+
+        let mut staked = Vec::new();
+        for pool_id in pool_ids {
+            match promise_result_json::<HumanReadableAccount>(index) {
+                Some(account) => staked.push((
+                    pool_id,
+                    (account.staked_balance.0 + account.unstaked_balance.0).into(),
+                )),
+                None => failed.push(pool_id),
+            }
+            index += 1;
+        }
+
+        let snapshot = ReserveSnapshot {
+            index: U64(0),
+            timestamp: env::block_timestamp().into(),
+            near: env::account_balance().into(),
+            tokens,
+            ref_deposits,
+            staked,
+            failed,
+        };
+        let snapshot = self.reserve_snapshots.push(snapshot);
+        event::emit::reserve_snapshot(&snapshot);
+    }
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use super::*;
+
+    fn snapshot(near: u128) -> ReserveSnapshot {
+        ReserveSnapshot {
+            index: U64(0),
+            timestamp: U64(0),
+            near: U128(near),
+            tokens: vec![],
+            ref_deposits: vec![],
+            staked: vec![],
+            failed: vec![],
+        }
+    }
+
+    #[test]
+    fn test_latest_snapshots() {
+        let mut snapshots = ReserveSnapshots::new(b"r".to_vec());
+        assert!(snapshots.latest(10).is_empty());
+        for i in 0..3 {
+            snapshots.push(snapshot(i));
+        }
+        let latest = snapshots.latest(2);
+        assert_eq!(latest.len(), 2);
+        assert_eq!(latest[0].index, U64(2));
+        assert_eq!(latest[0].near, U128(2));
+        assert_eq!(latest[1].index, U64(1));
+        assert_eq!(snapshots.latest(10).len(), 3);
+    }
+
+    #[test]
+    fn test_snapshot_ring_buffer() {
+        let mut snapshots = ReserveSnapshots::new(b"r".to_vec());
+        for i in 0..(SNAPSHOT_CAPACITY + 5) as u128 {
+            snapshots.push(snapshot(i));
+        }
+        let latest = snapshots.latest(SNAPSHOT_CAPACITY * 2);
+        assert_eq!(latest.len() as u64, SNAPSHOT_CAPACITY);
+        assert_eq!(latest[0].index, U64(SNAPSHOT_CAPACITY + 4));
+        assert_eq!(latest.last().unwrap().index, U64(5));
+    }
+}
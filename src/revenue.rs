@@ -0,0 +1,104 @@
+//! Split of the commission revenue between the DAO, an insurance fund and burning.
+//!
+//! The split is applied when the commission v2 is transferred: the receiver gets
+//! the DAO share, the insurance fund gets its share, and the burned share is
+//! never minted, so it stays in the treasury as extra backing of USN.
+//! The commission v1 goes to the receiver whole.
+
+use crate::*;
+
+const MAX_PERCENT: u32 = 100;
+
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(crate = "near_sdk::serde")]
+pub struct RevenueSplit {
+    /// Percent of the commission left for the insurance fund.
+    pub insurance: u32,
+    /// Percent of the commission burned.
+    pub burn: u32,
+    pub insurance_id: Option<AccountId>,
+}
+
+impl Default for RevenueSplit {
+    /// Everything goes to the receiver of the commission, i.e. the DAO.
+    fn default() -> Self {
+        Self {
+            insurance: 0,
+            burn: 0,
+            insurance_id: None,
+        }
+    }
+}
+
+/// Accumulated amounts of the commission per bucket.
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug, Default)]
+#[serde(crate = "near_sdk::serde")]
+pub struct RevenueAccrual {
+    pub dao: U128,
+    pub insurance: U128,
+    pub burned: U128,
+}
+
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug, Default)]
+#[serde(crate = "near_sdk::serde")]
+pub struct Revenue {
+    pub split: RevenueSplit,
+    pub accrued: RevenueAccrual,
+}
+
+pub struct RevenueShares {
+    pub dao: Balance,
+    pub insurance: Balance,
+    pub burn: Balance,
+}
+
+impl Revenue {
+    /// Splits the transferred commission. The rounding remainder goes to the DAO.
+    pub fn apply(&mut self, amount: Balance) -> RevenueShares {
+        let insurance = rounding::mul_div(
+            amount,
+            self.split.insurance as u128,
+            MAX_PERCENT as u128,
+            rounding::REVENUE_SPLIT,
+        );
+        let burn = rounding::mul_div(
+            amount,
+            self.split.burn as u128,
+            MAX_PERCENT as u128,
+            rounding::REVENUE_SPLIT,
+        );
+        let dao = amount - insurance - burn;
+
+        self.accrued.dao = (self.accrued.dao.0 + dao).into();
+        self.accrued.insurance = (self.accrued.insurance.0 + insurance).into();
+        self.accrued.burned = (self.accrued.burned.0 + burn).into();
+
+        RevenueShares {
+            dao,
+            insurance,
+            burn,
+        }
+    }
+}
+
+#[near_bindgen]
+impl Contract {
+    /// Sets percents of the transferred commission v2 going to the insurance fund and burned.
+    /// The rest goes to the receiver of `transfer_commission`. Only can be called by owner.
+    pub fn set_revenue_split(&mut self, split: RevenueSplit) {
+        self.assert_owner("set_revenue_split");
+        assert!(
+            split.insurance + split.burn <= MAX_PERCENT,
+            "The revenue split exceeds 100%"
+        );
+        assert!(
+            split.insurance == 0 || split.insurance_id.is_some(),
+            "The insurance fund account is not specified"
+        );
+        self.revenue.split = split;
+    }
+
+    pub fn revenue(&self) -> Revenue {
+        self.revenue.clone()
+    }
+}
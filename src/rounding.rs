@@ -0,0 +1,294 @@
+//! Rounding policy of the token math.
+//!
+//! Every division which may lose precision goes through this module and takes
+//! its rounding direction from one of the constants below. The rule of thumb is
+//! that rounding never leaves USN unbacked: withdrawn assets, minted USN and
+//! removed pool shares are rounded down. The commission is a part of already
+//! backed USN, so it's rounded down too, and its refund repeats the same math.
+//!
+//! Arithmetic failures of the hot paths go through [`overflow`] with a call-site
+//! identifier. With the `paranoid` feature it logs a `math_overflow` event before panicking.
+
+use crate::*;
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub enum Rounding {
+    Down,
+    Up,
+}
+
+/// USN <-> stable asset decimal conversion, i.e. the withdrawn asset amount.
+pub const STABLE_DECIMALS: Rounding = Rounding::Down;
+
+/// Commission charged on deposit and withdrawal of stable assets.
+/// The refund of the commission MUST use the same direction.
+pub const COMMISSION: Rounding = Rounding::Down;
+
+/// NEAR part of v1 commission transferred along with the USN part.
+pub const NEAR_COMMISSION: Rounding = Rounding::Down;
+
+/// USN minted by the owner for NEAR.
+pub const MINT_BY_NEAR: Rounding = Rounding::Down;
+
+/// Percent of ref.finance pool shares removed from the liquidity.
+pub const POOL_SHARES: Rounding = Rounding::Down;
+
+/// Expected output of a swap in the ref.finance pool.
+pub const POOL_QUOTE: Rounding = Rounding::Down;
+
+/// Insurance and burned shares of the transferred commission, the remainder goes to the DAO.
+pub const REVENUE_SPLIT: Rounding = Rounding::Down;
+
+/// wNEAR added along with USN to a USN/wNEAR pool and its USN value.
+pub const SIMPLE_LIQUIDITY: Rounding = Rounding::Down;
+
+/// USN unlocked by a vesting schedule.
+pub const VESTING: Rounding = Rounding::Down;
+
+/// USN value of the reserves and the backing ratio.
+pub const BACKING: Rounding = Rounding::Down;
+
+/// USN burned by a withdrawal of the exact asset amount, at least as much as `withdraw` takes.
+pub const WITHDRAW_EXACT: Rounding = Rounding::Up;
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+#[serde(crate = "near_sdk::serde")]
+pub struct RoundingPolicy {
+    pub stable_decimals: Rounding,
+    pub commission: Rounding,
+    pub near_commission: Rounding,
+    pub mint_by_near: Rounding,
+    pub pool_shares: Rounding,
+    pub pool_quote: Rounding,
+    pub revenue_split: Rounding,
+    pub simple_liquidity: Rounding,
+    pub vesting: Rounding,
+    pub withdraw_exact: Rounding,
+    pub backing: Rounding,
+}
+
+impl RoundingPolicy {
+    pub fn current() -> Self {
+        Self {
+            stable_decimals: STABLE_DECIMALS,
+            commission: COMMISSION,
+            near_commission: NEAR_COMMISSION,
+            mint_by_near: MINT_BY_NEAR,
+            pool_shares: POOL_SHARES,
+            pool_quote: POOL_QUOTE,
+            revenue_split: REVENUE_SPLIT,
+            simple_liquidity: SIMPLE_LIQUIDITY,
+            vesting: VESTING,
+            withdraw_exact: WITHDRAW_EXACT,
+            backing: BACKING,
+        }
+    }
+}
+
+/// Aborts the execution on an arithmetic failure at `site`.
+pub fn overflow(site: &str, message: &str) -> ! {
+    #[cfg(feature = "paranoid")]
+    event::emit::math_overflow(site, message);
+    #[cfg(not(feature = "paranoid"))]
+    let _ = site;
+    env::panic_str(message)
+}
+
+pub fn checked_add(a: u128, b: u128, site: &str) -> u128 {
+    a.checked_add(b)
+        .unwrap_or_else(|| overflow(site, "Addition overflow"))
+}
+
+pub fn checked_sub(a: u128, b: u128, site: &str) -> u128 {
+    a.checked_sub(b)
+        .unwrap_or_else(|| overflow(site, "Subtraction overflow"))
+}
+
+/// Converts U256 to u128 panicking if the value doesn't fit into 128 bits.
+pub fn to_u128(value: U256, site: &str) -> u128 {
+    if value > U256::from(u128::MAX) {
+        overflow(site, "Multiplication overflow");
+    }
+    value.as_u128()
+}
+
+pub fn div(numerator: U256, denominator: U256, rounding: Rounding) -> U256 {
+    if denominator.is_zero() {
+        overflow("rounding::div", "Division by zero");
+    }
+    let (quotient, remainder) = numerator.div_mod(denominator);
+    if rounding == Rounding::Up && !remainder.is_zero() {
+        quotient + 1
+    } else {
+        quotient
+    }
+}
+
+/// Calculates `a * b / c` without intermediate overflow.
+/// It panics if the result doesn't fit into 128 bits.
+pub fn mul_div(a: u128, b: u128, c: u128, rounding: Rounding) -> u128 {
+    let result = div(U256::from(a) * U256::from(b), U256::from(c), rounding);
+    to_u128(result, "rounding::mul_div")
+}
+
+pub fn convert_decimals(
+    amount: u128,
+    decimals_from: u8,
+    decimals_to: u8,
+    rounding: Rounding,
+) -> u128 {
+    if decimals_from < decimals_to {
+        amount
+            .checked_mul(10u128.pow(u32::from(decimals_to - decimals_from)))
+            .unwrap_or_else(|| overflow("rounding::convert_decimals", "Multiplication overflow"))
+    } else if decimals_from > decimals_to {
+        mul_div(
+            amount,
+            1,
+            10u128.pow(u32::from(decimals_from - decimals_to)),
+            rounding,
+        )
+    } else {
+        amount
+    }
+}
+
+#[near_bindgen]
+impl Contract {
+    pub fn rounding_policy(&self) -> RoundingPolicy {
+        RoundingPolicy::current()
+    }
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_div_exact() {
+        for rounding in [Rounding::Down, Rounding::Up] {
+            assert_eq!(
+                div(U256::from(100), U256::from(10), rounding),
+                U256::from(10)
+            );
+            assert_eq!(div(U256::zero(), U256::from(7), rounding), U256::zero());
+        }
+    }
+
+    #[test]
+    fn test_div_inexact() {
+        assert_eq!(
+            div(U256::from(101), U256::from(10), Rounding::Down),
+            U256::from(10)
+        );
+        assert_eq!(
+            div(U256::from(101), U256::from(10), Rounding::Up),
+            U256::from(11)
+        );
+        assert_eq!(
+            div(U256::from(109), U256::from(10), Rounding::Down),
+            U256::from(10)
+        );
+        assert_eq!(
+            div(U256::from(109), U256::from(10), Rounding::Up),
+            U256::from(11)
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Division by zero")]
+    fn test_div_by_zero() {
+        div(U256::from(1), U256::zero(), Rounding::Down);
+    }
+
+    #[test]
+    fn test_mul_div_properties() {
+        for a in 0..50u128 {
+            for b in 0..50u128 {
+                for c in 1..50u128 {
+                    let down = mul_div(a, b, c, Rounding::Down);
+                    let up = mul_div(a, b, c, Rounding::Up);
+                    assert!(down * c <= a * b);
+                    assert!(up * c >= a * b);
+                    if (a * b) % c == 0 {
+                        assert_eq!(down, up);
+                    } else {
+                        assert_eq!(down + 1, up);
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_mul_div_no_intermediate_overflow() {
+        assert_eq!(
+            mul_div(u128::MAX, u128::MAX, u128::MAX, Rounding::Down),
+            u128::MAX
+        );
+        assert_eq!(
+            mul_div(u128::MAX, 3, 4, Rounding::Up),
+            (U256::from(u128::MAX) * U256::from(3) / U256::from(4)).as_u128() + 1
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Multiplication overflow")]
+    fn test_mul_div_overflow() {
+        mul_div(u128::MAX, 2, 1, Rounding::Down);
+    }
+
+    #[test]
+    fn test_convert_decimals_round_trip() {
+        for amount in [0u128, 1, 9, 10, 999_999, 1_000_000, 123_456_789] {
+            for from in 1..=24u8 {
+                for to in 1..=24u8 {
+                    let converted = convert_decimals(amount, from, to, Rounding::Down);
+                    let back = convert_decimals(converted, to, from, Rounding::Down);
+                    assert!(back <= amount);
+                    if from <= to {
+                        assert_eq!(back, amount);
+                    }
+                    let converted_up = convert_decimals(amount, from, to, Rounding::Up);
+                    assert!(converted_up >= converted && converted_up - converted <= 1);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_convert_decimals_down_up() {
+        assert_eq!(convert_decimals(1_999_999, 18, 6, Rounding::Down), 0);
+        assert_eq!(convert_decimals(1_999_999, 18, 6, Rounding::Up), 1);
+        assert_eq!(convert_decimals(1_000_000_000_000, 18, 6, Rounding::Up), 1);
+        assert_eq!(
+            convert_decimals(1, 6, 18, Rounding::Down),
+            1_000_000_000_000
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Addition overflow")]
+    fn test_checked_add_overflow() {
+        checked_add(u128::MAX, 1, "test");
+    }
+
+    #[test]
+    #[should_panic(expected = "Subtraction overflow")]
+    fn test_checked_sub_overflow() {
+        checked_sub(0, 1, "test");
+    }
+
+    #[test]
+    fn test_policy_keeps_usn_backed() {
+        let policy = RoundingPolicy::current();
+        assert_eq!(policy.stable_decimals, Rounding::Down);
+        assert_eq!(policy.near_commission, Rounding::Down);
+        assert_eq!(policy.mint_by_near, Rounding::Down);
+        assert_eq!(policy.pool_shares, Rounding::Down);
+        assert_eq!(policy.vesting, Rounding::Down);
+        assert_eq!(policy.withdraw_exact, Rounding::Up);
+        assert_eq!(policy.backing, Rounding::Down);
+    }
+}
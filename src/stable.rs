@@ -1,6 +1,11 @@
+use crate::oracle::Price;
 use crate::*;
 
-use near_sdk::{collections::UnorderedMap, IntoStorageKey};
+use near_sdk::{
+    collections::{LookupMap, UnorderedMap},
+    IntoStorageKey,
+};
+use std::collections::HashMap;
 
 const PERCENT_MULTIPLICATOR: u128 = 100;
 const USDT_DECIMALS: u8 = 6;
@@ -8,6 +13,12 @@ const MAX_VALID_DECIMALS: u8 = 37;
 const MAX_COMMISSION_RATE: u32 = 50000; // 0.05 = 5%
 const SPREAD_DECIMAL: u8 = 6;
 const INITIAL_COMMISSION_RATE: u32 = 100; // 0.0001 = 0.01%
+/// $1 in basis points.
+const PEG_PRICE: u32 = 10_000;
+/// Commission is reported per 30-day epochs counted from the Unix epoch.
+const COMMISSION_EPOCH_NS: u64 = 30 * 24 * 60 * 60 * 1_000_000_000;
+/// A checked peg needs a price synced within the last hour.
+const MAX_PEG_PRICE_AGE_NS: u64 = 60 * 60 * 1_000_000_000;
 
 pub fn usdt_id() -> AccountId {
     if cfg!(feature = "mainnet") {
@@ -26,6 +37,10 @@ pub fn usdt_id() -> AccountId {
 pub enum AssetStatus {
     Enabled,
     Disabled,
+    /// Deposits are disabled, withdrawals are open until the deadline.
+    Delisting {
+        deadline: U64,
+    },
 }
 
 #[derive(Debug)]
@@ -50,6 +65,59 @@ impl Default for CommissionRate {
     }
 }
 
+/// Minimal amounts protecting from dust deposits and withdrawals.
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug, Default)]
+#[serde(crate = "near_sdk::serde")]
+pub struct AssetLimits {
+    /// In the asset.
+    pub min_deposit: U128,
+    /// In USN.
+    pub min_withdraw: U128,
+}
+
+/// The oracle price of the asset and the allowed deviation from $1.
+/// With the tolerance set, a missing or stale price rejects both directions.
+#[derive(
+    BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug, Default, PartialEq,
+)]
+#[serde(crate = "near_sdk::serde")]
+pub struct AssetPeg {
+    /// In basis points, `None` disables the check.
+    pub tolerance: Option<u32>,
+    /// The last synced price in basis points of $1.
+    pub price: Option<u32>,
+    pub updated_at: U64,
+}
+
+impl AssetPeg {
+    /// Deposits are rejected below the band: USN would be minted for less than $1.
+    fn is_below(&self) -> bool {
+        match (self.price, self.tolerance) {
+            (Some(price), Some(tolerance)) => price < PEG_PRICE.saturating_sub(tolerance),
+            _ => false,
+        }
+    }
+
+    /// Withdrawals are rejected above the band: USN would be redeemed for more than $1.
+    fn is_above(&self) -> bool {
+        match (self.price, self.tolerance) {
+            (Some(price), Some(tolerance)) => price > PEG_PRICE.saturating_add(tolerance),
+            _ => false,
+        }
+    }
+
+    pub fn is_depegged(&self) -> bool {
+        self.is_below() || self.is_above()
+    }
+
+    /// The price is unknown or older than `MAX_PEG_PRICE_AGE_NS`, while the check is on.
+    pub fn is_stale(&self) -> bool {
+        self.tolerance.is_some()
+            && (self.price.is_none()
+                || env::block_timestamp().saturating_sub(self.updated_at.0) > MAX_PEG_PRICE_AGE_NS)
+    }
+}
+
 #[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Debug)]
 #[serde(crate = "near_sdk::serde")]
 pub struct AssetInfo {
@@ -58,6 +126,13 @@ pub struct AssetInfo {
     // Stored in USN due to more precise value
     commission: U128,
     commission_rate: CommissionRate,
+    // Stored separately to keep the layout of assets, filled for views.
+    #[borsh_skip]
+    #[serde(default)]
+    limits: AssetLimits,
+    #[borsh_skip]
+    #[serde(default)]
+    peg: AssetPeg,
 }
 
 impl AssetInfo {
@@ -72,17 +147,83 @@ impl AssetInfo {
             status: AssetStatus::Enabled,
             commission: U128(0),
             commission_rate: CommissionRate::default(),
+            limits: AssetLimits::default(),
+            peg: AssetPeg::default(),
         }
     }
 
     pub fn commission(&self) -> U128 {
         self.commission
     }
+
+    pub fn is_enabled(&self) -> bool {
+        self.status == AssetStatus::Enabled
+    }
+}
+
+/// Commission v2 flows in USN within an epoch or the lifetime.
+#[derive(
+    BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug, Default, PartialEq,
+)]
+#[serde(crate = "near_sdk::serde")]
+pub struct CommissionEpoch {
+    pub accrued: U128,
+    /// Returned with refunds of failed withdrawals.
+    pub refunded: U128,
+    /// Paid out by `transfer_commission` or `withdraw_commission_as_asset`.
+    pub transferred: U128,
+}
+
+pub fn commission_epoch() -> u64 {
+    env::block_timestamp() / COMMISSION_EPOCH_NS
+}
+
+/// USN and asset amounts of a deposit or withdrawal, the commission is in USN.
+/// `amount` is minted USN for a deposit and burned USN for a withdrawal.
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+#[serde(crate = "near_sdk::serde")]
+pub struct StableQuote {
+    pub amount: U128,
+    pub asset_amount: U128,
+    pub commission: U128,
+}
+
+/// What happens to a withdrawal when the asset transfer fails.
+#[derive(
+    BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Copy, Debug, PartialEq,
+)]
+#[serde(crate = "near_sdk::serde")]
+pub enum WithdrawRefundMode {
+    /// Burned USN is minted back.
+    Remint,
+    /// The asset amount is kept for the user to claim it later.
+    Claim,
+}
+
+impl Default for WithdrawRefundMode {
+    fn default() -> Self {
+        WithdrawRefundMode::Remint
+    }
 }
 
 #[derive(BorshDeserialize, BorshSerialize)]
 pub struct StableTreasury {
     assets: UnorderedMap<AccountId, AssetInfo>,
+    /// Asset amounts backing USN: deposited minus withdrawn.
+    reserves: LookupMap<AccountId, Balance>,
+    /// Asset amounts of failed withdrawals per account and asset.
+    failed_withdrawals: LookupMap<AccountId, HashMap<AccountId, Balance>>,
+    limits: LookupMap<AccountId, AssetLimits>,
+    pegs: LookupMap<AccountId, AssetPeg>,
+    commission_epochs: LookupMap<u64, CommissionEpoch>,
+    /// Since the commission is tracked per epoch, not since the first deposit.
+    lifetime_commission: CommissionEpoch,
+}
+
+/// The layout of v2.3.4 without reserves.
+#[derive(BorshDeserialize)]
+pub struct StableTreasuryV1 {
+    assets: UnorderedMap<AccountId, AssetInfo>,
 }
 
 impl StableTreasury {
@@ -90,8 +231,15 @@ impl StableTreasury {
     where
         S: IntoStorageKey,
     {
+        let prefix = prefix.into_storage_key();
         let mut this = Self {
-            assets: UnorderedMap::new(prefix),
+            assets: UnorderedMap::new(prefix.clone()),
+            reserves: LookupMap::new(reserves_prefix(prefix.clone())),
+            failed_withdrawals: LookupMap::new(failed_withdrawals_prefix(prefix.clone())),
+            limits: LookupMap::new(limits_prefix(prefix.clone())),
+            pegs: LookupMap::new(pegs_prefix(prefix.clone())),
+            commission_epochs: LookupMap::new(commission_epochs_prefix(prefix)),
+            lifetime_commission: CommissionEpoch::default(),
         };
 
         // USDT is supported by default.
@@ -99,6 +247,23 @@ impl StableTreasury {
         this
     }
 
+    /// Reserves of migrated assets are unknown until the owner sets them.
+    pub fn migrate<S>(prev: StableTreasuryV1, prefix: S) -> Self
+    where
+        S: IntoStorageKey,
+    {
+        let prefix = prefix.into_storage_key();
+        Self {
+            assets: prev.assets,
+            reserves: LookupMap::new(reserves_prefix(prefix.clone())),
+            failed_withdrawals: LookupMap::new(failed_withdrawals_prefix(prefix.clone())),
+            limits: LookupMap::new(limits_prefix(prefix.clone())),
+            pegs: LookupMap::new(pegs_prefix(prefix.clone())),
+            commission_epochs: LookupMap::new(commission_epochs_prefix(prefix)),
+            lifetime_commission: CommissionEpoch::default(),
+        }
+    }
+
     pub fn add_asset(&mut self, asset_id: &AccountId, decimals: u8) {
         assert!(
             self.assets.get(asset_id).is_none(),
@@ -121,14 +286,184 @@ impl StableTreasury {
         self.switch_status(asset_id, AssetStatus::Disabled);
     }
 
+    /// Starts delisting of the asset: deposits are disabled immediately,
+    /// but users can withdraw the asset until the `deadline`.
+    pub fn begin_delisting(&mut self, asset_id: &AccountId, deadline: Timestamp) {
+        self.assert_asset(asset_id);
+        if let AssetStatus::Delisting { .. } = self.assets.get(asset_id).unwrap().status {
+            env::panic_str(&format!("Asset {} is already delisting", asset_id));
+        }
+        assert!(
+            deadline > env::block_timestamp(),
+            "The deadline should be in the future"
+        );
+        self.switch_status(
+            asset_id,
+            AssetStatus::Delisting {
+                deadline: deadline.into(),
+            },
+        );
+        event::emit::asset_delisting(asset_id, deadline);
+    }
+
+    /// Removes the delisted asset after the deadline.
+    /// Its accumulated commission is moved to the `successor_id` asset, so it isn't trapped.
+    pub fn finish_delisting(&mut self, asset_id: &AccountId, successor_id: &AccountId) {
+        self.assert_asset(asset_id);
+        self.assert_asset(successor_id);
+        assert_ne!(
+            asset_id, successor_id,
+            "The successor should be another asset"
+        );
+
+        let asset_info = self.assets.get(asset_id).unwrap();
+        match asset_info.status {
+            AssetStatus::Delisting { deadline } if env::block_timestamp() >= deadline.0 => {}
+            AssetStatus::Delisting { .. } => {
+                env::panic_str(&format!("Asset {} is still open for withdrawals", asset_id))
+            }
+            _ => env::panic_str(&format!("Asset {} is currently not delisting", asset_id)),
+        }
+
+        let mut successor_info = self.assets.get(successor_id).unwrap();
+        successor_info.commission = rounding::checked_add(
+            successor_info.commission.0,
+            asset_info.commission.0,
+            "stable::finish_delisting",
+        )
+        .into();
+        self.assets.insert(successor_id, &successor_info);
+        self.assets.remove(asset_id);
+        self.reserves.remove(asset_id);
+        self.limits.remove(asset_id);
+        self.pegs.remove(asset_id);
+
+        event::emit::asset_delisted(asset_id, successor_id, asset_info.commission.0);
+    }
+
     fn switch_status(&mut self, asset_id: &AccountId, status: AssetStatus) {
         let mut asset_info = self.assets.get(asset_id).unwrap();
         asset_info.status = status;
         self.assets.insert(asset_id, &asset_info);
     }
 
+    pub fn is_supported(&self, asset_id: &AccountId) -> bool {
+        self.assets.get(asset_id).is_some()
+    }
+
     pub fn supported_assets(&self) -> Vec<(AccountId, AssetInfo)> {
-        self.assets.to_vec()
+        self.assets
+            .iter()
+            .map(|(asset_id, mut asset_info)| {
+                asset_info.limits = self.limits(&asset_id);
+                asset_info.peg = self.peg(&asset_id);
+                (asset_id, asset_info)
+            })
+            .collect()
+    }
+
+    pub fn limits(&self, asset_id: &AccountId) -> AssetLimits {
+        self.limits.get(asset_id).unwrap_or_default()
+    }
+
+    pub fn set_limits(&mut self, asset_id: &AccountId, limits: AssetLimits) {
+        self.assert_asset(asset_id);
+        self.limits.insert(asset_id, &limits);
+    }
+
+    pub fn peg(&self, asset_id: &AccountId) -> AssetPeg {
+        self.pegs.get(asset_id).unwrap_or_default()
+    }
+
+    pub fn set_peg_tolerance(&mut self, asset_id: &AccountId, tolerance: Option<u32>) {
+        self.assert_asset(asset_id);
+        let mut peg = self.peg(asset_id);
+        peg.tolerance = tolerance;
+        self.pegs.insert(asset_id, &peg);
+    }
+
+    /// Records the oracle price of one token of the asset.
+    pub fn set_peg_price(&mut self, asset_id: &AccountId, price: &Price) -> AssetPeg {
+        self.assert_asset(asset_id);
+        let decimals = self.assets.get(asset_id).unwrap().decimals;
+        let mut peg = self.peg(asset_id);
+        peg.price = Some(price_bps(price, decimals));
+        peg.updated_at = env::block_timestamp().into();
+        self.pegs.insert(asset_id, &peg);
+        peg
+    }
+
+    /// Describes why the peg doesn't allow the action, if it doesn't.
+    pub fn peg_violation(&self, asset_id: &AccountId, action: AssetAction) -> Option<String> {
+        let peg = self.peg(asset_id);
+        let depegged = match action {
+            AssetAction::Deposit => peg.is_below(),
+            AssetAction::Withdraw => peg.is_above(),
+        };
+        if peg.is_stale() {
+            Some(format!(
+                "The price of {} is stale, call sync_stable_prices",
+                asset_id
+            ))
+        } else if depegged {
+            Some(format!(
+                "Asset {} is de-pegged: the price is {} bps of $1",
+                asset_id,
+                peg.price.unwrap()
+            ))
+        } else {
+            None
+        }
+    }
+
+    fn assert_pegged(&self, asset_id: &AccountId, action: AssetAction) {
+        if let Some(violation) = self.peg_violation(asset_id, action) {
+            env::panic_str(&violation);
+        }
+    }
+
+    pub fn reserve(&self, asset_id: &AccountId) -> Balance {
+        self.assert_asset(asset_id);
+        self.reserves.get(asset_id).unwrap_or(0)
+    }
+
+    /// Reserves of all assets converted to USN.
+    pub fn total_reserve(&self) -> Balance {
+        self.assets.iter().fold(0, |total, (asset_id, asset_info)| {
+            let reserve = self.reserves.get(&asset_id).unwrap_or(0);
+            rounding::checked_add(
+                total,
+                self.convert_decimals(reserve, asset_info.decimals, USN_DECIMALS),
+                "stable::total_reserve",
+            )
+        })
+    }
+
+    pub fn set_reserve(&mut self, asset_id: &AccountId, reserve: Balance) {
+        self.assert_asset(asset_id);
+        self.reserves.insert(asset_id, &reserve);
+        event::emit::stable_reserve_set(asset_id, reserve);
+    }
+
+    fn increase_reserve(&mut self, asset_id: &AccountId, asset_amount: Balance) -> Balance {
+        let reserve = rounding::checked_add(
+            self.reserves.get(asset_id).unwrap_or(0),
+            asset_amount,
+            "stable::increase_reserve",
+        );
+        self.reserves.insert(asset_id, &reserve);
+        reserve
+    }
+
+    fn decrease_reserve(&mut self, asset_id: &AccountId, asset_amount: Balance) -> Balance {
+        // Saturates for migrated assets until their reserves are set by the owner.
+        let reserve = self
+            .reserves
+            .get(asset_id)
+            .unwrap_or(0)
+            .saturating_sub(asset_amount);
+        self.reserves.insert(asset_id, &reserve);
+        reserve
     }
 
     pub fn deposit(
@@ -140,11 +475,27 @@ impl StableTreasury {
     ) {
         self.assert_asset(asset_id);
         self.assert_status(asset_id, AssetStatus::Enabled);
+        self.assert_pegged(asset_id, AssetAction::Deposit);
+        let min_deposit = self.limits(asset_id).min_deposit.0;
+        if asset_amount < min_deposit {
+            env::panic_str(&format!(
+                "The deposit of {} is less than the minimum of {}",
+                asset_id, min_deposit
+            ));
+        }
         let asset = self.assets.get(asset_id).unwrap();
         let amount = self.convert_decimals(asset_amount, asset.decimals, USN_DECIMALS);
         let amount_without_fee = self.withdraw_commission(asset_id, amount, AssetAction::Deposit);
+        self.increase_reserve(asset_id, asset_amount);
         ft.internal_deposit(account_id, amount_without_fee);
         event::emit::ft_mint(account_id, amount_without_fee, None);
+        event::emit::stable_deposit(
+            account_id,
+            asset_id,
+            asset_amount,
+            amount_without_fee,
+            amount - amount_without_fee,
+        );
     }
 
     pub fn withdraw(
@@ -154,8 +505,71 @@ impl StableTreasury {
         asset_id: &AccountId,
         amount: Balance,
     ) -> u128 {
+        let (asset_amount, commission, reserve) = self.take_withdrawal(asset_id, amount);
+        ft.internal_withdraw(account_id, amount);
+        event::emit::ft_burn(account_id, amount, None);
+        event::emit::stable_withdraw(
+            account_id,
+            asset_id,
+            amount,
+            asset_amount,
+            commission,
+            reserve,
+            ft.total_supply,
+        );
+        asset_amount
+    }
+
+    /// Withdraws several assets burning their total USN at once.
+    /// Returns the asset amounts to transfer.
+    pub fn withdraw_multi(
+        &mut self,
+        ft: &mut FungibleTokenFreeStorage,
+        account_id: &AccountId,
+        withdrawals: &[(AccountId, Balance)],
+    ) -> Vec<u128> {
+        let legs: Vec<(u128, u128, u128)> = withdrawals
+            .iter()
+            .map(|(asset_id, amount)| self.take_withdrawal(asset_id, *amount))
+            .collect();
+        let total = withdrawals.iter().fold(0, |total, (_, amount)| {
+            rounding::checked_add(total, *amount, "stable::withdraw_multi")
+        });
+        ft.internal_withdraw(account_id, total);
+        event::emit::ft_burn(account_id, total, None);
+        withdrawals
+            .iter()
+            .zip(legs)
+            .map(
+                |((asset_id, amount), (asset_amount, commission, reserve))| {
+                    event::emit::stable_withdraw(
+                        account_id,
+                        asset_id,
+                        *amount,
+                        asset_amount,
+                        commission,
+                        reserve,
+                        ft.total_supply,
+                    );
+                    asset_amount
+                },
+            )
+            .collect()
+    }
+
+    /// Takes the commission and the reserve of the withdrawal, USN is burned by the caller.
+    /// Returns the asset amount, the commission and the reserve left.
+    fn take_withdrawal(&mut self, asset_id: &AccountId, amount: Balance) -> (u128, u128, u128) {
         self.assert_asset(asset_id);
-        self.assert_status(asset_id, AssetStatus::Enabled);
+        self.assert_withdrawable(asset_id);
+        self.assert_pegged(asset_id, AssetAction::Withdraw);
+        let min_withdraw = self.limits(asset_id).min_withdraw.0;
+        if amount < min_withdraw {
+            env::panic_str(&format!(
+                "The withdrawal of {} is less than the minimum of {} USN",
+                asset_id, min_withdraw
+            ));
+        }
         let asset = self.assets.get(asset_id).unwrap();
         let amount_without_fee = self.withdraw_commission(asset_id, amount, AssetAction::Withdraw);
         let asset_amount = self.convert_decimals(amount_without_fee, USN_DECIMALS, asset.decimals);
@@ -163,11 +577,112 @@ impl StableTreasury {
             asset_amount, 0,
             "Not enough USN: specified amount exchanges to 0 tokens"
         );
-        ft.internal_withdraw(account_id, amount);
-        event::emit::ft_burn(account_id, amount, None);
+        let reserve = self.decrease_reserve(asset_id, asset_amount);
+        (asset_amount, amount - amount_without_fee, reserve)
+    }
+
+    /// Takes `amount` of the asset commission (in USN) to pay it out in the asset itself.
+    /// Returns the asset amount to transfer.
+    pub fn withdraw_commission_as_asset(
+        &mut self,
+        asset_id: &AccountId,
+        amount: Balance,
+    ) -> Balance {
+        self.assert_asset(asset_id);
+        let asset = self.assets.get(asset_id).unwrap();
+        let asset_amount = self.convert_decimals(amount, USN_DECIMALS, asset.decimals);
+        assert_ne!(
+            asset_amount, 0,
+            "The commission amount exchanges to 0 tokens"
+        );
+        self.decrease_commission(asset_id, amount);
+        self.decrease_reserve(asset_id, asset_amount);
         asset_amount
     }
 
+    /// Restores the commission taken by `withdraw_commission_as_asset` if the transfer failed.
+    pub fn restore_commission(&mut self, asset_id: &AccountId, amount: Balance) {
+        self.assert_asset(asset_id);
+        let mut asset_info = self.assets.get(asset_id).unwrap();
+        let asset_amount = self.convert_decimals(amount, USN_DECIMALS, asset_info.decimals);
+        asset_info.commission = rounding::checked_add(
+            asset_info.commission.0,
+            amount,
+            "stable::restore_commission",
+        )
+        .into();
+        self.assets.insert(asset_id, &asset_info);
+        self.increase_reserve(asset_id, asset_amount);
+    }
+
+    /// Predicts USN minted for the deposit of `asset_amount` with the same math as `deposit`.
+    pub fn quote_deposit(&self, asset_id: &AccountId, asset_amount: Balance) -> StableQuote {
+        self.assert_asset(asset_id);
+        self.assert_status(asset_id, AssetStatus::Enabled);
+        self.assert_pegged(asset_id, AssetAction::Deposit);
+        let asset = self.assets.get(asset_id).unwrap();
+        let amount = self.convert_decimals(asset_amount, asset.decimals, USN_DECIMALS);
+        let commission = self.commission_of(asset_id, amount, AssetAction::Deposit);
+        StableQuote {
+            amount: rounding::checked_sub(amount, commission, "stable::commission").into(),
+            asset_amount: asset_amount.into(),
+            commission: commission.into(),
+        }
+    }
+
+    /// Predicts the asset amount withdrawn for `amount` of USN with the same math as `withdraw`.
+    pub fn quote_withdraw(&self, asset_id: &AccountId, amount: Balance) -> StableQuote {
+        self.assert_asset(asset_id);
+        self.assert_withdrawable(asset_id);
+        self.assert_pegged(asset_id, AssetAction::Withdraw);
+        let asset = self.assets.get(asset_id).unwrap();
+        let commission = self.commission_of(asset_id, amount, AssetAction::Withdraw);
+        let amount_without_fee = rounding::checked_sub(amount, commission, "stable::commission");
+        StableQuote {
+            amount: amount.into(),
+            asset_amount: self
+                .convert_decimals(amount_without_fee, USN_DECIMALS, asset.decimals)
+                .into(),
+            commission: commission.into(),
+        }
+    }
+
+    /// Predicts USN burned by `withdraw` delivering exactly `asset_amount` of the asset.
+    pub fn quote_withdraw_exact(&self, asset_id: &AccountId, asset_amount: Balance) -> StableQuote {
+        self.assert_asset(asset_id);
+        self.assert_withdrawable(asset_id);
+        self.assert_pegged(asset_id, AssetAction::Withdraw);
+        let asset = self.assets.get(asset_id).unwrap();
+        let amount_without_fee = self.convert_decimals(asset_amount, asset.decimals, USN_DECIMALS);
+        if self.convert_decimals(amount_without_fee, USN_DECIMALS, asset.decimals) != asset_amount {
+            env::panic_str(&format!(
+                "Asset {} can't be withdrawn in the exact amount {}",
+                asset_id, asset_amount
+            ));
+        }
+
+        let rate = asset.commission_rate.withdraw.unwrap();
+        let scale = 10u128.pow(SPREAD_DECIMAL as u32);
+        let mut amount = rounding::mul_div(
+            amount_without_fee,
+            scale,
+            scale - rate as u128,
+            rounding::WITHDRAW_EXACT,
+        );
+        // The commission is rounded down, so a bit less USN may be enough.
+        while amount > 0
+            && amount - 1 - self.calculate_commission(amount - 1, rate) >= amount_without_fee
+        {
+            amount -= 1;
+        }
+
+        StableQuote {
+            amount: amount.into(),
+            asset_amount: asset_amount.into(),
+            commission: self.calculate_commission(amount, rate).into(),
+        }
+    }
+
     pub fn refund(
         &mut self,
         ft: &mut FungibleTokenFreeStorage,
@@ -175,21 +690,74 @@ impl StableTreasury {
         asset_id: &AccountId,
         original_amount: Balance,
     ) {
+        // The refund must succeed even if the asset has been disabled
+        // or its delisting deadline has passed while the withdrawal was in flight.
+        // A removed asset isn't refunded, see `Contract::internal_withdraw_refund`.
         self.assert_asset(asset_id);
-        self.assert_status(asset_id, AssetStatus::Enabled);
-        self.refund_commission(asset_id, original_amount);
+        let commission = self.refund_commission(asset_id, original_amount);
+        let asset = self.assets.get(asset_id).unwrap();
+        let amount_without_fee =
+            rounding::checked_sub(original_amount, commission, "stable::refund");
+        let asset_amount = self.convert_decimals(amount_without_fee, USN_DECIMALS, asset.decimals);
+        self.increase_reserve(asset_id, asset_amount);
         ft.internal_deposit(account_id, original_amount);
         event::emit::ft_mint(account_id, original_amount, Some("Refund"));
+        event::emit::stable_refund(account_id, asset_id, original_amount, commission);
     }
 
-    fn convert_decimals(&self, amount: u128, decimals_from: u8, decimals_to: u8) -> u128 {
-        if decimals_from < decimals_to {
-            amount * 10u128.pow(u32::from(decimals_to - decimals_from))
-        } else if decimals_from > decimals_to {
-            amount / 10u128.pow(u32::from(decimals_from - decimals_to))
+    /// Keeps the asset amount of the failed withdrawal to be claimed later.
+    /// The withdrawal itself stays completed: USN is burned and the commission is taken.
+    pub fn add_failed_withdrawal(
+        &mut self,
+        account_id: &AccountId,
+        asset_id: &AccountId,
+        asset_amount: Balance,
+    ) {
+        let mut claims = self.failed_withdrawals.get(account_id).unwrap_or_default();
+        let claim = claims.entry(asset_id.clone()).or_insert(0);
+        *claim = rounding::checked_add(*claim, asset_amount, "stable::add_failed_withdrawal");
+        self.failed_withdrawals.insert(account_id, &claims);
+        event::emit::failed_withdrawal_add(account_id, asset_id, asset_amount);
+    }
+
+    /// Removes the claim of the asset returning its amount to transfer.
+    pub fn take_failed_withdrawal(
+        &mut self,
+        account_id: &AccountId,
+        asset_id: &AccountId,
+    ) -> Balance {
+        let mut claims = self.failed_withdrawals.get(account_id).unwrap_or_default();
+        let asset_amount = claims
+            .remove(asset_id)
+            .unwrap_or_else(|| env::panic_str("No failed withdrawal of the asset"));
+        if claims.is_empty() {
+            self.failed_withdrawals.remove(account_id);
         } else {
-            amount
+            self.failed_withdrawals.insert(account_id, &claims);
         }
+        event::emit::failed_withdrawal_claim(account_id, asset_id, asset_amount);
+        asset_amount
+    }
+
+    pub fn failed_withdrawals(&self, account_id: &AccountId) -> Vec<(AccountId, U128)> {
+        let mut claims: Vec<(AccountId, U128)> = self
+            .failed_withdrawals
+            .get(account_id)
+            .unwrap_or_default()
+            .into_iter()
+            .map(|(asset_id, asset_amount)| (asset_id, asset_amount.into()))
+            .collect();
+        claims.sort_by(|a, b| a.0.cmp(&b.0));
+        claims
+    }
+
+    fn convert_decimals(&self, amount: u128, decimals_from: u8, decimals_to: u8) -> u128 {
+        rounding::convert_decimals(
+            amount,
+            decimals_from,
+            decimals_to,
+            rounding::STABLE_DECIMALS,
+        )
     }
 
     fn assert_asset(&self, asset_id: &AccountId) {
@@ -204,29 +772,87 @@ impl StableTreasury {
         }
     }
 
+    fn assert_withdrawable(&self, asset_id: &AccountId) {
+        match self.assets.get(asset_id).unwrap().status {
+            AssetStatus::Enabled => {}
+            AssetStatus::Delisting { deadline } if env::block_timestamp() < deadline.0 => {}
+            AssetStatus::Delisting { .. } => env::panic_str(&format!(
+                "Asset {} is delisted: the withdrawal deadline has passed",
+                asset_id
+            )),
+            AssetStatus::Disabled => env::panic_str(&format!(
+                "Asset {} is currently not {:?}",
+                asset_id,
+                AssetStatus::Enabled
+            )),
+        }
+    }
+
+    fn commission_of(&self, asset_id: &AccountId, amount: u128, action: AssetAction) -> u128 {
+        let asset_info = self.assets.get(asset_id).unwrap();
+        let commission_rate = match action {
+            AssetAction::Deposit => asset_info.commission_rate.deposit.unwrap(),
+            AssetAction::Withdraw => asset_info.commission_rate.withdraw.unwrap(),
+        };
+        self.calculate_commission(amount, commission_rate)
+    }
+
     fn withdraw_commission(
         &mut self,
         asset_id: &AccountId,
         amount: u128,
         action: AssetAction,
     ) -> u128 {
+        let commission = self.commission_of(asset_id, amount, action);
         let mut asset_info = self.assets.get(asset_id).unwrap();
-        let commission_rate = match action {
-            AssetAction::Deposit => asset_info.commission_rate.deposit.unwrap(),
-            AssetAction::Withdraw => asset_info.commission_rate.withdraw.unwrap(),
-        };
-        let commission = amount * commission_rate as u128 / 10u128.pow(SPREAD_DECIMAL as u32);
-        asset_info.commission = (asset_info.commission.0 + commission).into();
+        asset_info.commission =
+            rounding::checked_add(asset_info.commission.0, commission, "stable::commission").into();
         self.assets.insert(asset_id, &asset_info);
+        self.record_commission(|epoch| &mut epoch.accrued, commission);
 
-        amount - commission
+        rounding::checked_sub(amount, commission, "stable::commission")
     }
 
-    fn refund_commission(&mut self, asset_id: &AccountId, amount: u128) {
+    fn refund_commission(&mut self, asset_id: &AccountId, amount: u128) -> u128 {
         let asset_info = self.assets.get(asset_id).unwrap();
-        let commission = amount * asset_info.commission_rate.withdraw.unwrap() as u128
-            / 10u128.pow(SPREAD_DECIMAL as u32);
+        let commission =
+            self.calculate_commission(amount, asset_info.commission_rate.withdraw.unwrap());
         self.decrease_commission(asset_id, commission);
+        self.record_commission(|epoch| &mut epoch.refunded, commission);
+        commission
+    }
+
+    /// Records the paid out commission in the current epoch.
+    pub fn record_commission_transfer(&mut self, amount: Balance) {
+        self.record_commission(|epoch| &mut epoch.transferred, amount);
+    }
+
+    /// Adds `amount` to the `field` of the current epoch and the lifetime figures.
+    fn record_commission(&mut self, field: fn(&mut CommissionEpoch) -> &mut U128, amount: Balance) {
+        let index = commission_epoch();
+        let mut epoch = self.commission_epoch(index);
+        for figures in [&mut epoch, &mut self.lifetime_commission] {
+            let value = field(figures);
+            *value = rounding::checked_add(value.0, amount, "stable::record_commission").into();
+        }
+        self.commission_epochs.insert(&index, &epoch);
+    }
+
+    pub fn commission_epoch(&self, index: u64) -> CommissionEpoch {
+        self.commission_epochs.get(&index).unwrap_or_default()
+    }
+
+    pub fn lifetime_commission(&self) -> CommissionEpoch {
+        self.lifetime_commission.clone()
+    }
+
+    fn calculate_commission(&self, amount: u128, rate: u32) -> u128 {
+        rounding::mul_div(
+            amount,
+            rate as u128,
+            10u128.pow(SPREAD_DECIMAL as u32),
+            rounding::COMMISSION,
+        )
     }
 
     pub fn decrease_commission(&mut self, asset_id: &AccountId, commission: u128) {
@@ -234,7 +860,10 @@ impl StableTreasury {
         if let Some(commission) = asset_info.commission.0.checked_sub(commission) {
             asset_info.commission = commission.into();
         } else {
-            env::panic_str(&format!("Failed to decrease asset {} commission", asset_id));
+            rounding::overflow(
+                "stable::decrease_commission",
+                &format!("Failed to decrease asset {} commission", asset_id),
+            );
         }
         self.assets.insert(asset_id, &asset_info);
     }
@@ -278,10 +907,54 @@ impl StableTreasury {
     }
 }
 
+fn reserves_prefix(mut prefix: Vec<u8>) -> Vec<u8> {
+    // `UnorderedMap` of assets occupies `i`, `k` and `v` suffixes.
+    prefix.push(b'r');
+    prefix
+}
+
+fn failed_withdrawals_prefix(mut prefix: Vec<u8>) -> Vec<u8> {
+    prefix.push(b'f');
+    prefix
+}
+
+pub(crate) fn limits_prefix(mut prefix: Vec<u8>) -> Vec<u8> {
+    prefix.push(b'l');
+    prefix
+}
+
+pub(crate) fn pegs_prefix(mut prefix: Vec<u8>) -> Vec<u8> {
+    prefix.push(b'p');
+    prefix
+}
+
+pub(crate) fn commission_epochs_prefix(mut prefix: Vec<u8>) -> Vec<u8> {
+    prefix.push(b'e');
+    prefix
+}
+
+/// The oracle price is in USD per the smallest unit of the asset with `price.decimals`.
+fn price_bps(price: &Price, asset_decimals: u8) -> u32 {
+    let bps_decimals = u32::from(asset_decimals) + 4;
+    let decimals = u32::from(price.decimals);
+    let multiplier = price.multiplier.0;
+    let bps = if decimals >= bps_decimals {
+        10u128
+            .checked_pow(decimals - bps_decimals)
+            .map_or(0, |divisor| multiplier / divisor)
+    } else {
+        10u128
+            .checked_pow(bps_decimals - decimals)
+            .map_or(u128::MAX, |factor| multiplier.saturating_mul(factor))
+    };
+    bps.min(u32::MAX as u128) as u32
+}
+
 #[cfg(all(test, not(target_arch = "wasm32")))]
 mod tests {
     use super::*;
-    use near_sdk::test_utils::accounts;
+    use near_sdk::test_utils::{accounts, VMContextBuilder};
+    use near_sdk::testing_env;
 
     #[test]
     fn test_stable_assets() {
@@ -438,6 +1111,146 @@ mod tests {
         assert_eq!(token.accounts.get(&accounts(1)).unwrap(), 9999);
     }
 
+    #[test]
+    fn test_asset_limits() {
+        let mut treasury = StableTreasury::new(StorageKey::StableTreasury);
+        let mut token = FungibleTokenFreeStorage::new(StorageKey::Token);
+        treasury.set_limits(
+            &usdt_id(),
+            AssetLimits {
+                min_deposit: U128(10000),
+                min_withdraw: U128(1000000000000000),
+            },
+        );
+        assert_eq!(
+            treasury.supported_assets()[0].1.limits.min_deposit,
+            U128(10000)
+        );
+
+        treasury.deposit(&mut token, &accounts(1), &usdt_id(), 10000);
+        let usn_amount = token.accounts.get(&accounts(1)).unwrap();
+        treasury.withdraw(&mut token, &accounts(1), &usdt_id(), usn_amount);
+    }
+
+    fn usdt_price(multiplier: u128) -> Price {
+        Price {
+            multiplier: U128(multiplier),
+            decimals: 10,
+        }
+    }
+
+    #[test]
+    fn test_asset_peg() {
+        let mut treasury = StableTreasury::new(StorageKey::StableTreasury);
+        let mut token = FungibleTokenFreeStorage::new(StorageKey::Token);
+        assert_eq!(price_bps(&usdt_price(10000), 6), 10000);
+        assert_eq!(price_bps(&usdt_price(u128::MAX), 40), u32::MAX);
+
+        // Without the tolerance the price isn't checked.
+        treasury.set_peg_price(&usdt_id(), &usdt_price(9000));
+        treasury.deposit(&mut token, &accounts(1), &usdt_id(), 10000);
+
+        treasury.set_peg_tolerance(&usdt_id(), Some(50));
+        treasury.set_peg_price(&usdt_id(), &usdt_price(9960));
+        assert!(!treasury.peg(&usdt_id()).is_depegged());
+        treasury.deposit(&mut token, &accounts(1), &usdt_id(), 10000);
+
+        // Withdrawals of an asset below the peg are allowed.
+        let peg = treasury.set_peg_price(&usdt_id(), &usdt_price(9900));
+        assert!(peg.is_depegged());
+        assert_eq!(treasury.supported_assets()[0].1.peg, peg);
+        let usn_amount = token.accounts.get(&accounts(1)).unwrap();
+        treasury.withdraw(&mut token, &accounts(1), &usdt_id(), usn_amount);
+    }
+
+    #[test]
+    #[should_panic(expected = "Asset usdt.test.near is de-pegged: the price is 9900 bps of $1")]
+    fn test_deposit_depegged() {
+        let mut treasury = StableTreasury::new(StorageKey::StableTreasury);
+        let mut token = FungibleTokenFreeStorage::new(StorageKey::Token);
+        treasury.set_peg_tolerance(&usdt_id(), Some(50));
+        treasury.set_peg_price(&usdt_id(), &usdt_price(9900));
+        treasury.deposit(&mut token, &accounts(1), &usdt_id(), 10000);
+    }
+
+    #[test]
+    #[should_panic(expected = "Asset usdt.test.near is de-pegged: the price is 10100 bps of $1")]
+    fn test_withdraw_depegged() {
+        let mut treasury = StableTreasury::new(StorageKey::StableTreasury);
+        let mut token = FungibleTokenFreeStorage::new(StorageKey::Token);
+        treasury.deposit(&mut token, &accounts(1), &usdt_id(), 10000);
+        treasury.set_peg_tolerance(&usdt_id(), Some(50));
+        treasury.set_peg_price(&usdt_id(), &usdt_price(10100));
+        let usn_amount = token.accounts.get(&accounts(1)).unwrap();
+        treasury.withdraw(&mut token, &accounts(1), &usdt_id(), usn_amount);
+    }
+
+    #[test]
+    fn test_stale_peg_price() {
+        let mut context = VMContextBuilder::new();
+        let mut treasury = StableTreasury::new(StorageKey::StableTreasury);
+        let mut token = FungibleTokenFreeStorage::new(StorageKey::Token);
+        treasury.deposit(&mut token, &accounts(1), &usdt_id(), 10000);
+
+        // The check is on before the first sync.
+        treasury.set_peg_tolerance(&usdt_id(), Some(50));
+        assert!(treasury.peg(&usdt_id()).is_stale());
+
+        treasury.set_peg_price(&usdt_id(), &usdt_price(10000));
+        testing_env!(context.block_timestamp(MAX_PEG_PRICE_AGE_NS).build());
+        assert_eq!(
+            treasury.peg_violation(&usdt_id(), AssetAction::Withdraw),
+            None
+        );
+
+        testing_env!(context.block_timestamp(MAX_PEG_PRICE_AGE_NS + 1).build());
+        assert_eq!(
+            treasury.peg_violation(&usdt_id(), AssetAction::Withdraw),
+            Some("The price of usdt.test.near is stale, call sync_stable_prices".to_string())
+        );
+        assert!(treasury
+            .peg_violation(&usdt_id(), AssetAction::Deposit)
+            .is_some());
+
+        // Disabling the check ignores the age.
+        treasury.set_peg_tolerance(&usdt_id(), None);
+        let usn_amount = token.accounts.get(&accounts(1)).unwrap();
+        treasury.withdraw(&mut token, &accounts(1), &usdt_id(), usn_amount);
+    }
+
+    #[test]
+    #[should_panic(expected = "The deposit of usdt.test.near is less than the minimum of 10000")]
+    fn test_deposit_less_than_min() {
+        let mut treasury = StableTreasury::new(StorageKey::StableTreasury);
+        let mut token = FungibleTokenFreeStorage::new(StorageKey::Token);
+        treasury.set_limits(
+            &usdt_id(),
+            AssetLimits {
+                min_deposit: U128(10000),
+                min_withdraw: U128(0),
+            },
+        );
+        treasury.deposit(&mut token, &accounts(1), &usdt_id(), 9999);
+    }
+
+    #[test]
+    #[should_panic(
+        expected = "The withdrawal of usdt.test.near is less than the minimum of 1000 USN"
+    )]
+    fn test_withdraw_less_than_min() {
+        let mut treasury = StableTreasury::new(StorageKey::StableTreasury);
+        let mut token = FungibleTokenFreeStorage::new(StorageKey::Token);
+        treasury.set_limits(
+            &usdt_id(),
+            AssetLimits {
+                min_deposit: U128(0),
+                min_withdraw: U128(1000),
+            },
+        );
+        treasury.deposit(&mut token, &accounts(1), &usdt_id(), 10000);
+        treasury.withdraw(&mut token, &accounts(1), &usdt_id(), 999);
+    }
+
     #[test]
     fn test_deposit_with_max_commission_rate() {
         let mut treasury = StableTreasury::new(StorageKey::StableTreasury);
@@ -485,6 +1298,35 @@ mod tests {
         assert_eq!(withdrawn, 99980);
     }
 
+    #[test]
+    fn test_withdraw_exact() {
+        let mut treasury = StableTreasury::new(StorageKey::StableTreasury);
+        let mut token = FungibleTokenFreeStorage::new(StorageKey::Token);
+        treasury.add_asset(&accounts(2), 18);
+        treasury.deposit(&mut token, &accounts(1), &usdt_id(), 10000000);
+        treasury.deposit(&mut token, &accounts(1), &accounts(2), 10000000);
+
+        for (asset_id, asset_amount) in vec![(usdt_id(), 1000000), (accounts(2), 12345)] {
+            let quote = treasury.quote_withdraw_exact(&asset_id, asset_amount);
+            assert_eq!(
+                treasury
+                    .quote_withdraw(&asset_id, quote.amount.0 - 1)
+                    .asset_amount,
+                U128(asset_amount - 1)
+            );
+            let withdrawn = treasury.withdraw(&mut token, &accounts(1), &asset_id, quote.amount.0);
+            assert_eq!(withdrawn, asset_amount);
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "Asset danny can't be withdrawn in the exact amount 101")]
+    fn test_withdraw_exact_unreachable_amount() {
+        let mut treasury = StableTreasury::new(StorageKey::StableTreasury);
+        treasury.add_asset(&accounts(3), 20);
+        treasury.quote_withdraw_exact(&accounts(3), 101);
+    }
+
     #[test]
     fn test_withdraw_with_max_commission_rate() {
         let mut treasury = StableTreasury::new(StorageKey::StableTreasury);
@@ -784,4 +1626,203 @@ mod tests {
         let treasury = StableTreasury::new(StorageKey::StableTreasury);
         treasury.commission_rate(&accounts(1));
     }
+
+    #[test]
+    fn test_deposit_withdraw_events() {
+        let mut treasury = StableTreasury::new(StorageKey::StableTreasury);
+        let mut token = FungibleTokenFreeStorage::new(StorageKey::Token);
+
+        treasury.deposit(&mut token, &accounts(1), &usdt_id(), 1000);
+        treasury.withdraw(&mut token, &accounts(1), &usdt_id(), 999900000000000);
+
+        let logs = near_sdk::test_utils::get_logs();
+        assert_eq!(logs.len(), 4);
+        assert_eq!(
+            logs[1],
+            r#"EVENT_JSON:{"data":[{"account_id":"bob","amount":"999900000000000","asset_amount":"1000","asset_id":"usdt.test.near","commission":"100000000000"}],"event":"stable_deposit","standard":"usn","version":"1.0.0"}"#
+        );
+        assert_eq!(
+            logs[3],
+            r#"EVENT_JSON:{"data":[{"account_id":"bob","amount":"999900000000000","asset_amount":"999","asset_id":"usdt.test.near","commission":"99990000000","reserve":"1","total_supply":"0"}],"event":"stable_withdraw","standard":"usn","version":"1.0.0"}"#
+        );
+    }
+
+    #[test]
+    fn test_delisting() {
+        let mut context = VMContextBuilder::new();
+        testing_env!(context.block_timestamp(100).build());
+        let mut treasury = StableTreasury::new(StorageKey::StableTreasury);
+        let mut token = FungibleTokenFreeStorage::new(StorageKey::Token);
+
+        treasury.add_asset(&accounts(2), 8);
+        treasury.set_peg_tolerance(&accounts(2), Some(100));
+        treasury.set_peg_price(&accounts(2), &usdt_price(100));
+        treasury.deposit(&mut token, &accounts(1), &accounts(2), 100000);
+        treasury.begin_delisting(&accounts(2), 200);
+        assert_eq!(
+            treasury.supported_assets()[1].1.status,
+            AssetStatus::Delisting { deadline: U64(200) }
+        );
+
+        // Withdrawals are still open.
+        treasury.withdraw(&mut token, &accounts(1), &accounts(2), 999900000000000);
+        let commission = treasury.supported_assets()[1].1.commission;
+        assert_eq!(commission, U128(199990000000));
+
+        testing_env!(context.block_timestamp(200).build());
+        treasury.finish_delisting(&accounts(2), &usdt_id());
+        assert_eq!(treasury.supported_assets().len(), 1);
+        assert_eq!(treasury.supported_assets()[0].1.commission, commission);
+        assert!(treasury.pegs.get(&accounts(2)).is_none());
+    }
+
+    #[test]
+    #[should_panic(expected = "Asset usdt.test.near is currently not Enabled")]
+    fn test_deposit_delisting_asset() {
+        let mut context = VMContextBuilder::new();
+        testing_env!(context.block_timestamp(100).build());
+        let mut treasury = StableTreasury::new(StorageKey::StableTreasury);
+        let mut token = FungibleTokenFreeStorage::new(StorageKey::Token);
+
+        treasury.begin_delisting(&usdt_id(), 200);
+        treasury.deposit(&mut token, &accounts(1), &usdt_id(), 100000);
+    }
+
+    #[test]
+    #[should_panic(expected = "Asset charlie is delisted: the withdrawal deadline has passed")]
+    fn test_withdraw_after_delisting_deadline() {
+        let mut context = VMContextBuilder::new();
+        testing_env!(context.block_timestamp(100).build());
+        let mut treasury = StableTreasury::new(StorageKey::StableTreasury);
+        let mut token = FungibleTokenFreeStorage::new(StorageKey::Token);
+
+        treasury.add_asset(&accounts(2), 8);
+        treasury.deposit(&mut token, &accounts(1), &accounts(2), 100000);
+        treasury.begin_delisting(&accounts(2), 200);
+
+        testing_env!(context.block_timestamp(200).build());
+        treasury.withdraw(&mut token, &accounts(1), &accounts(2), 999900000000000);
+    }
+
+    #[test]
+    #[should_panic(expected = "Asset charlie is still open for withdrawals")]
+    fn test_finish_delisting_before_deadline() {
+        let mut context = VMContextBuilder::new();
+        testing_env!(context.block_timestamp(100).build());
+        let mut treasury = StableTreasury::new(StorageKey::StableTreasury);
+
+        treasury.add_asset(&accounts(2), 8);
+        treasury.begin_delisting(&accounts(2), 200);
+        treasury.finish_delisting(&accounts(2), &usdt_id());
+    }
+
+    #[test]
+    fn test_reserve() {
+        let mut treasury = StableTreasury::new(StorageKey::StableTreasury);
+        let mut token = FungibleTokenFreeStorage::new(StorageKey::Token);
+
+        treasury.deposit(&mut token, &accounts(1), &usdt_id(), 100000);
+        assert_eq!(treasury.reserve(&usdt_id()), 100000);
+
+        treasury.withdraw(&mut token, &accounts(1), &usdt_id(), 99990000000000000);
+        assert_eq!(treasury.reserve(&usdt_id()), 20);
+
+        treasury.refund(&mut token, &accounts(1), &usdt_id(), 99990000000000000);
+        assert_eq!(treasury.reserve(&usdt_id()), 100000);
+
+        treasury.set_reserve(&usdt_id(), 5);
+        treasury.withdraw(&mut token, &accounts(1), &usdt_id(), 99990000000000000);
+        assert_eq!(treasury.reserve(&usdt_id()), 0);
+    }
+
+    #[test]
+    fn test_quotes() {
+        let mut treasury = StableTreasury::new(StorageKey::StableTreasury);
+        let mut token = FungibleTokenFreeStorage::new(StorageKey::Token);
+        treasury.set_commission_rate(
+            &usdt_id(),
+            CommissionRate {
+                deposit: Some(1000),
+                withdraw: Some(3000),
+            },
+        );
+
+        let quote = treasury.quote_deposit(&usdt_id(), 123456);
+        treasury.deposit(&mut token, &accounts(1), &usdt_id(), 123456);
+        assert_eq!(
+            token.internal_unwrap_balance_of(&accounts(1)),
+            quote.amount.0
+        );
+        assert_eq!(quote.commission, U128(123456000000000));
+
+        let quote = treasury.quote_withdraw(&usdt_id(), 100000000000000001);
+        let asset_amount =
+            treasury.withdraw(&mut token, &accounts(1), &usdt_id(), 100000000000000001);
+        assert_eq!(quote.asset_amount.0, asset_amount);
+        assert_eq!(quote.asset_amount, U128(99700));
+    }
+
+    #[test]
+    fn test_withdraw_commission_as_asset() {
+        let mut treasury = StableTreasury::new(StorageKey::StableTreasury);
+        let mut token = FungibleTokenFreeStorage::new(StorageKey::Token);
+
+        treasury.deposit(&mut token, &accounts(1), &usdt_id(), 100000000);
+        assert_eq!(
+            treasury.assets.get(&usdt_id()).unwrap().commission,
+            U128(10000000000000000)
+        );
+
+        let asset_amount = treasury.withdraw_commission_as_asset(&usdt_id(), 4000000000000000);
+        assert_eq!(asset_amount, 4000);
+        assert_eq!(
+            treasury.assets.get(&usdt_id()).unwrap().commission,
+            U128(6000000000000000)
+        );
+        assert_eq!(treasury.reserve(&usdt_id()), 99996000);
+
+        treasury.restore_commission(&usdt_id(), 4000000000000000);
+        assert_eq!(
+            treasury.assets.get(&usdt_id()).unwrap().commission,
+            U128(10000000000000000)
+        );
+        assert_eq!(treasury.reserve(&usdt_id()), 100000000);
+    }
+
+    #[test]
+    #[should_panic(expected = "Failed to decrease asset usdt.test.near commission")]
+    fn test_withdraw_commission_as_asset_exceeded() {
+        let mut treasury = StableTreasury::new(StorageKey::StableTreasury);
+        treasury.withdraw_commission_as_asset(&usdt_id(), 1000000000000);
+    }
+
+    #[test]
+    fn test_failed_withdrawals() {
+        let mut treasury = StableTreasury::new(StorageKey::StableTreasury);
+        let asset_id: AccountId = "dai.test.near".parse().unwrap();
+        treasury.add_asset(&asset_id, 18);
+
+        treasury.add_failed_withdrawal(&accounts(1), &usdt_id(), 999);
+        treasury.add_failed_withdrawal(&accounts(1), &usdt_id(), 1);
+        treasury.add_failed_withdrawal(&accounts(1), &asset_id, 5);
+        assert_eq!(
+            treasury.failed_withdrawals(&accounts(1)),
+            vec![(asset_id.clone(), U128(5)), (usdt_id(), U128(1000))]
+        );
+        assert!(treasury.failed_withdrawals(&accounts(2)).is_empty());
+
+        assert_eq!(
+            treasury.take_failed_withdrawal(&accounts(1), &usdt_id()),
+            1000
+        );
+        assert_eq!(treasury.take_failed_withdrawal(&accounts(1), &asset_id), 5);
+        assert!(treasury.failed_withdrawals(&accounts(1)).is_empty());
+    }
+
+    #[test]
+    #[should_panic(expected = "No failed withdrawal of the asset")]
+    fn test_take_missing_failed_withdrawal() {
+        let mut treasury = StableTreasury::new(StorageKey::StableTreasury);
+        treasury.take_failed_withdrawal(&accounts(1), &usdt_id());
+    }
 }
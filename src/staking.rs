@@ -1,10 +1,10 @@
 use crate::*;
 
+use near_sdk::EpochHeight;
+
 const GAS_SURPLUS: Gas = Gas(7_000_000_000_000);
-const GAS_FOR_GET_ACCOUNT: Gas = Gas(7_000_000_000_000);
-const GAS_FOR_STAKE: Gas = Gas(35_000_000_000_000);
-const GAS_FOR_UNSTAKE: Gas = Gas(35_000_000_000_000);
-const GAS_FOR_WITHDRAW: Gas = Gas(35_000_000_000_000);
+/// Gas of `handle_staking_action` besides the view of the pool account.
+const GAS_FOR_ACTION_CALLBACK: Gas = Gas(18_000_000_000_000);
 
 #[derive(Serialize, Deserialize, Debug)]
 #[serde(crate = "near_sdk::serde")]
@@ -18,6 +18,48 @@ pub struct HumanReadableAccount {
     pub can_withdraw: bool,
 }
 
+/// Bookkeeping of NEAR reserves delegated to a staking pool.
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug, Default)]
+#[serde(crate = "near_sdk::serde")]
+pub struct StakingPosition {
+    /// NEAR sent to the pool by `stake`.
+    pub deposited: U128,
+    /// NEAR returned from the pool by `withdraw_all`.
+    pub withdrawn: U128,
+    /// Balances reported by the pool at `synced_at` epoch.
+    pub staked_balance: U128,
+    pub unstaked_balance: U128,
+    pub can_withdraw: bool,
+    /// The epoch of the last unstake: the unstaked balance is locked for a few epochs after it.
+    pub unstaked_at: Option<EpochHeight>,
+    pub synced_at: EpochHeight,
+}
+
+impl StakingPosition {
+    /// Rewards earned by the pool including already withdrawn ones.
+    pub fn rewards(&self) -> Balance {
+        (self.staked_balance.0 + self.unstaked_balance.0 + self.withdrawn.0)
+            .saturating_sub(self.deposited.0)
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct StakingSummary {
+    pub total_staked: U128,
+    pub total_unstaked: U128,
+    pub total_rewards: U128,
+    pub pools: Vec<(AccountId, StakingPosition)>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub enum StakingAction {
+    Stake(U128),
+    Unstake,
+    Withdraw(U128),
+}
+
 #[ext_contract(ext_pool)]
 pub trait StackingPool {
     fn deposit_and_stake(&mut self);
@@ -31,37 +73,85 @@ pub trait StackingPool {
     fn get_account(&self, account_id: AccountId) -> HumanReadableAccount;
 }
 
-pub(crate) fn stake(amount: U128, pool_id: AccountId) -> Promise {
+/// Gas of `handle_staking_action`, which records the action and syncs the position.
+fn gas_for_sync(config: &GasConfig) -> Gas {
+    GAS_FOR_ACTION_CALLBACK + config.staking_view
+}
+
+pub(crate) fn stake(amount: U128, pool_id: AccountId, config: &GasConfig) -> Promise {
     assert!(
         amount.0 <= env::account_balance(),
         "The account doesn't have enough balance"
     );
 
-    ext_pool::deposit_and_stake(pool_id, amount.0, GAS_FOR_STAKE)
+    ext_pool::deposit_and_stake(pool_id.clone(), amount.0, config.stake).then(
+        ext_self::handle_staking_action(
+            pool_id,
+            StakingAction::Stake(amount),
+            env::current_account_id(),
+            NO_DEPOSIT,
+            gas_for_sync(config),
+        ),
+    )
+}
+
+pub(crate) fn withdraw_all(pool_id: AccountId, config: &GasConfig) -> Promise {
+    ext_pool::get_account(
+        env::current_account_id(),
+        pool_id.clone(),
+        NO_DEPOSIT,
+        config.staking_view,
+    )
+    .then(ext_self::handle_withdraw_all(
+        pool_id,
+        env::current_account_id(),
+        NO_DEPOSIT,
+        GAS_SURPLUS + config.staking_withdraw + gas_for_sync(config),
+    ))
 }
 
-pub(crate) fn withdraw_all(pool_id: AccountId) -> Promise {
-    ext_pool::withdraw_all(pool_id, NO_DEPOSIT, GAS_FOR_WITHDRAW)
+/// Refreshes balances of the position from the pool.
+pub(crate) fn sync(pool_id: AccountId, config: &GasConfig) -> Promise {
+    ext_pool::get_account(
+        env::current_account_id(),
+        pool_id.clone(),
+        NO_DEPOSIT,
+        config.staking_view,
+    )
+    .then(ext_self::handle_staking_account(
+        pool_id,
+        env::current_account_id(),
+        NO_DEPOSIT,
+        GAS_SURPLUS,
+    ))
 }
 
-pub(crate) fn unstake(amount: U128, pool_id: AccountId) -> Promise {
+pub(crate) fn unstake(amount: U128, pool_id: AccountId, config: &GasConfig) -> Promise {
     ext_pool::get_account(
         env::current_account_id(),
         pool_id.clone(),
         NO_DEPOSIT,
-        GAS_FOR_GET_ACCOUNT,
+        config.staking_view,
     )
     .then(ext_self::handle_unstake(
         amount,
         pool_id,
         env::current_account_id(),
         NO_DEPOSIT,
-        GAS_SURPLUS + GAS_FOR_UNSTAKE,
+        GAS_SURPLUS + config.unstake + gas_for_sync(config),
     ))
 }
 
-pub(crate) fn unstake_all(pool_id: AccountId) -> Promise {
-    ext_pool::unstake_all(pool_id, NO_DEPOSIT, GAS_FOR_UNSTAKE)
+pub(crate) fn unstake_all(pool_id: AccountId, config: &GasConfig) -> Promise {
+    ext_pool::unstake_all(pool_id.clone(), NO_DEPOSIT, config.unstake).then(
+        ext_self::handle_staking_action(
+            pool_id,
+            StakingAction::Unstake,
+            env::current_account_id(),
+            NO_DEPOSIT,
+            gas_for_sync(config),
+        ),
+    )
 }
 
 #[ext_contract(ext_self)]
@@ -73,6 +163,23 @@ trait SelfHandler {
         pool_id: AccountId,
         #[callback] account_info: HumanReadableAccount,
     ) -> Promise;
+
+    #[private]
+    fn handle_withdraw_all(
+        &mut self,
+        pool_id: AccountId,
+        #[callback] account_info: HumanReadableAccount,
+    ) -> Promise;
+
+    #[private]
+    fn handle_staking_action(&mut self, pool_id: AccountId, action: StakingAction) -> Promise;
+
+    #[private]
+    fn handle_staking_account(
+        &mut self,
+        pool_id: AccountId,
+        #[callback] account_info: HumanReadableAccount,
+    );
 }
 
 trait SelfHandler {
@@ -82,6 +189,16 @@ trait SelfHandler {
         pool_id: AccountId,
         account_info: HumanReadableAccount,
     ) -> Promise;
+
+    fn handle_withdraw_all(
+        &mut self,
+        pool_id: AccountId,
+        account_info: HumanReadableAccount,
+    ) -> Promise;
+
+    fn handle_staking_action(&mut self, pool_id: AccountId, action: StakingAction) -> Promise;
+
+    fn handle_staking_account(&mut self, pool_id: AccountId, account_info: HumanReadableAccount);
 }
 
 #[near_bindgen]
@@ -98,6 +215,160 @@ impl SelfHandler for Contract {
         } else {
             account_info.staked_balance.0
         };
-        ext_pool::unstake(unstake_amount.into(), pool_id, NO_DEPOSIT, GAS_FOR_UNSTAKE)
+        ext_pool::unstake(
+            unstake_amount.into(),
+            pool_id.clone(),
+            NO_DEPOSIT,
+            self.gas_config.unstake,
+        )
+        .then(ext_self::handle_staking_action(
+            pool_id,
+            StakingAction::Unstake,
+            env::current_account_id(),
+            NO_DEPOSIT,
+            gas_for_sync(&self.gas_config),
+        ))
+    }
+
+    #[private]
+    fn handle_withdraw_all(
+        &mut self,
+        pool_id: AccountId,
+        #[callback] account_info: HumanReadableAccount,
+    ) -> Promise {
+        ext_pool::withdraw_all(
+            pool_id.clone(),
+            NO_DEPOSIT,
+            self.gas_config.staking_withdraw,
+        )
+        .then(ext_self::handle_staking_action(
+            pool_id,
+            StakingAction::Withdraw(account_info.unstaked_balance),
+            env::current_account_id(),
+            NO_DEPOSIT,
+            gas_for_sync(&self.gas_config),
+        ))
+    }
+
+    /// Records the action if it succeeded and refreshes the position balances anyway.
+    #[private]
+    fn handle_staking_action(&mut self, pool_id: AccountId, action: StakingAction) -> Promise {
+        let _profile = GasProfile::start("handle_staking_action");
+        let success = is_promise_success();
+        let mut position = self.staking_pools.get(&pool_id).unwrap_or_default();
+        let operation = match action {
+            StakingAction::Stake(amount) => {
+                if success {
+                    position.deposited = (position.deposited.0 + amount.0).into();
+                }
+                TreasuryOperation::Stake {
+                    pool_id: pool_id.clone(),
+                    amount,
+                }
+            }
+            StakingAction::Unstake => {
+                if success {
+                    position.unstaked_at = Some(env::epoch_height());
+                }
+                TreasuryOperation::Unstake {
+                    pool_id: pool_id.clone(),
+                }
+            }
+            StakingAction::Withdraw(amount) => {
+                if success {
+                    position.withdrawn = (position.withdrawn.0 + amount.0).into();
+                }
+                TreasuryOperation::WithdrawStake {
+                    pool_id: pool_id.clone(),
+                    amount,
+                }
+            }
+        };
+        if success {
+            self.staking_pools.insert(&pool_id, &position);
+        }
+        self.treasury_journal.record(operation, success);
+        sync(pool_id, &self.gas_config)
+    }
+
+    #[private]
+    fn handle_staking_account(
+        &mut self,
+        pool_id: AccountId,
+        #[callback] account_info: HumanReadableAccount,
+    ) {
+        let mut position = self.staking_pools.get(&pool_id).unwrap_or_default();
+        position.staked_balance = account_info.staked_balance;
+        position.unstaked_balance = account_info.unstaked_balance;
+        position.can_withdraw = account_info.can_withdraw;
+        position.synced_at = env::epoch_height();
+        self.staking_pools.insert(&pool_id, &position);
+    }
+}
+
+#[near_bindgen]
+impl Contract {
+    /// Refreshes balances of a staking pool where the reserve NEAR is delegated.
+    pub fn sync_staking(&mut self, pool_id: AccountId) -> Promise {
+        if self.staking_pools.get(&pool_id).is_none() {
+            env::panic_str(&format!("Staking pool {} is not used", pool_id));
+        }
+        sync(pool_id, &self.gas_config)
+    }
+
+    pub fn staking_summary(&self) -> StakingSummary {
+        let pools = self.staking_pools.to_vec();
+        StakingSummary {
+            total_staked: pools
+                .iter()
+                .map(|(_, p)| p.staked_balance.0)
+                .sum::<u128>()
+                .into(),
+            total_unstaked: pools
+                .iter()
+                .map(|(_, p)| p.unstaked_balance.0)
+                .sum::<u128>()
+                .into(),
+            total_rewards: pools.iter().map(|(_, p)| p.rewards()).sum::<u128>().into(),
+            pools,
+        }
+    }
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use super::*;
+    use near_sdk::test_utils::{accounts, VMContextBuilder};
+    use near_sdk::testing_env;
+
+    #[test]
+    fn test_staking_summary() {
+        testing_env!(VMContextBuilder::new()
+            .current_account_id(accounts(0))
+            .predecessor_account_id(accounts(0))
+            .build());
+        let mut contract = Contract::new(accounts(1));
+        contract.staking_pools.insert(
+            &accounts(3),
+            &StakingPosition {
+                deposited: U128(1000),
+                withdrawn: U128(300),
+                ..Default::default()
+            },
+        );
+        contract.handle_staking_account(
+            accounts(3),
+            HumanReadableAccount {
+                account_id: accounts(0),
+                unstaked_balance: U128(100),
+                staked_balance: U128(650),
+                can_withdraw: false,
+            },
+        );
+
+        let summary = contract.staking_summary();
+        assert_eq!(summary.total_staked, U128(650));
+        assert_eq!(summary.total_unstaked, U128(100));
+        assert_eq!(summary.total_rewards, U128(50));
     }
 }
@@ -1,16 +1,66 @@
 use crate::*;
 
-use near_contract_standards::storage_management::StorageBalance;
+use near_contract_standards::storage_management::{StorageBalance, StorageBalanceBounds};
 
+/// 1.25 milliNEAR, the balance reported for every account.
+const FREE_STORAGE_BALANCE: Balance = 1250000000000000000000;
+
+/// NEP-145 storage API of a token with free storage.
+/// Every account is treated as registered, so wallets and integrations
+/// probing the storage before transfers don't need to pay for it.
 #[near_bindgen]
 impl Contract {
-    /// Always returns 125 milliNEAR indicating that user doesn't need to be registered.
+    /// Always returns 1.25 milliNEAR indicating that user doesn't need to be registered.
     /// It's a workaround for integrations required NEP-125 storage compatibility.
     pub fn storage_balance_of(&self, account_id: AccountId) -> Option<StorageBalance> {
         let _ = account_id;
-        Some(StorageBalance {
-            total: 1250000000000000000000.into(),
-            available: 0.into(),
-        })
+        Some(free_storage_balance())
+    }
+
+    /// Refunds the attached deposit as the storage is free.
+    #[payable]
+    pub fn storage_deposit(
+        &mut self,
+        account_id: Option<AccountId>,
+        registration_only: Option<bool>,
+    ) -> StorageBalance {
+        let _ = (account_id, registration_only);
+        let amount = env::attached_deposit();
+        if amount > 0 {
+            Promise::new(env::predecessor_account_id()).transfer(amount);
+        }
+        free_storage_balance()
+    }
+
+    /// There is nothing to withdraw: the available storage balance is always zero.
+    #[payable]
+    pub fn storage_withdraw(&mut self, amount: Option<U128>) -> StorageBalance {
+        assert_one_yocto();
+        if amount.map_or(false, |amount| amount.0 > 0) {
+            env::panic_str("The amount is greater than the available storage balance");
+        }
+        free_storage_balance()
+    }
+
+    /// Accounts can't be unregistered: there is no storage deposit to return.
+    #[payable]
+    pub fn storage_unregister(&mut self, force: Option<bool>) -> bool {
+        assert_one_yocto();
+        let _ = force;
+        false
+    }
+
+    pub fn storage_balance_bounds(&self) -> StorageBalanceBounds {
+        StorageBalanceBounds {
+            min: 0.into(),
+            max: Some(0.into()),
+        }
+    }
+}
+
+fn free_storage_balance() -> StorageBalance {
+    StorageBalance {
+        total: FREE_STORAGE_BALANCE.into(),
+        available: 0.into(),
     }
 }
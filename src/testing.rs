@@ -0,0 +1,16 @@
+//! Fixtures shared by unit tests of all modules.
+
+use crate::*;
+
+use near_sdk::test_utils::{accounts, VMContextBuilder};
+use near_sdk::testing_env;
+
+/// Creates the contract owned by `owner_id`, the owner keeps calling it.
+pub(crate) fn setup_contract(owner_id: AccountId) -> (VMContextBuilder, Contract) {
+    let mut context = VMContextBuilder::new();
+    context
+        .current_account_id(accounts(0))
+        .predecessor_account_id(owner_id.clone());
+    testing_env!(context.build());
+    (context, Contract::new(owner_id))
+}
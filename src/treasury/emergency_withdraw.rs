@@ -0,0 +1,115 @@
+use std::collections::HashMap;
+
+use crate::*;
+
+use super::gas::*;
+use super::pool::ref_address;
+use super::ref_finance::*;
+
+use near_sdk::json_types::U128;
+use near_sdk::{require, ONE_YOCTO};
+
+/// Each withdrawal takes the `ref_withdraw` gas, so only a few tokens fit into one call.
+const MAX_EMERGENCY_TOKENS: usize = 3;
+
+#[near_bindgen]
+impl Contract {
+    #[payable]
+    /// Withdraws all deposits of the given tokens from ref.finance back to the contract.
+    /// Bypasses the liquidity logic, nothing is burned. Only can be called by owner or guardians.
+    pub fn emergency_withdraw_from_ref(&mut self, token_ids: Vec<AccountId>) -> Promise {
+        assert_one_yocto();
+        self.assert_owner_or_guardian("emergency_withdraw_from_ref");
+        require!(
+            !token_ids.is_empty() && token_ids.len() <= MAX_EMERGENCY_TOKENS,
+            &format!("Specify from 1 to {} tokens", MAX_EMERGENCY_TOKENS)
+        );
+
+        let token_count = token_ids.len() as u64;
+        ext_ref_finance::get_deposits(
+            env::current_account_id(),
+            ref_address().parse().unwrap(),
+            NO_DEPOSIT,
+            GAS_FOR_GET_DEPOSITS,
+        )
+        .then(ext_self::handle_emergency_withdraw(
+            token_ids,
+            env::current_account_id(),
+            NO_DEPOSIT,
+            GAS_SURPLUS
+                + (GAS_SURPLUS + self.gas_config.ref_withdraw + GAS_FOR_FINISH_BURNING)
+                    * token_count,
+        ))
+    }
+}
+
+#[ext_contract(ext_self)]
+trait EmergencyWithdrawHandler {
+    #[private]
+    fn handle_emergency_withdraw(
+        &mut self,
+        token_ids: Vec<AccountId>,
+        #[callback] deposits: HashMap<AccountId, U128>,
+    ) -> PromiseOrValue<()>;
+
+    #[private]
+    fn finish_emergency_withdraw(&mut self, token_id: AccountId, amount: U128);
+}
+
+trait EmergencyWithdrawHandler {
+    fn handle_emergency_withdraw(
+        &mut self,
+        token_ids: Vec<AccountId>,
+        deposits: HashMap<AccountId, U128>,
+    ) -> PromiseOrValue<()>;
+
+    fn finish_emergency_withdraw(&mut self, token_id: AccountId, amount: U128);
+}
+
+#[near_bindgen]
+impl EmergencyWithdrawHandler for Contract {
+    #[private]
+    fn handle_emergency_withdraw(
+        &mut self,
+        token_ids: Vec<AccountId>,
+        #[callback] deposits: HashMap<AccountId, U128>,
+    ) -> PromiseOrValue<()> {
+        let withdrawals = token_ids.into_iter().filter_map(|token_id| {
+            deposits
+                .get(&token_id)
+                .filter(|amount| amount.0 > 0)
+                .map(|amount| (token_id, *amount))
+        });
+
+        withdrawals
+            .map(|(token_id, amount)| {
+                ext_ref_finance::withdraw(
+                    token_id.clone(),
+                    amount,
+                    Some(false),
+                    ref_address().parse().unwrap(),
+                    ONE_YOCTO,
+                    self.gas_config.ref_withdraw,
+                )
+                .then(ext_self::finish_emergency_withdraw(
+                    token_id,
+                    amount,
+                    env::current_account_id(),
+                    NO_DEPOSIT,
+                    GAS_FOR_FINISH_BURNING,
+                ))
+            })
+            .reduce(|all, withdrawal| all.and(withdrawal))
+            .map_or(PromiseOrValue::Value(()), PromiseOrValue::Promise)
+    }
+
+    #[private]
+    fn finish_emergency_withdraw(&mut self, token_id: AccountId, amount: U128) {
+        let success = is_promise_success();
+        event::emit::ref_emergency_withdraw(&token_id, amount.into(), success);
+        self.treasury_journal.record(
+            TreasuryOperation::EmergencyWithdraw { token_id, amount },
+            success,
+        );
+    }
+}
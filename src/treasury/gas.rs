@@ -1,10 +1,7 @@
 use near_sdk::Gas;
 
 pub const GAS_FOR_GET_DEPOSITS: Gas = Gas(7_000_000_000_000);
+pub const GAS_FOR_GET_POOL: Gas = Gas(7_000_000_000_000);
 pub const GAS_FOR_GET_SHARES: Gas = Gas(7_000_000_000_000);
-pub const GAS_FOR_FT_TRANSFER_CALL: Gas = Gas(45_000_000_000_000);
-pub const GAS_FOR_ADD_LIQUIDITY: Gas = Gas(17_000_000_000_000);
-pub const GAS_FOR_REMOVE_LIQUIDITY: Gas = Gas(17_000_000_000_000);
-pub const GAS_FOR_WITHDRAW: Gas = Gas(55_000_000_000_000);
 pub const GAS_FOR_FINISH_BURNING: Gas = Gas(7_000_000_000_000);
 pub const GAS_SURPLUS: Gas = Gas(7_000_000_000_000);
@@ -0,0 +1,154 @@
+use crate::*;
+
+use near_sdk::collections::LookupMap;
+use near_sdk::IntoStorageKey;
+
+/// Number of the latest operations kept in the journal.
+const JOURNAL_CAPACITY: u64 = 200;
+const DEFAULT_HISTORY_LIMIT: u64 = 50;
+
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(crate = "near_sdk::serde")]
+pub enum TreasuryOperation {
+    /// USN minted to be added to the pool.
+    MintForLiquidity {
+        pool_id: u64,
+        amount: U128,
+    },
+    AddLiquidity {
+        pool_id: u64,
+        amounts: Vec<U128>,
+    },
+    RemoveLiquidity {
+        pool_id: u64,
+        shares: U128,
+    },
+    /// USN returned from the pool and burned.
+    BurnRemovedLiquidity {
+        amount: U128,
+    },
+    Stake {
+        pool_id: AccountId,
+        amount: U128,
+    },
+    Unstake {
+        pool_id: AccountId,
+    },
+    WithdrawStake {
+        pool_id: AccountId,
+        amount: U128,
+    },
+    /// Deposit withdrawn from ref.finance by `emergency_withdraw_from_ref`.
+    EmergencyWithdraw {
+        token_id: AccountId,
+        amount: U128,
+    },
+    /// USN of the contract account burned as not escrowed for anyone.
+    BurnUntracked {
+        amount: U128,
+    },
+}
+
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(crate = "near_sdk::serde")]
+pub struct JournalEntry {
+    pub index: U64,
+    pub timestamp: U64,
+    pub operation: TreasuryOperation,
+    pub success: bool,
+}
+
+/// Ring buffer of the latest treasury operations.
+#[derive(BorshDeserialize, BorshSerialize)]
+pub struct TreasuryJournal {
+    entries: LookupMap<u64, JournalEntry>,
+    /// Number of operations ever recorded, i.e. the index of the next one.
+    len: u64,
+}
+
+impl TreasuryJournal {
+    pub fn new<S>(prefix: S) -> Self
+    where
+        S: IntoStorageKey,
+    {
+        Self {
+            entries: LookupMap::new(prefix),
+            len: 0,
+        }
+    }
+
+    pub fn record(&mut self, operation: TreasuryOperation, success: bool) {
+        let entry = JournalEntry {
+            index: self.len.into(),
+            timestamp: env::block_timestamp().into(),
+            operation,
+            success,
+        };
+        self.entries.insert(&(self.len % JOURNAL_CAPACITY), &entry);
+        self.len += 1;
+    }
+
+    /// Returns up to `limit` entries starting from the `from` index.
+    /// Entries older than the journal capacity are skipped.
+    pub fn history(&self, from: u64, limit: u64) -> Vec<JournalEntry> {
+        let from = from.max(self.len.saturating_sub(JOURNAL_CAPACITY));
+        let to = self.len.min(from.saturating_add(limit));
+        (from..to)
+            .map(|index| self.entries.get(&(index % JOURNAL_CAPACITY)).unwrap())
+            .collect()
+    }
+}
+
+#[near_bindgen]
+impl Contract {
+    /// Returns the treasury operations starting from the `from` index (the oldest by default).
+    pub fn get_treasury_history(&self, from: Option<U64>, limit: Option<u64>) -> Vec<JournalEntry> {
+        self.treasury_journal.history(
+            from.map_or(0, |from| from.0),
+            limit.unwrap_or(DEFAULT_HISTORY_LIMIT),
+        )
+    }
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use super::*;
+
+    fn stake(amount: u128) -> TreasuryOperation {
+        TreasuryOperation::Stake {
+            pool_id: "pool.test.near".parse().unwrap(),
+            amount: U128(amount),
+        }
+    }
+
+    #[test]
+    fn test_history() {
+        let mut journal = TreasuryJournal::new(b"j".to_vec());
+        for i in 0..3 {
+            journal.record(stake(i), true);
+        }
+        let history = journal.history(1, 10);
+        assert_eq!(history.len(), 2);
+        assert_eq!(history[0].index, U64(1));
+        assert_eq!(history[0].operation, stake(1));
+        assert_eq!(journal.history(0, 1).len(), 1);
+        assert!(journal.history(3, 10).is_empty());
+    }
+
+    #[test]
+    fn test_ring_buffer() {
+        let mut journal = TreasuryJournal::new(b"j".to_vec());
+        for i in 0..(JOURNAL_CAPACITY + 5) as u128 {
+            journal.record(stake(i), i % 2 == 0);
+        }
+        let history = journal.history(0, JOURNAL_CAPACITY * 2);
+        assert_eq!(history.len(), JOURNAL_CAPACITY as usize);
+        assert_eq!(history[0].index, U64(5));
+        assert_eq!(history[0].operation, stake(5));
+        assert!(!history[0].success);
+        assert_eq!(
+            history.last().unwrap().operation,
+            stake((JOURNAL_CAPACITY + 4) as u128)
+        );
+    }
+}
@@ -0,0 +1,120 @@
+use crate::*;
+
+use super::gas::*;
+
+use near_sdk::require;
+
+/// A lock outlives its promise chain only if the release callback is lost.
+const LOCK_DURATION_SEC: u64 = 5 * 60;
+
+/// Treasury workflows which must not run concurrently with themselves.
+#[derive(
+    BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Copy, Debug, PartialEq,
+)]
+#[serde(crate = "near_sdk::serde")]
+pub enum LockedOperation {
+    TransferStableLiquidity,
+    TransferSimpleLiquidity,
+    WithdrawStablePool,
+}
+
+impl Contract {
+    /// Locks the operation until the chain is released or the lock expires.
+    /// Returns the expiration which identifies the lock.
+    pub(crate) fn lock_operation(&mut self, operation: LockedOperation) -> Timestamp {
+        let now = env::block_timestamp();
+        require!(
+            self.operation_locks
+                .get(&operation)
+                .map_or(true, |expires_at| expires_at <= now),
+            &format!("Operation {:?} is already in progress", operation)
+        );
+        let expires_at = now + LOCK_DURATION_SEC * 10u64.pow(9);
+        self.operation_locks.insert(&operation, &expires_at);
+        expires_at
+    }
+}
+
+/// Chains the release of the lock after the operation, successful or not.
+/// The result of the operation is passed through.
+pub fn release_lock_after(
+    promise: Promise,
+    operation: LockedOperation,
+    expires_at: Timestamp,
+) -> Promise {
+    promise.then(ext_self::release_operation_lock(
+        operation,
+        expires_at.into(),
+        env::current_account_id(),
+        NO_DEPOSIT,
+        GAS_SURPLUS,
+    ))
+}
+
+#[near_bindgen]
+impl Contract {
+    /// Returns active locks of treasury operations with their expiration.
+    pub fn operation_locks(&self) -> Vec<(LockedOperation, U64)> {
+        let now = env::block_timestamp();
+        self.operation_locks
+            .iter()
+            .filter(|&(_, expires_at)| expires_at > now)
+            .map(|(operation, expires_at)| (operation, expires_at.into()))
+            .collect()
+    }
+}
+
+#[ext_contract(ext_self)]
+trait OperationLockHandler {
+    #[private]
+    fn release_operation_lock(&mut self, operation: LockedOperation, expires_at: U64);
+}
+
+trait OperationLockHandler {
+    fn release_operation_lock(&mut self, operation: LockedOperation, expires_at: U64);
+}
+
+#[near_bindgen]
+impl OperationLockHandler for Contract {
+    /// Never panics, so the lock is released even if the operation failed.
+    #[private]
+    fn release_operation_lock(&mut self, operation: LockedOperation, expires_at: U64) {
+        // An expired lock could be taken by the next execution already.
+        if self.operation_locks.get(&operation) == Some(expires_at.0) {
+            self.operation_locks.remove(&operation);
+        }
+        match env::promise_result(0) {
+            PromiseResult::Successful(data) => env::value_return(&data),
+            _ => env::log_str(&format!("Operation {:?} failed", operation)),
+        }
+    }
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use super::*;
+    use crate::testing::setup_contract;
+    use near_sdk::test_utils::accounts;
+    use near_sdk::testing_env;
+
+    #[test]
+    fn test_lock_expiration() {
+        let (mut context, mut contract) = setup_contract(accounts(1));
+
+        let expires_at = contract.lock_operation(LockedOperation::WithdrawStablePool);
+        contract.lock_operation(LockedOperation::TransferStableLiquidity);
+        assert_eq!(contract.operation_locks().len(), 2);
+
+        testing_env!(context.block_timestamp(expires_at).build());
+        assert_eq!(contract.operation_locks().len(), 0);
+        contract.lock_operation(LockedOperation::WithdrawStablePool);
+    }
+
+    #[test]
+    #[should_panic(expected = "Operation WithdrawStablePool is already in progress")]
+    fn test_lock_twice() {
+        let (_, mut contract) = setup_contract(accounts(1));
+        contract.lock_operation(LockedOperation::WithdrawStablePool);
+        contract.lock_operation(LockedOperation::WithdrawStablePool);
+    }
+}
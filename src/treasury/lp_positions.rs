@@ -0,0 +1,156 @@
+use crate::*;
+
+use super::gas::*;
+use super::pool::Pool;
+use super::ref_finance::*;
+
+use near_sdk::json_types::{U128, U64};
+use near_sdk::{require, ONE_YOCTO};
+
+/// LP shares of the contract in a ref.finance pool.
+/// Updated after each add or remove of liquidity and refreshed by `sync_ref_lp_positions`.
+#[derive(
+    BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug, Default, PartialEq,
+)]
+#[serde(crate = "near_sdk::serde")]
+pub struct LpPosition {
+    pub shares: U128,
+    /// Timestamp of the last update in nanoseconds.
+    pub updated_at: U64,
+}
+
+#[near_bindgen]
+impl Contract {
+    pub fn get_ref_lp_positions(&self) -> Vec<(u64, LpPosition)> {
+        self.lp_positions.to_vec()
+    }
+
+    /// Refreshes LP shares of all registered pools from ref.finance.
+    pub fn sync_ref_lp_positions(&mut self) -> Promise {
+        self.pool_registry
+            .iter()
+            .map(|(pool_id, info)| {
+                ext_ref_finance::get_pool_shares(
+                    pool_id,
+                    env::current_account_id(),
+                    info.ref_id,
+                    NO_DEPOSIT,
+                    GAS_FOR_GET_SHARES,
+                )
+                .then(ext_self::handle_sync_lp_shares(
+                    pool_id,
+                    env::current_account_id(),
+                    NO_DEPOSIT,
+                    GAS_SURPLUS,
+                ))
+            })
+            .reduce(|all, sync| all.and(sync))
+            .unwrap_or_else(|| env::panic_str("No pools are registered"))
+    }
+
+    #[payable]
+    /// Removes `shares` of the pool liquidity leaving the tokens deposited on ref.finance.
+    /// Only can be called by owner.
+    pub fn remove_stable_liquidity(
+        &mut self,
+        pool_id: u64,
+        shares: U128,
+        min_amounts: Vec<U128>,
+    ) -> Promise {
+        assert_one_yocto();
+        self.assert_owner("remove_stable_liquidity");
+        let pool = Pool::from_registry_with_assert(&self.pool_registry, pool_id);
+        require!(
+            min_amounts.len() == pool.tokens.len(),
+            "Specify minimal amounts of all pool tokens"
+        );
+
+        ext_ref_finance::remove_liquidity(
+            pool.id,
+            shares,
+            min_amounts,
+            pool.ref_id,
+            ONE_YOCTO,
+            self.gas_config.ref_remove_liquidity,
+        )
+        .then(ext_self::handle_remove_lp_shares(
+            pool.id,
+            shares,
+            env::current_account_id(),
+            NO_DEPOSIT,
+            GAS_SURPLUS,
+        ))
+    }
+
+    pub(crate) fn increase_lp_shares(&mut self, pool_id: u64, shares: Balance) {
+        let mut position = self.lp_positions.get(&pool_id).unwrap_or_default();
+        position.shares =
+            rounding::checked_add(position.shares.0, shares, "treasury::lp_shares").into();
+        self.update_lp_position(pool_id, position);
+    }
+
+    pub(crate) fn decrease_lp_shares(&mut self, pool_id: u64, shares: Balance) {
+        let mut position = self.lp_positions.get(&pool_id).unwrap_or_default();
+        // Saturates for shares added before the local accounting.
+        position.shares = position.shares.0.saturating_sub(shares).into();
+        self.update_lp_position(pool_id, position);
+    }
+
+    fn update_lp_position(&mut self, pool_id: u64, mut position: LpPosition) {
+        position.updated_at = env::block_timestamp().into();
+        self.lp_positions.insert(&pool_id, &position);
+    }
+}
+
+#[ext_contract(ext_self)]
+trait LpPositionsHandler {
+    #[private]
+    fn handle_sync_lp_shares(&mut self, pool_id: u64, #[callback] shares: U128);
+
+    #[private]
+    fn handle_remove_lp_shares(
+        &mut self,
+        pool_id: u64,
+        shares: U128,
+        #[callback] amounts: Vec<U128>,
+    ) -> Vec<U128>;
+}
+
+trait LpPositionsHandler {
+    fn handle_sync_lp_shares(&mut self, pool_id: u64, shares: U128);
+
+    fn handle_remove_lp_shares(
+        &mut self,
+        pool_id: u64,
+        shares: U128,
+        amounts: Vec<U128>,
+    ) -> Vec<U128>;
+}
+
+#[near_bindgen]
+impl LpPositionsHandler for Contract {
+    #[private]
+    fn handle_sync_lp_shares(&mut self, pool_id: u64, #[callback] shares: U128) {
+        self.update_lp_position(
+            pool_id,
+            LpPosition {
+                shares,
+                ..Default::default()
+            },
+        );
+    }
+
+    #[private]
+    fn handle_remove_lp_shares(
+        &mut self,
+        pool_id: u64,
+        shares: U128,
+        #[callback] amounts: Vec<U128>,
+    ) -> Vec<U128> {
+        self.decrease_lp_shares(pool_id, shares.into());
+        event::emit::liquidity_remove(pool_id, shares.into());
+        self.treasury_journal
+            .record(TreasuryOperation::RemoveLiquidity { pool_id, shares }, true);
+        amounts
+    }
+}
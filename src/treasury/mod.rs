@@ -1,6 +1,20 @@
+mod emergency_withdraw;
 mod ft;
 mod gas;
+mod journal;
+mod locks;
+mod lp_positions;
 mod pool;
+mod quote;
 mod ref_finance;
+mod transfer_simple_liquidity;
 mod transfer_stable_liquidity;
 mod withdraw_stable_pool;
+
+pub use journal::{TreasuryJournal, TreasuryOperation};
+pub use locks::LockedOperation;
+pub use lp_positions::LpPosition;
+pub use pool::{pool_registry, pools_config_is_valid, ref_address, PoolInfo};
+pub use quote::PoolSnapshot;
+pub use ref_finance::ext_ref_finance;
+pub use transfer_simple_liquidity::SimpleLiquidity;
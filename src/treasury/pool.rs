@@ -1,5 +1,8 @@
 use crate::*;
 
+use near_sdk::collections::UnorderedMap;
+use near_sdk::IntoStorageKey;
+
 pub(super) const USDT_DECIMALS: u8 = 6;
 
 struct PoolsConfig {
@@ -54,10 +57,90 @@ const CONFIG: PoolsConfig = if cfg!(feature = "mainnet") {
     }
 };
 
+/// A ref.finance pool which the treasury is allowed to work with.
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(crate = "near_sdk::serde")]
+pub struct PoolInfo {
+    pub ref_id: AccountId,
+    pub tokens: Vec<AccountId>,
+    pub decimals: Vec<u8>,
+}
+
+/// Creates the on-chain pool registry filled with the compile-time configuration.
+pub fn pool_registry<S>(prefix: S) -> UnorderedMap<u64, PoolInfo>
+where
+    S: IntoStorageKey,
+{
+    let mut registry = UnorderedMap::new(prefix);
+    for &(pool_id, tokens) in CONFIG.pools {
+        registry.insert(
+            &pool_id,
+            &PoolInfo {
+                ref_id: CONFIG.ref_address.parse().unwrap(),
+                tokens: tokens.iter().map(|t| t.0.parse().unwrap()).collect(),
+                decimals: tokens.iter().map(|t| t.1).collect(),
+            },
+        );
+    }
+    registry
+}
+
 #[near_bindgen]
 impl Contract {
     pub fn pools(&self) -> Vec<u64> {
-        CONFIG.pools.iter().map(|&(pool_id, _)| pool_id).collect()
+        self.pool_registry.keys().collect()
+    }
+
+    pub fn list_pools(&self) -> Vec<(u64, PoolInfo)> {
+        self.pool_registry.to_vec()
+    }
+
+    /// Allows the treasury to add liquidity to the pool. Only can be called by owner.
+    pub fn register_pool(
+        &mut self,
+        pool_id: u64,
+        ref_id: AccountId,
+        tokens: Vec<AccountId>,
+        decimals: Vec<u8>,
+    ) {
+        self.assert_owner("register_pool");
+        assert!(
+            tokens.len() > 1 && tokens.len() == decimals.len(),
+            "Every pool token requires decimals"
+        );
+        assert!(
+            decimals.iter().all(|&d| d > 0),
+            "Decimal value is out of bounds"
+        );
+        assert!(
+            tokens.contains(&env::current_account_id()),
+            "The pool must contain USN"
+        );
+        if self.pool_registry.get(&pool_id).is_some() {
+            env::panic_str(&format!("pool_id {} is already registered", pool_id));
+        }
+        self.pool_registry.insert(
+            &pool_id,
+            &PoolInfo {
+                ref_id,
+                tokens,
+                decimals,
+            },
+        );
+    }
+
+    /// Removes the pool from the registry. Only can be called by owner.
+    /// The stable pool can't be removed to keep `withdraw_stable_pool` working.
+    pub fn remove_pool(&mut self, pool_id: u64) {
+        self.assert_owner("remove_pool");
+        assert_ne!(
+            pool_id,
+            Pool::stable_pool().id,
+            "The stable pool can't be removed"
+        );
+        if self.pool_registry.remove(&pool_id).is_none() {
+            env::panic_str(&format!("pool_id {} is not registered", pool_id));
+        }
     }
 }
 
@@ -69,23 +152,16 @@ pub struct Pool {
 }
 
 impl Pool {
-    pub fn from_config_with_assert(pool_id: u64) -> Self {
-        CONFIG
-            .pools
-            .iter()
-            .find_map(|&(id, tokens)| {
-                if pool_id == id {
-                    Some(Self {
-                        ref_id: CONFIG.ref_address.parse().unwrap(),
-                        id: pool_id,
-                        tokens: tokens.iter().map(|t| t.0.parse().unwrap()).collect(),
-                        decimals: tokens.iter().map(|t| t.1).collect(),
-                    })
-                } else {
-                    None
-                }
-            })
-            .unwrap_or_else(|| env::panic_str(&format!("pool_id {} is not allowed", pool_id)))
+    pub fn from_registry_with_assert(registry: &UnorderedMap<u64, PoolInfo>, pool_id: u64) -> Self {
+        let info = registry
+            .get(&pool_id)
+            .unwrap_or_else(|| env::panic_str(&format!("pool_id {} is not allowed", pool_id)));
+        Self {
+            ref_id: info.ref_id,
+            id: pool_id,
+            tokens: info.tokens,
+            decimals: info.decimals,
+        }
     }
 
     /// Extends the whole part of the amount (the left part to the decimal point)
@@ -112,6 +188,22 @@ impl Pool {
     }
 }
 
+pub fn ref_address() -> &'static str {
+    CONFIG.ref_address
+}
+
+/// Checks that the compile-time pools configuration is usable.
+pub fn pools_config_is_valid() -> bool {
+    CONFIG.ref_address.parse::<AccountId>().is_ok()
+        && !CONFIG.pools.is_empty()
+        && CONFIG.pools.iter().all(|&(_, tokens)| {
+            tokens.len() > 1
+                && tokens
+                    .iter()
+                    .all(|token| token.0.parse::<AccountId>().is_ok() && token.1 > 0)
+        })
+}
+
 pub fn extend_decimals(whole: u128, decimals: u8) -> u128 {
     whole * 10u128.pow(decimals as u32)
 }
@@ -0,0 +1,154 @@
+use crate::*;
+
+use super::gas::*;
+use super::pool::Pool;
+use super::ref_finance::*;
+
+use near_sdk::json_types::U64;
+use near_sdk::require;
+
+const FEE_DIVISOR: u32 = 10_000;
+
+/// Reserves of the configured ref.finance pool cached by `sync_pool`.
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct PoolSnapshot {
+    pub pool_id: u64,
+    pub tokens: Vec<AccountId>,
+    pub reserves: Vec<U128>,
+    /// Swap fee in basis points.
+    pub total_fee: u32,
+    pub timestamp: U64,
+}
+
+impl PoolSnapshot {
+    /// Calculates the constant-product swap output deducting the pool fee.
+    pub fn quote(
+        &self,
+        token_in: &AccountId,
+        token_out: &AccountId,
+        amount_in: Balance,
+    ) -> Balance {
+        require!(token_in != token_out, "Tokens should be different");
+        let reserve_in = self.reserve(token_in);
+        let reserve_out = self.reserve(token_out);
+        require!(
+            reserve_in > 0 && reserve_out > 0,
+            "The pool has no liquidity"
+        );
+
+        let amount_in_with_fee = U256::from(amount_in) * U256::from(FEE_DIVISOR - self.total_fee);
+        let numerator = amount_in_with_fee * U256::from(reserve_out);
+        let denominator = U256::from(reserve_in) * U256::from(FEE_DIVISOR) + amount_in_with_fee;
+
+        rounding::to_u128(
+            rounding::div(numerator, denominator, rounding::POOL_QUOTE),
+            "treasury::pool_quote",
+        )
+    }
+
+    fn reserve(&self, token_id: &AccountId) -> Balance {
+        self.tokens
+            .iter()
+            .position(|token| token == token_id)
+            .map(|i| self.reserves[i].0)
+            .unwrap_or_else(|| env::panic_str(&format!("Token {} is not in the pool", token_id)))
+    }
+}
+
+#[near_bindgen]
+impl Contract {
+    /// Refreshes the cached reserves of the configured swap pool from ref.finance.
+    pub fn sync_pool(&mut self) -> Promise {
+        let pool = Pool::stable_pool();
+
+        ext_ref_finance::get_pool(pool.id, pool.ref_id, NO_DEPOSIT, GAS_FOR_GET_POOL).then(
+            ext_self::handle_sync_pool(pool.id, env::current_account_id(), NO_DEPOSIT, GAS_SURPLUS),
+        )
+    }
+
+    pub fn pool_snapshot(&self) -> Option<PoolSnapshot> {
+        self.pool_snapshot.clone()
+    }
+
+    /// Returns the expected output of swapping `amount_in` of `token_in`
+    /// to `token_out` against the cached reserves of the configured pool.
+    pub fn pool_quote(&self, token_in: AccountId, token_out: AccountId, amount_in: U128) -> U128 {
+        self.pool_snapshot
+            .as_ref()
+            .unwrap_or_else(|| env::panic_str("The pool is not synchronized yet"))
+            .quote(&token_in, &token_out, amount_in.into())
+            .into()
+    }
+}
+
+#[ext_contract(ext_self)]
+trait PoolHandler {
+    #[private]
+    fn handle_sync_pool(&mut self, pool_id: u64, #[callback] info: RefPoolInfo);
+}
+
+trait PoolHandler {
+    fn handle_sync_pool(&mut self, pool_id: u64, info: RefPoolInfo);
+}
+
+#[near_bindgen]
+impl PoolHandler for Contract {
+    #[private]
+    fn handle_sync_pool(&mut self, pool_id: u64, #[callback] info: RefPoolInfo) {
+        require!(
+            info.token_account_ids.len() == info.amounts.len(),
+            "Malformed pool info"
+        );
+        self.pool_snapshot = Some(PoolSnapshot {
+            pool_id,
+            tokens: info.token_account_ids,
+            reserves: info.amounts,
+            total_fee: info.total_fee,
+            timestamp: env::block_timestamp().into(),
+        });
+    }
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use super::*;
+    use near_sdk::test_utils::accounts;
+
+    fn snapshot(total_fee: u32) -> PoolSnapshot {
+        PoolSnapshot {
+            pool_id: 0,
+            tokens: vec![accounts(1), accounts(2)],
+            reserves: vec![U128(1_000_000), U128(2_000_000)],
+            total_fee,
+            timestamp: U64(0),
+        }
+    }
+
+    #[test]
+    fn test_quote_without_fee() {
+        let snapshot = snapshot(0);
+        assert_eq!(
+            snapshot.quote(&accounts(1), &accounts(2), 1_000_000),
+            1_000_000
+        );
+        assert_eq!(
+            snapshot.quote(&accounts(2), &accounts(1), 2_000_000),
+            500_000
+        );
+        assert_eq!(snapshot.quote(&accounts(1), &accounts(2), 1), 1);
+    }
+
+    #[test]
+    fn test_quote_with_fee() {
+        let snapshot = snapshot(30);
+        // 1000 * 9970 * 2000000 / (1000000 * 10000 + 1000 * 9970)
+        assert_eq!(snapshot.quote(&accounts(1), &accounts(2), 1000), 1992);
+    }
+
+    #[test]
+    #[should_panic(expected = "Token danny is not in the pool")]
+    fn test_quote_unknown_token() {
+        snapshot(0).quote(&accounts(1), &accounts(3), 1000);
+    }
+}
@@ -1,11 +1,30 @@
 use crate::*;
 
+// From https://github.com/ref-finance/ref-contracts/blob/main/ref-exchange/src/views.rs
+#[derive(Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct RefPoolInfo {
+    pub token_account_ids: Vec<AccountId>,
+    pub amounts: Vec<U128>,
+    pub total_fee: u32,
+}
+
 #[ext_contract(ext_ref_finance)]
 trait RefFinance {
+    fn get_pool(&self, pool_id: u64) -> RefPoolInfo;
+
     fn get_deposits(&self, account_id: AccountId) -> HashMap<AccountId, U128>;
 
     fn get_pool_shares(&self, pool_id: u64, account_id: AccountId) -> U128;
 
+    #[payable]
+    fn add_liquidity(
+        &mut self,
+        pool_id: u64,
+        amounts: Vec<U128>,
+        min_amounts: Option<Vec<U128>>,
+    ) -> U128;
+
     #[payable]
     fn add_stable_liquidity(&mut self, pool_id: u64, amounts: Vec<U128>, min_shares: U128) -> U128;
 
@@ -0,0 +1,243 @@
+use crate::*;
+
+use super::ft::*;
+use super::gas::*;
+use super::locks::*;
+use super::pool::Pool;
+use super::ref_finance::*;
+
+use near_sdk::json_types::U128;
+use near_sdk::require;
+
+/// Liquidity added by the treasury to a USN/wNEAR pool.
+///
+/// The impermanent loss is the difference between the hodl value of the added tokens
+/// at the current price and the current value of the pool shares.
+#[derive(
+    BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug, Default, PartialEq,
+)]
+#[serde(crate = "near_sdk::serde")]
+pub struct SimpleLiquidity {
+    pub usn: U128,
+    pub wnear: U128,
+    /// USN value of the added tokens at the oracle prices of each addition.
+    pub value: U128,
+    pub shares: U128,
+}
+
+impl SimpleLiquidity {
+    /// USN value of holding the added tokens instead of providing liquidity.
+    pub fn hodl_value(&self, rate: &ExchangeRate) -> Balance {
+        self.usn.0 + near_to_usn(self.wnear.0, rate)
+    }
+}
+
+#[near_bindgen]
+impl Contract {
+    /// Adds `usn_amount` of USN and wNEAR of the same value at the oracle price
+    /// to a regular USN/wNEAR ref.finance pool. Mints necessary amount of USN.
+    /// wNEAR must be already deposited on ref.finance on behalf of "usn".
+    #[payable]
+    pub fn transfer_simple_liquidity(&mut self, pool_id: u64, usn_amount: U128) -> Promise {
+        self.assert_operator("transfer_simple_liquidity");
+
+        let pool = Pool::from_registry_with_assert(&self.pool_registry, pool_id);
+        require!(
+            pool.tokens
+                == vec![
+                    env::current_account_id(),
+                    self.oracle_config.asset_id.clone()
+                ],
+            "The pool must consist of USN and wNEAR"
+        );
+        require!(usn_amount.0 > 0, "The token amount must be not zero");
+        // add_liquidity() requires a storage deposit.
+        require!(
+            env::attached_deposit() > 0,
+            "Requires attached deposit of at least 1 yoctoNEAR"
+        );
+
+        let lock = self.lock_operation(LockedOperation::TransferSimpleLiquidity);
+
+        let transfer = self.oracle_config.get_exchange_rate_promise().then(
+            ext_self::handle_simple_liquidity_price(
+                pool.id,
+                usn_amount,
+                env::current_account_id(),
+                env::attached_deposit(),
+                self.gas_config.ref_ft_transfer_call
+                    + self.gas_config.ref_add_liquidity
+                    + GAS_SURPLUS * 4,
+            ),
+        );
+        release_lock_after(transfer, LockedOperation::TransferSimpleLiquidity, lock)
+    }
+
+    pub fn get_simple_liquidity(&self, pool_id: u64) -> Option<SimpleLiquidity> {
+        self.simple_liquidity.get(&pool_id)
+    }
+
+    /// Returns the USN value of holding the added tokens at the last oracle price.
+    /// Compared with the value of the pool shares it gives the impermanent loss.
+    pub fn simple_liquidity_hodl_value(&self, pool_id: u64) -> U128 {
+        let rate = self
+            .oracle
+            .last_report
+            .as_ref()
+            .unwrap_or_else(|| env::panic_str("No oracle price is reported yet"));
+        self.simple_liquidity
+            .get(&pool_id)
+            .map_or(0, |position| position.hodl_value(rate))
+            .into()
+    }
+}
+
+fn near_to_usn(near: Balance, rate: &ExchangeRate) -> Balance {
+    rounding::mul_div(
+        near,
+        rate.multiplier(),
+        10u128.pow(u32::from(rate.decimals() - USN_DECIMALS)),
+        rounding::SIMPLE_LIQUIDITY,
+    )
+}
+
+fn usn_to_near(usn: Balance, rate: &ExchangeRate) -> Balance {
+    rounding::mul_div(
+        usn,
+        10u128.pow(u32::from(rate.decimals() - USN_DECIMALS)),
+        rate.multiplier(),
+        rounding::SIMPLE_LIQUIDITY,
+    )
+}
+
+#[ext_contract(ext_self)]
+trait SimpleLiquidityHandler {
+    #[private]
+    #[payable]
+    fn handle_simple_liquidity_price(
+        &mut self,
+        pool_id: u64,
+        usn_amount: U128,
+        #[callback] price: PriceData,
+    ) -> Promise;
+
+    #[private]
+    fn handle_add_simple_liquidity(
+        &mut self,
+        pool_id: u64,
+        amounts: Vec<U128>,
+        value: U128,
+        #[callback] shares: U128,
+    ) -> U128;
+}
+
+trait SimpleLiquidityHandler {
+    fn handle_simple_liquidity_price(
+        &mut self,
+        pool_id: u64,
+        usn_amount: U128,
+        price: PriceData,
+    ) -> Promise;
+
+    fn handle_add_simple_liquidity(
+        &mut self,
+        pool_id: u64,
+        amounts: Vec<U128>,
+        value: U128,
+        shares: U128,
+    ) -> U128;
+}
+
+#[near_bindgen]
+impl SimpleLiquidityHandler for Contract {
+    #[private]
+    #[payable]
+    fn handle_simple_liquidity_price(
+        &mut self,
+        pool_id: u64,
+        usn_amount: U128,
+        #[callback] price: PriceData,
+    ) -> Promise {
+        let pool = Pool::from_registry_with_assert(&self.pool_registry, pool_id);
+        let rate = self.oracle_config.exchange_rates(price).current().clone();
+        event::emit::oracle_rate(&rate, RateSource::Current);
+        self.oracle.last_report = Some(rate.clone());
+
+        let usn_id = env::current_account_id();
+        let usn_amount = usn_amount.0;
+        let wnear_amount = usn_to_near(usn_amount, &rate);
+        require!(wnear_amount > 0, "The USN amount exchanges to 0 wNEAR");
+
+        let usn_balance = self.token.internal_unwrap_balance_of(&usn_id);
+        if usn_balance < usn_amount {
+            let yet_to_mint = usn_amount - usn_balance;
+            self.token.internal_deposit(&usn_id, yet_to_mint);
+            event::emit::ft_mint(&usn_id, yet_to_mint, None);
+            self.treasury_journal.record(
+                TreasuryOperation::MintForLiquidity {
+                    pool_id: pool.id,
+                    amount: yet_to_mint.into(),
+                },
+                true,
+            );
+            self.check_backing();
+        }
+
+        let amounts = vec![U128(usn_amount), U128(wnear_amount)];
+        // wNEAR is added at the same value, so the total is about twice the USN amount.
+        let value = U128(usn_amount + near_to_usn(wnear_amount, &rate));
+
+        event::emit::liquidity_add(pool.id, &amounts);
+        self.treasury_journal.record(
+            TreasuryOperation::AddLiquidity {
+                pool_id: pool.id,
+                amounts: amounts.clone(),
+            },
+            true,
+        );
+
+        self.token
+            .internal_transfer_call(
+                &usn_id,
+                &pool.ref_id,
+                usn_amount,
+                self.gas_config.ref_ft_transfer_call,
+                None,
+                REF_DEPOSIT_ACTION.to_string(),
+            )
+            .then(ext_ref_finance::add_liquidity(
+                pool.id,
+                amounts.clone(),
+                None,
+                pool.ref_id,
+                env::attached_deposit(),
+                self.gas_config.ref_add_liquidity,
+            ))
+            .then(ext_self::handle_add_simple_liquidity(
+                pool.id,
+                amounts,
+                value,
+                env::current_account_id(),
+                NO_DEPOSIT,
+                GAS_SURPLUS,
+            ))
+    }
+
+    #[private]
+    fn handle_add_simple_liquidity(
+        &mut self,
+        pool_id: u64,
+        amounts: Vec<U128>,
+        value: U128,
+        #[callback] shares: U128,
+    ) -> U128 {
+        let mut position = self.simple_liquidity.get(&pool_id).unwrap_or_default();
+        position.usn = (position.usn.0 + amounts[0].0).into();
+        position.wnear = (position.wnear.0 + amounts[1].0).into();
+        position.value = (position.value.0 + value.0).into();
+        position.shares = (position.shares.0 + shares.0).into();
+        self.simple_liquidity.insert(&pool_id, &position);
+        self.increase_lp_shares(pool_id, shares.into());
+        shares
+    }
+}
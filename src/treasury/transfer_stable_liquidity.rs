@@ -4,6 +4,7 @@ use crate::*;
 
 use super::ft::*;
 use super::gas::*;
+use super::locks::*;
 use super::pool::Pool;
 use super::ref_finance::*;
 
@@ -27,11 +28,13 @@ impl Contract {
     ///         next time with full ref.finance deposits (transfers would fail in this case).
     /// Step 4. `REF -> POOL`: add_stable_liquidity to the TOKENS/USN stable pool filling it
     ///         from usn deposit.
+    /// Step 5. `REF -> USN`: If the liquidity wasn't added, withdraw the minted USN back
+    ///         and burn it.
     #[payable]
     pub fn transfer_stable_liquidity(&mut self, pool_id: u64, whole_amount: U128) -> Promise {
-        self.assert_owner();
+        self.assert_operator("transfer_stable_liquidity");
 
-        let pool = Pool::from_config_with_assert(pool_id);
+        let pool = Pool::from_registry_with_assert(&self.pool_registry, pool_id);
 
         // 1 yoctoNEAR for each ft_transfer_call (except of the internal transfer).
         // More NEARs could be required for add_stable_liquidity().
@@ -50,8 +53,9 @@ impl Contract {
         );
 
         let usn_id = env::current_account_id();
+        let lock = self.lock_operation(LockedOperation::TransferStableLiquidity);
 
-        ext_ref_finance::get_deposits(
+        let transfer = ext_ref_finance::get_deposits(
             usn_id,
             pool.ref_id.clone(),
             NO_DEPOSIT,
@@ -62,11 +66,14 @@ impl Contract {
             whole_amount,
             env::current_account_id(),
             env::attached_deposit(),
-            GAS_FOR_FT_TRANSFER_CALL * pool.tokens.len() as u64
+            self.gas_config.ref_ft_transfer_call * pool.tokens.len() as u64
                 + GAS_FOR_GET_DEPOSITS
-                + GAS_FOR_ADD_LIQUIDITY
-                + GAS_SURPLUS * 3,
-        ))
+                + self.gas_config.ref_add_liquidity
+                + self.gas_config.ref_withdraw
+                + GAS_FOR_FINISH_BURNING
+                + GAS_SURPLUS * 5,
+        ));
+        release_lock_after(transfer, LockedOperation::TransferStableLiquidity, lock)
     }
 }
 
@@ -89,6 +96,21 @@ trait RefFinanceHandler {
         whole_amount: U128,
         #[callback] deposits: HashMap<AccountId, U128>,
     );
+
+    #[private]
+    fn handle_add_lp_shares(&mut self, pool_id: u64, #[callback] shares: U128) -> U128;
+
+    #[private]
+    #[payable]
+    fn handle_liquidity_added(&mut self, pool_id: u64, minted: U128) -> PromiseOrValue<U128>;
+
+    #[private]
+    fn handle_minted_usn_withdrawn(
+        &mut self,
+        pool_id: u64,
+        minted: U128,
+        untracked_before: U128,
+    ) -> U128;
 }
 
 trait RefFinanceHandler {
@@ -105,6 +127,17 @@ trait RefFinanceHandler {
         whole_amount: U128,
         deposits: HashMap<AccountId, U128>,
     );
+
+    fn handle_add_lp_shares(&mut self, pool_id: u64, shares: U128) -> U128;
+
+    fn handle_liquidity_added(&mut self, pool_id: u64, minted: U128) -> PromiseOrValue<U128>;
+
+    fn handle_minted_usn_withdrawn(
+        &mut self,
+        pool_id: u64,
+        minted: U128,
+        untracked_before: U128,
+    ) -> U128;
 }
 
 #[near_bindgen]
@@ -117,7 +150,8 @@ impl RefFinanceHandler for Contract {
         whole_amount: U128,
         #[callback] deposits: HashMap<AccountId, U128>,
     ) -> Promise {
-        let pool = Pool::from_config_with_assert(pool_id);
+        let pool = Pool::from_registry_with_assert(&self.pool_registry, pool_id);
+        let mut minted = 0;
 
         let tokens = pool
             // Convert the whole decimal part to a full number for each token.
@@ -142,7 +176,7 @@ impl RefFinanceHandler for Contract {
                         REF_DEPOSIT_ACTION.to_string(),
                         token_id.clone(),
                         ONE_YOCTO,
-                        GAS_FOR_FT_TRANSFER_CALL,
+                        self.gas_config.ref_ft_transfer_call,
                     )
                 } else {
                     let usn_balance = self.token.internal_unwrap_balance_of(&usn_id);
@@ -151,14 +185,23 @@ impl RefFinanceHandler for Contract {
                     if usn_balance < amount {
                         let yet_to_mint = amount - usn_balance;
                         self.token.internal_deposit(&usn_id, yet_to_mint);
+                        minted = yet_to_mint;
                         event::emit::ft_mint(&usn_id, yet_to_mint, None);
+                        self.treasury_journal.record(
+                            TreasuryOperation::MintForLiquidity {
+                                pool_id: pool.id,
+                                amount: yet_to_mint.into(),
+                            },
+                            true,
+                        );
+                        self.check_backing();
                     }
 
                     self.token.internal_transfer_call(
                         &usn_id,
                         &pool.ref_id.clone(),
                         amount,
-                        GAS_FOR_FT_TRANSFER_CALL,
+                        self.gas_config.ref_ft_transfer_call,
                         None,
                         REF_DEPOSIT_ACTION.to_string(),
                     )
@@ -179,13 +222,24 @@ impl RefFinanceHandler for Contract {
             whole_amount,
             env::current_account_id(),
             env::attached_deposit() - ONE_YOCTO * (pool.tokens.len() as u128 - 1),
-            GAS_FOR_ADD_LIQUIDITY + GAS_SURPLUS,
+            self.gas_config.ref_add_liquidity + GAS_SURPLUS * 2,
+        );
+
+        let liquidity_added = ext_self::handle_liquidity_added(
+            pool.id,
+            minted.into(),
+            env::current_account_id(),
+            ONE_YOCTO,
+            GAS_SURPLUS + self.gas_config.ref_withdraw + GAS_FOR_FINISH_BURNING,
         );
 
         if let Some(transfers) = maybe_transfers {
-            transfers.then(get_deposits).then(add_liquidity)
+            transfers
+                .then(get_deposits)
+                .then(add_liquidity)
+                .then(liquidity_added)
         } else {
-            get_deposits.then(add_liquidity)
+            get_deposits.then(add_liquidity).then(liquidity_added)
         }
     }
 
@@ -197,7 +251,7 @@ impl RefFinanceHandler for Contract {
         whole_amount: U128,
         #[callback] deposits: HashMap<AccountId, U128>,
     ) {
-        let pool = Pool::from_config_with_assert(pool_id);
+        let pool = Pool::from_registry_with_assert(&self.pool_registry, pool_id);
         let amounts = pool.extend_decimals(whole_amount.into());
 
         // All deposits must have enough of liquidity.
@@ -210,15 +264,131 @@ impl RefFinanceHandler for Contract {
         }
 
         let min_shares = NO_DEPOSIT.into();
+        let amounts: Vec<U128> = amounts.map(|(_, amount)| amount.into()).collect();
+
+        event::emit::liquidity_add(pool.id, &amounts);
+        self.treasury_journal.record(
+            TreasuryOperation::AddLiquidity {
+                pool_id: pool.id,
+                amounts: amounts.clone(),
+            },
+            true,
+        );
 
         ext_ref_finance::add_stable_liquidity(
             pool.id,
-            amounts.clone().map(|(_, amount)| amount.into()).collect(),
+            amounts,
             min_shares,
             pool.ref_id.clone(),
             env::attached_deposit(),
-            GAS_FOR_ADD_LIQUIDITY,
+            self.gas_config.ref_add_liquidity,
         )
+        .then(ext_self::handle_add_lp_shares(
+            pool.id,
+            env::current_account_id(),
+            NO_DEPOSIT,
+            GAS_SURPLUS,
+        ))
         .as_return();
     }
+
+    #[private]
+    fn handle_add_lp_shares(&mut self, pool_id: u64, #[callback] shares: U128) -> U128 {
+        self.increase_lp_shares(pool_id, shares.into());
+        shares
+    }
+
+    /// Withdraws the USN minted for the transfer back from ref.finance
+    /// if the liquidity wasn't added, it's burned by `handle_minted_usn_withdrawn`.
+    #[private]
+    #[payable]
+    fn handle_liquidity_added(&mut self, pool_id: u64, minted: U128) -> PromiseOrValue<U128> {
+        let _profile = GasProfile::start("handle_liquidity_added");
+        if let Some(shares) = promise_result_json::<U128>(0) {
+            return PromiseOrValue::Value(shares);
+        }
+        if minted.0 == 0 {
+            return PromiseOrValue::Value(U128(0));
+        }
+
+        let pool = Pool::from_registry_with_assert(&self.pool_registry, pool_id);
+        ext_ref_finance::withdraw(
+            env::current_account_id(),
+            minted,
+            Some(false),
+            pool.ref_id,
+            ONE_YOCTO,
+            self.gas_config.ref_withdraw,
+        )
+        .then(ext_self::handle_minted_usn_withdrawn(
+            pool_id,
+            minted,
+            self.untracked_contract_usn(),
+            env::current_account_id(),
+            NO_DEPOSIT,
+            GAS_FOR_FINISH_BURNING,
+        ))
+        .into()
+    }
+
+    /// Burns the minted USN returned by the withdrawal, i.e. the growth of untracked USN
+    /// of the contract account up to `minted`. Other untracked USN is left to reconciliation.
+    #[private]
+    fn handle_minted_usn_withdrawn(
+        &mut self,
+        pool_id: u64,
+        minted: U128,
+        untracked_before: U128,
+    ) -> U128 {
+        let returned = self
+            .untracked_contract_usn()
+            .0
+            .saturating_sub(untracked_before.0)
+            .min(minted.0);
+        if returned > 0 {
+            self.burn_contract_usn(returned);
+            event::emit::usn_reconcile(Some(pool_id), returned);
+        }
+        U128(returned)
+    }
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use super::*;
+    use crate::testing::setup_contract;
+    use near_sdk::test_utils::accounts;
+    use near_sdk::testing_env;
+
+    #[test]
+    fn test_burn_withdrawn_minted_usn() {
+        let (mut context, mut contract) = setup_contract(accounts(1));
+        testing_env!(context.predecessor_account_id(accounts(0)).build());
+        contract.token.internal_deposit(&accounts(0), 30);
+        let untracked_before = contract.untracked_contract_usn();
+
+        // The withdrawal returned 100 minted USN.
+        contract.token.internal_deposit(&accounts(0), 100);
+        assert_eq!(
+            contract.handle_minted_usn_withdrawn(0, U128(100), untracked_before),
+            U128(100)
+        );
+        assert_eq!(contract.ft_balance_of(accounts(0)), U128(30));
+        assert_eq!(contract.ft_total_supply(), U128(30));
+    }
+
+    #[test]
+    fn test_burn_failed_withdrawal_of_minted_usn() {
+        let (mut context, mut contract) = setup_contract(accounts(1));
+        testing_env!(context.predecessor_account_id(accounts(0)).build());
+        contract.token.internal_deposit(&accounts(0), 30);
+
+        // The withdrawal failed, the unrelated untracked USN isn't burned.
+        let untracked_before = contract.untracked_contract_usn();
+        assert_eq!(
+            contract.handle_minted_usn_withdrawn(0, U128(100), untracked_before),
+            U128(0)
+        );
+        assert_eq!(contract.ft_balance_of(accounts(0)), U128(30));
+    }
 }
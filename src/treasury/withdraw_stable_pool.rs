@@ -1,16 +1,13 @@
 use crate::*;
 
 use super::gas::*;
+use super::locks::*;
 use super::pool::Pool;
 use super::ref_finance::*;
 
 use near_sdk::json_types::U128;
 use near_sdk::{require, ONE_YOCTO};
 
-uint::construct_uint!(
-    pub struct U256(4);
-);
-
 #[near_bindgen]
 impl Contract {
     #[payable]
@@ -22,9 +19,9 @@ impl Contract {
     ///
     /// It fails if 'usn' is the only liquidity provider in the stable pool.
     pub fn withdraw_stable_pool(&mut self, percent: Option<u8>) -> Promise {
-        self.assert_owner();
+        self.assert_operator("withdraw_stable_pool");
 
-        let pool = Pool::stable_pool();
+        let pool = Pool::from_registry_with_assert(&self.pool_registry, Pool::stable_pool().id);
 
         // 3 yoctoNEARs: 2 `withdraw` and 1 `remove_liquidity`.
         require!(
@@ -32,7 +29,9 @@ impl Contract {
             "Requires exactly 3 yoctoNEAR of attached deposit"
         );
 
-        ext_ref_finance::get_pool_shares(
+        let lock = self.lock_operation(LockedOperation::WithdrawStablePool);
+
+        let withdrawal = ext_ref_finance::get_pool_shares(
             pool.id,
             env::current_account_id(),
             pool.ref_id.clone(),
@@ -44,10 +43,11 @@ impl Contract {
             env::current_account_id(),
             env::attached_deposit(),
             GAS_SURPLUS * 6
-                + GAS_FOR_REMOVE_LIQUIDITY
-                + GAS_FOR_WITHDRAW * 2
+                + self.gas_config.ref_remove_liquidity
+                + self.gas_config.ref_withdraw * 2
                 + GAS_FOR_FINISH_BURNING,
-        ))
+        ));
+        release_lock_after(withdrawal, LockedOperation::WithdrawStablePool, lock)
     }
 }
 
@@ -59,7 +59,7 @@ trait RefFinanceHandler {
 
     #[private]
     #[payable]
-    fn handle_remove_deposit(&mut self, #[callback] amounts: Vec<U128>) -> Promise;
+    fn handle_remove_deposit(&mut self, shares: U128, #[callback] amounts: Vec<U128>) -> Promise;
 
     #[private]
     fn finish_removing_with_burn(&mut self, amount: U128);
@@ -68,7 +68,7 @@ trait RefFinanceHandler {
 trait RefFinanceHandler {
     fn handle_start_removing(&mut self, percent: Option<u8>, shares: U128) -> Promise;
 
-    fn handle_remove_deposit(&mut self, amounts: Vec<U128>) -> Promise;
+    fn handle_remove_deposit(&mut self, shares: U128, amounts: Vec<U128>) -> Promise;
 
     fn finish_removing_with_burn(&mut self, amount: U128);
 }
@@ -92,20 +92,35 @@ impl RefFinanceHandler for Contract {
 
         require!(percent <= 100, "Maximum 100% of shares can be withdrawn");
 
-        let shares_amount = U256::from(u128::from(shares)) * U256::from(percent) / 100u128;
+        let shares_amount = rounding::mul_div(
+            shares.into(),
+            percent.into(),
+            PERCENT_MULTIPLIER,
+            rounding::POOL_SHARES,
+        );
+
+        event::emit::liquidity_remove(pool.id, shares_amount);
+        self.treasury_journal.record(
+            TreasuryOperation::RemoveLiquidity {
+                pool_id: pool.id,
+                shares: shares_amount.into(),
+            },
+            true,
+        );
 
         ext_ref_finance::remove_liquidity(
             pool.id,
-            U128(U256::as_u128(&shares_amount)),
+            U128(shares_amount),
             min_amounts,
             pool.ref_id,
             ONE_YOCTO,
-            GAS_FOR_REMOVE_LIQUIDITY,
+            self.gas_config.ref_remove_liquidity,
         )
         .then(ext_self::handle_remove_deposit(
+            U128(shares_amount),
             env::current_account_id(),
             ONE_YOCTO * 2,
-            GAS_SURPLUS * 3 + GAS_FOR_WITHDRAW * 2 + GAS_FOR_FINISH_BURNING,
+            GAS_SURPLUS * 3 + self.gas_config.ref_withdraw * 2 + GAS_FOR_FINISH_BURNING,
         ))
     }
 
@@ -114,19 +129,21 @@ impl RefFinanceHandler for Contract {
     // and the first token is USN.
     #[private]
     #[payable]
-    fn handle_remove_deposit(&mut self, #[callback] amounts: Vec<U128>) -> Promise {
+    fn handle_remove_deposit(&mut self, shares: U128, #[callback] amounts: Vec<U128>) -> Promise {
         let pool = Pool::stable_pool();
 
         require!(amounts.len() == 2);
         require!(pool.tokens[0] == env::current_account_id());
 
+        self.decrease_lp_shares(pool.id, shares.into());
+
         ext_ref_finance::withdraw(
             pool.tokens[1].clone(),
             amounts[1],
             Some(false),
             pool.ref_id.clone(),
             ONE_YOCTO,
-            GAS_FOR_WITHDRAW,
+            self.gas_config.ref_withdraw,
         )
         .then(ext_ref_finance::withdraw(
             pool.tokens[0].clone(),
@@ -134,7 +151,7 @@ impl RefFinanceHandler for Contract {
             Some(false),
             pool.ref_id,
             ONE_YOCTO,
-            GAS_FOR_WITHDRAW,
+            self.gas_config.ref_withdraw,
         ))
         .then(ext_self::finish_removing_with_burn(
             amounts[0],
@@ -146,10 +163,13 @@ impl RefFinanceHandler for Contract {
 
     #[private]
     fn finish_removing_with_burn(&mut self, amount: U128) {
-        if is_promise_success() {
+        let success = is_promise_success();
+        if success {
             self.token
                 .internal_withdraw(&env::current_account_id(), amount.into());
             event::emit::ft_burn(&env::current_account_id(), amount.into(), None);
         }
+        self.treasury_journal
+            .record(TreasuryOperation::BurnRemovedLiquidity { amount }, success);
     }
 }
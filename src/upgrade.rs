@@ -0,0 +1,160 @@
+//! Two-stage upgrade: the code is staged first and deployed after a timelock.
+//!
+//! The staged code lives in raw storage keys outside of the contract state,
+//! so the state layout doesn't depend on the pending upgrade. The deployment
+//! and the migration are batched together, so a failing migration leaves
+//! the old code active.
+
+use std::convert::TryInto;
+
+use crate::*;
+
+use near_sdk::json_types::Base58CryptoHash;
+use near_sdk::{require, CryptoHash};
+
+const UPGRADE_TIMELOCK_SEC: u64 = 2 * 24 * 60 * 60;
+const STAGED_CODE_KEY: &[u8] = b"STAGED_CODE";
+const STAGED_UPGRADE_KEY: &[u8] = b"STAGED_UPGRADE";
+const MIGRATE_METHOD_NAME: &str = "migrate";
+const UPDATE_GAS_LEFTOVER: Gas = Gas(5_000_000_000_000);
+
+#[derive(BorshDeserialize, BorshSerialize)]
+struct StagedUpgrade {
+    hash: CryptoHash,
+    /// Timestamp in nanoseconds.
+    activates_at: Timestamp,
+}
+
+fn staged_upgrade() -> Option<StagedUpgrade> {
+    env::storage_read(STAGED_UPGRADE_KEY)
+        .map(|data| StagedUpgrade::try_from_slice(&data).expect("Invalid staged upgrade"))
+}
+
+/// Stores the code replacing the previously staged one.
+fn stage(code: &[u8]) {
+    let hash: CryptoHash = env::sha256(code).try_into().unwrap();
+    let staged = StagedUpgrade {
+        hash,
+        activates_at: env::block_timestamp() + UPGRADE_TIMELOCK_SEC * 10u64.pow(9),
+    };
+    env::storage_write(STAGED_CODE_KEY, code);
+    env::storage_write(STAGED_UPGRADE_KEY, &staged.try_to_vec().unwrap());
+    event::emit::upgrade_stage(&hash.into(), staged.activates_at);
+}
+
+/// Removes the staged code. Called by `migrate()` of the deployed code.
+pub(crate) fn clear_staged_code() {
+    env::storage_remove(STAGED_CODE_KEY);
+    env::storage_remove(STAGED_UPGRADE_KEY);
+}
+
+/// Stages the code passed as raw input. Only can be called by owner.
+/// The contract balance must cover the storage of the code.
+#[no_mangle]
+pub fn stage_code() {
+    env::setup_panic_hook();
+
+    let contract: Contract = env::state_read().expect("Contract is not initialized");
+    contract.assert_owner("stage_code");
+
+    let code = env::input().unwrap_or_default();
+    require!(!code.is_empty(), "The code is empty");
+    stage(&code);
+}
+
+#[near_bindgen]
+impl Contract {
+    /// Deploys the staged code after the timelock and migrates the state.
+    /// `hash` must match the staged code. Only can be called by owner.
+    pub fn commit_upgrade(&mut self, hash: Base58CryptoHash) -> Promise {
+        self.assert_owner("commit_upgrade");
+        let staged = staged_upgrade().unwrap_or_else(|| env::panic_str("No code is staged"));
+        require!(
+            Base58CryptoHash::from(staged.hash) == hash,
+            "The hash doesn't match the staged code"
+        );
+        require!(
+            env::block_timestamp() >= staged.activates_at,
+            "The staged code is timelocked"
+        );
+
+        let code = env::storage_read(STAGED_CODE_KEY).expect("The staged code is missing");
+        event::emit::upgrade_commit(&hash);
+
+        // Batched together to fail upgrade if migration fails.
+        Promise::new(env::current_account_id())
+            .deploy_contract(code)
+            .function_call(
+                MIGRATE_METHOD_NAME.to_string(),
+                vec![],
+                0,
+                env::prepaid_gas() - env::used_gas() - UPDATE_GAS_LEFTOVER,
+            )
+    }
+
+    /// Removes the staged code. Only can be called by owner or guardians.
+    pub fn cancel_staged_code(&mut self) {
+        self.assert_owner_or_guardian("cancel_staged_code");
+        clear_staged_code();
+    }
+
+    pub fn staged_code_hash(&self) -> Option<Base58CryptoHash> {
+        staged_upgrade().map(|staged| staged.hash.into())
+    }
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use super::*;
+    use crate::testing::setup_contract;
+    use near_sdk::test_utils::{accounts, VMContextBuilder};
+    use near_sdk::testing_env;
+
+    const TIMELOCK_NS: u64 = UPGRADE_TIMELOCK_SEC * 1_000_000_000;
+    const CODE: &[u8] = b"\0asm";
+
+    fn setup_staged() -> (VMContextBuilder, Contract) {
+        let (context, contract) = setup_contract(accounts(1));
+        stage(CODE);
+        (context, contract)
+    }
+
+    fn code_hash() -> Base58CryptoHash {
+        let hash: CryptoHash = env::sha256(CODE).try_into().unwrap();
+        hash.into()
+    }
+
+    #[test]
+    fn test_commit_upgrade() {
+        let (mut context, mut contract) = setup_staged();
+        assert_eq!(contract.staged_code_hash(), Some(code_hash()));
+
+        testing_env!(context
+            .block_timestamp(TIMELOCK_NS)
+            .prepaid_gas(Gas(300_000_000_000_000))
+            .build());
+        contract.commit_upgrade(code_hash());
+    }
+
+    #[test]
+    #[should_panic(expected = "The staged code is timelocked")]
+    fn test_commit_upgrade_timelock() {
+        let (_, mut contract) = setup_staged();
+        contract.commit_upgrade(code_hash());
+    }
+
+    #[test]
+    #[should_panic(expected = "The hash doesn't match the staged code")]
+    fn test_commit_upgrade_hash() {
+        let (mut context, mut contract) = setup_staged();
+        testing_env!(context.block_timestamp(TIMELOCK_NS).build());
+        contract.commit_upgrade([0u8; 32].into());
+    }
+
+    #[test]
+    fn test_cancel_staged_code() {
+        let (_, mut contract) = setup_staged();
+        contract.cancel_staged_code();
+        assert!(contract.staged_code_hash().is_none());
+    }
+}
@@ -0,0 +1,253 @@
+//! Vesting schedules of USN grants.
+//!
+//! The owner escrows USN on the contract account for a beneficiary. Nothing is
+//! unlocked before the cliff, then the grant unlocks linearly from `start` to `end`.
+//!
+//! The escrowed total is counted since v14. A state migrated from an older version
+//! has it unsynced until the owner sets it, untracked USN isn't burned meanwhile.
+
+use crate::*;
+
+use near_sdk::require;
+
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(crate = "near_sdk::serde")]
+pub struct VestingSchedule {
+    pub total: U128,
+    pub claimed: U128,
+    /// Timestamps in nanoseconds.
+    pub start: U64,
+    pub cliff: U64,
+    pub end: U64,
+}
+
+impl VestingSchedule {
+    pub fn unlocked(&self, now: Timestamp) -> Balance {
+        if now < self.cliff.0 {
+            0
+        } else if now >= self.end.0 {
+            self.total.0
+        } else {
+            rounding::mul_div(
+                self.total.0,
+                (now - self.start.0) as u128,
+                (self.end.0 - self.start.0) as u128,
+                rounding::VESTING,
+            )
+        }
+    }
+}
+
+#[near_bindgen]
+impl Contract {
+    /// Creates the vesting schedule funded with the owner's USN. Only can be called by owner.
+    #[payable]
+    pub fn create_vesting(
+        &mut self,
+        account_id: AccountId,
+        total: U128,
+        start: U64,
+        cliff: U64,
+        end: U64,
+    ) {
+        assert_one_yocto();
+        self.assert_owner("create_vesting");
+        require!(
+            start.0 <= cliff.0 && cliff.0 <= end.0 && start.0 < end.0,
+            "Invalid vesting period"
+        );
+        require!(
+            self.vesting.get(&account_id).is_none(),
+            "The account already has a vesting schedule"
+        );
+        self.token.internal_transfer(
+            &self.owner_id,
+            &env::current_account_id(),
+            total.into(),
+            Some("Vesting".to_string()),
+        );
+        self.vesting_escrow += total.0;
+        self.vesting.insert(
+            &account_id,
+            &VestingSchedule {
+                total,
+                claimed: U128(0),
+                start,
+                cliff,
+                end,
+            },
+        );
+        event::emit::vesting_create(&account_id, total.into(), start.0, cliff.0, end.0);
+    }
+
+    /// Returns the grant to the owner if the cliff hasn't been reached.
+    /// Only can be called by owner.
+    #[payable]
+    pub fn revoke_vesting(&mut self, account_id: AccountId) {
+        assert_one_yocto();
+        self.assert_owner("revoke_vesting");
+        let schedule = self.vesting_or_panic(&account_id);
+        require!(
+            env::block_timestamp() < schedule.cliff.0,
+            "The vesting cliff has been reached"
+        );
+        self.vesting.remove(&account_id);
+        self.release_vesting_escrow(schedule.total.0);
+        self.token.internal_transfer(
+            &env::current_account_id(),
+            &self.owner_id,
+            schedule.total.0,
+            Some("Vesting revocation".to_string()),
+        );
+        event::emit::vesting_revoke(&account_id, schedule.total.0);
+    }
+
+    /// Transfers the unlocked and not yet claimed USN to the caller.
+    pub fn claim_vested(&mut self) -> U128 {
+        let account_id = env::predecessor_account_id();
+        self.abort_if_pause();
+        self.abort_if_blacklisted(&account_id);
+
+        let mut schedule = self.vesting_or_panic(&account_id);
+        let amount = schedule.unlocked(env::block_timestamp()) - schedule.claimed.0;
+        require!(amount > 0, "Nothing to claim");
+
+        schedule.claimed = (schedule.claimed.0 + amount).into();
+        if schedule.claimed == schedule.total {
+            self.vesting.remove(&account_id);
+        } else {
+            self.vesting.insert(&account_id, &schedule);
+        }
+        self.release_vesting_escrow(amount);
+        self.token.internal_transfer(
+            &env::current_account_id(),
+            &account_id,
+            amount,
+            Some("Vesting".to_string()),
+        );
+        event::emit::vesting_claim(&account_id, amount);
+        amount.into()
+    }
+
+    /// Sets the USN escrowed by all vesting schedules once after the migration
+    /// from a state older than v14. Only can be called by owner.
+    #[payable]
+    pub fn set_vesting_escrow(&mut self, amount: U128) {
+        assert_one_yocto();
+        self.assert_owner("set_vesting_escrow");
+        require!(
+            !self.vesting_escrow_synced,
+            "The vesting escrow is already synced"
+        );
+        require!(
+            self.airdrop_escrow + amount.0 + self.wusn_total_supply
+                <= self
+                    .token
+                    .internal_unwrap_balance_of(&env::current_account_id()),
+            "The vesting escrow exceeds USN of the contract account"
+        );
+        self.vesting_escrow = amount.0;
+        self.vesting_escrow_synced = true;
+    }
+
+    pub fn is_vesting_escrow_synced(&self) -> bool {
+        self.vesting_escrow_synced
+    }
+
+    pub fn vesting_schedule(&self, account_id: AccountId) -> Option<VestingSchedule> {
+        self.vesting.get(&account_id)
+    }
+
+    pub fn vested_amount(&self, account_id: AccountId) -> U128 {
+        self.vesting
+            .get(&account_id)
+            .map_or(0, |schedule| schedule.unlocked(env::block_timestamp()))
+            .into()
+    }
+
+    /// The unsynced escrow may be less than the released amount.
+    fn release_vesting_escrow(&mut self, amount: Balance) {
+        if self.vesting_escrow_synced {
+            self.vesting_escrow -= amount;
+        } else {
+            self.vesting_escrow = self.vesting_escrow.saturating_sub(amount);
+        }
+    }
+
+    fn vesting_or_panic(&self, account_id: &AccountId) -> VestingSchedule {
+        self.vesting
+            .get(account_id)
+            .unwrap_or_else(|| env::panic_str("The account doesn't have a vesting schedule"))
+    }
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use super::*;
+    use crate::testing::setup_contract;
+    use near_sdk::test_utils::{accounts, VMContextBuilder};
+    use near_sdk::{testing_env, ONE_YOCTO};
+
+    fn setup_vesting() -> (VMContextBuilder, Contract) {
+        let (mut context, mut contract) = setup_contract(accounts(1));
+        testing_env!(context.attached_deposit(ONE_YOCTO).build());
+        contract.token.internal_deposit(&accounts(1), 1000);
+        contract.create_vesting(accounts(2), U128(1000), U64(100), U64(200), U64(1100));
+        (context, contract)
+    }
+
+    #[test]
+    fn test_claim_vested() {
+        let (mut context, mut contract) = setup_vesting();
+        assert_eq!(contract.ft_balance_of(accounts(0)), U128(1000));
+
+        testing_env!(context
+            .predecessor_account_id(accounts(2))
+            .block_timestamp(600)
+            .build());
+        assert_eq!(contract.vested_amount(accounts(2)), U128(500));
+        assert_eq!(contract.claim_vested(), U128(500));
+        assert_eq!(contract.ft_balance_of(accounts(2)), U128(500));
+
+        testing_env!(context.block_timestamp(2000).build());
+        assert_eq!(contract.claim_vested(), U128(500));
+        assert_eq!(contract.ft_balance_of(accounts(2)), U128(1000));
+        assert!(contract.vesting_schedule(accounts(2)).is_none());
+    }
+
+    #[test]
+    #[should_panic(expected = "Nothing to claim")]
+    fn test_claim_before_cliff() {
+        let (mut context, mut contract) = setup_vesting();
+
+        testing_env!(context
+            .predecessor_account_id(accounts(2))
+            .block_timestamp(199)
+            .build());
+        contract.claim_vested();
+    }
+
+    #[test]
+    fn test_revoke_vesting() {
+        let (_, mut contract) = setup_vesting();
+        contract.revoke_vesting(accounts(2));
+        assert_eq!(contract.ft_balance_of(accounts(1)), U128(1000));
+        assert!(contract.vesting_schedule(accounts(2)).is_none());
+    }
+
+    #[test]
+    #[should_panic(expected = "The vesting cliff has been reached")]
+    fn test_revoke_vesting_after_cliff() {
+        let (mut context, mut contract) = setup_vesting();
+
+        testing_env!(context.block_timestamp(200).build());
+        contract.revoke_vesting(accounts(2));
+    }
+
+    #[test]
+    #[should_panic(expected = "The vesting escrow is already synced")]
+    fn test_set_synced_vesting_escrow() {
+        let (_, mut contract) = setup_vesting();
+        contract.set_vesting_escrow(U128(1000));
+    }
+}
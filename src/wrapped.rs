@@ -0,0 +1,195 @@
+//! wUSN: USN wrapped under the standard NEP-145 storage accounting.
+//!
+//! Unlike USN, wUSN balances exist only for accounts which paid the storage
+//! deposit, as strict integrations expect. Wrapped USN is held by the contract
+//! account, so the total supply of USN doesn't change.
+//!
+//! This is only the wrapping ledger: wUSN can't be transferred and has no NEP-141
+//! methods or events, since `ft_*` methods of this account belong to USN.
+//! A NEP-141 wUSN token needs its own account on top of this ledger.
+
+use crate::*;
+
+use near_contract_standards::storage_management::StorageBalance;
+use near_sdk::require;
+
+const WUSN_SYMBOL: &str = "wUSN";
+/// 1.25 milliNEAR, covers the storage of a wUSN account.
+const WUSN_STORAGE_DEPOSIT: Balance = 1_250_000_000_000_000_000_000;
+
+#[near_bindgen]
+impl Contract {
+    /// Registers the account for wUSN, the deposit over the required one is refunded.
+    #[payable]
+    pub fn wusn_storage_deposit(&mut self, account_id: Option<AccountId>) -> StorageBalance {
+        self.abort_if_pause();
+        let account_id = account_id.unwrap_or_else(env::predecessor_account_id);
+        let mut refund = env::attached_deposit();
+        if self.wusn_accounts.get(&account_id).is_none() {
+            require!(
+                refund >= WUSN_STORAGE_DEPOSIT,
+                "The attached deposit is less than the minimum storage balance"
+            );
+            self.wusn_accounts.insert(&account_id, &0);
+            refund -= WUSN_STORAGE_DEPOSIT;
+        }
+        if refund > 0 {
+            Promise::new(env::predecessor_account_id()).transfer(refund);
+        }
+        wusn_storage_balance()
+    }
+
+    /// Unregisters the caller with zero wUSN balance and returns the storage deposit.
+    #[payable]
+    pub fn wusn_storage_unregister(&mut self) -> bool {
+        assert_one_yocto();
+        self.abort_if_pause();
+        let account_id = env::predecessor_account_id();
+        match self.wusn_accounts.get(&account_id) {
+            None => false,
+            Some(0) => {
+                self.wusn_accounts.remove(&account_id);
+                Promise::new(account_id).transfer(WUSN_STORAGE_DEPOSIT + 1);
+                true
+            }
+            Some(_) => {
+                env::panic_str("Can't unregister the account with the positive wUSN balance")
+            }
+        }
+    }
+
+    pub fn wusn_storage_balance_of(&self, account_id: AccountId) -> Option<StorageBalance> {
+        self.wusn_accounts
+            .get(&account_id)
+            .map(|_| wusn_storage_balance())
+    }
+
+    /// Wraps `amount` of the caller's USN into wUSN.
+    #[payable]
+    pub fn wrap_usn(&mut self, amount: U128) {
+        assert_one_yocto();
+        self.abort_if_pause();
+        let account_id = env::predecessor_account_id();
+        self.abort_if_blacklisted(&account_id);
+        let balance = self.wusn_balance_or_panic(&account_id);
+
+        self.token.internal_transfer(
+            &account_id,
+            &env::current_account_id(),
+            amount.into(),
+            Some(WUSN_SYMBOL.to_string()),
+        );
+        self.wusn_accounts
+            .insert(&account_id, &(balance + amount.0));
+        self.wusn_total_supply += amount.0;
+        event::emit::wusn_wrap(&account_id, amount.into());
+    }
+
+    /// Unwraps `amount` of the caller's wUSN back into USN.
+    #[payable]
+    pub fn unwrap_usn(&mut self, amount: U128) {
+        assert_one_yocto();
+        self.abort_if_pause();
+        let account_id = env::predecessor_account_id();
+        self.abort_if_blacklisted(&account_id);
+        let balance = self.wusn_balance_or_panic(&account_id);
+        require!(balance >= amount.0, "The account doesn't have enough wUSN");
+
+        self.wusn_accounts
+            .insert(&account_id, &(balance - amount.0));
+        self.wusn_total_supply -= amount.0;
+        self.token.internal_transfer(
+            &env::current_account_id(),
+            &account_id,
+            amount.into(),
+            Some(WUSN_SYMBOL.to_string()),
+        );
+        event::emit::wusn_unwrap(&account_id, amount.into());
+    }
+
+    pub fn wusn_balance_of(&self, account_id: AccountId) -> U128 {
+        self.wusn_accounts.get(&account_id).unwrap_or(0).into()
+    }
+
+    pub fn wusn_total_supply(&self) -> U128 {
+        self.wusn_total_supply.into()
+    }
+
+    pub fn wusn_metadata(&self) -> FungibleTokenMetadata {
+        let mut metadata = self.metadata.get().unwrap();
+        metadata.name = format!("Wrapped {}", metadata.name);
+        metadata.symbol = WUSN_SYMBOL.to_string();
+        metadata
+    }
+
+    fn wusn_balance_or_panic(&self, account_id: &AccountId) -> Balance {
+        self.wusn_accounts
+            .get(account_id)
+            .unwrap_or_else(|| env::panic_str("The account is not registered for wUSN"))
+    }
+}
+
+fn wusn_storage_balance() -> StorageBalance {
+    StorageBalance {
+        total: WUSN_STORAGE_DEPOSIT.into(),
+        available: 0.into(),
+    }
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use super::*;
+    use crate::testing::setup_contract;
+    use near_sdk::test_utils::{accounts, VMContextBuilder};
+    use near_sdk::{testing_env, ONE_YOCTO};
+
+    fn setup_wusn() -> (VMContextBuilder, Contract) {
+        let (mut context, mut contract) = setup_contract(accounts(1));
+        contract.token.internal_deposit(&accounts(2), 1000);
+        testing_env!(context
+            .predecessor_account_id(accounts(2))
+            .attached_deposit(WUSN_STORAGE_DEPOSIT)
+            .build());
+        contract.wusn_storage_deposit(None);
+        testing_env!(context.attached_deposit(ONE_YOCTO).build());
+        (context, contract)
+    }
+
+    #[test]
+    fn test_wrap_unwrap() {
+        let (_, mut contract) = setup_wusn();
+        contract.wrap_usn(U128(600));
+        assert_eq!(contract.ft_balance_of(accounts(2)), U128(400));
+        assert_eq!(contract.ft_balance_of(accounts(0)), U128(600));
+        assert_eq!(contract.wusn_balance_of(accounts(2)), U128(600));
+        assert_eq!(contract.wusn_total_supply(), U128(600));
+
+        contract.unwrap_usn(U128(600));
+        assert_eq!(contract.ft_balance_of(accounts(2)), U128(1000));
+        assert_eq!(contract.wusn_total_supply(), U128(0));
+        assert!(contract.wusn_storage_unregister());
+        assert!(contract.wusn_storage_balance_of(accounts(2)).is_none());
+    }
+
+    #[test]
+    #[should_panic(expected = "The contract is under maintenance")]
+    fn test_storage_deposit_paused() {
+        let (mut context, mut contract) = setup_wusn();
+        testing_env!(context.predecessor_account_id(accounts(1)).build());
+        contract.pause();
+
+        testing_env!(context
+            .predecessor_account_id(accounts(3))
+            .attached_deposit(WUSN_STORAGE_DEPOSIT)
+            .build());
+        contract.wusn_storage_deposit(None);
+    }
+
+    #[test]
+    #[should_panic(expected = "The account is not registered for wUSN")]
+    fn test_wrap_unregistered() {
+        let (mut context, mut contract) = setup_wusn();
+        testing_env!(context.predecessor_account_id(accounts(3)).build());
+        contract.wrap_usn(U128(1));
+    }
+}